@@ -0,0 +1,114 @@
+//! The commands of the command line interpreter that inspect a program
+//! instead of running it through an `Engine`.
+
+use crate::diagnostics::Reporter;
+use crate::engine::front_end;
+use crate::errors::{Error, ValueError};
+use crate::interpreter::Interpreter;
+use crate::models::tokens::Token;
+use crate::native_funcs::builtin_exports;
+use crate::parser::Parser;
+use crate::scanner::Scanner;
+use crate::vm::compiler::Compiler;
+use crate::vm::disassembler::disassemble;
+use crate::vm::serializer::{deserialize, serialize};
+use crate::vm::value::Function;
+use std::fs;
+use std::path::Path;
+use std::rc::Rc;
+
+pub use crate::repl::Repl;
+pub use crate::vm::serializer::is_compiled;
+
+/// A program read from disk, with the reporter its diagnostics go to.
+pub struct Source<'a> {
+    pub file: &'a str,
+    pub text: &'a str,
+    pub reporter: &'a Reporter,
+}
+
+fn scan(source: &Source) -> Result<Vec<Token>, Error> {
+    let (tokens, errors) = Scanner::new(source.file, source.text).scan_tokens();
+    if !errors.is_empty() {
+        return Err(Error::Compile(errors));
+    }
+    Ok(tokens)
+}
+
+/// Prints every token, even when some characters could not be scanned.
+pub fn tokenize(source: &Source) -> Result<(), Error> {
+    let (tokens, errors) = Scanner::new(source.file, source.text).scan_tokens();
+    for token in tokens {
+        println!("{token}");
+    }
+    if !errors.is_empty() {
+        return Err(Error::Compile(errors));
+    }
+    Ok(())
+}
+
+/// Prints the syntax tree of one expression.
+pub fn parse(source: &Source) -> Result<(), Error> {
+    let tokens = scan(source)?;
+    let mut parser = Parser::new(&tokens);
+    let expr = parser
+        .parse_expression()
+        .map_err(|error| Error::Compile(vec![error]))?;
+    println!("{expr}");
+    Ok(())
+}
+
+/// Prints the value of one expression.
+pub fn evaluate(source: &Source) -> Result<(), Error> {
+    let tokens = scan(source)?;
+    let mut parser = Parser::new(&tokens);
+    let expr = parser
+        .parse_expression()
+        .map_err(|error| Error::Compile(vec![error]))?;
+
+    let mut interpreter = Interpreter::new();
+    match interpreter.evaluate(&expr) {
+        Ok(value) => println!("{value}"),
+        Err(ValueError::Error(error)) => return Err(Error::runtime(error)),
+        Err(ValueError::Return(_) | ValueError::Break | ValueError::Continue) => {}
+    }
+    Ok(())
+}
+
+/// Writes the bytecode of a program next to its source, or to `output`.
+pub fn compile(source: &Source, output: Option<&str>) -> Result<(), Error> {
+    let function = compile_to_function(source)?;
+    let path = match output {
+        Some(path) => Path::new(path).to_path_buf(),
+        None => Path::new(source.file).with_extension("loxc"),
+    };
+
+    fs::write(&path, serialize(&function)).map_err(|error| Error::Write(path, error))
+}
+
+/// Prints the bytecode of a program, annotated with its source lines.
+pub fn disasm(source: &Source) -> Result<(), Error> {
+    let function = compile_to_function(source)?;
+    print!("{}", disassemble(&function, Some(source.text)));
+    Ok(())
+}
+
+/// Prints the bytecode of a `.loxc` file.
+pub fn disasm_compiled(bytes: &[u8]) -> Result<(), Error> {
+    let function = deserialize(bytes).map_err(Error::Bytecode)?;
+    print!("{}", disassemble(&function, None));
+    Ok(())
+}
+
+fn compile_to_function(source: &Source) -> Result<Rc<Function>, Error> {
+    let program = front_end(
+        source.file,
+        source.text,
+        source.reporter,
+        &Rc::new(builtin_exports()),
+        true,
+    )?;
+    Compiler::with_modules(program.modules)
+        .compile(&program.stmts)
+        .map_err(Error::Compile)
+}
@@ -0,0 +1,348 @@
+use crate::errors::Error;
+use crate::models::span::Span;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter, Write};
+use std::rc::Rc;
+
+/// Category of a runtime error, which scripts see as the `kind` of the
+/// errors they catch.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ErrorKind {
+    /// Compile errors, failing native functions and uncaught `throw`s.
+    #[default]
+    Error,
+    /// An operand, callee or receiver of the wrong type.
+    Type,
+    /// An undefined variable.
+    Name,
+    /// An undefined property or method.
+    Property,
+    /// A call with the wrong number of arguments.
+    Arity,
+    /// A list index outside of the list.
+    Index,
+    /// A map key that is not in the map.
+    Key,
+    /// Too many nested calls.
+    StackOverflow,
+    /// More steps than `Limits::max_steps`.
+    StepLimit,
+    /// A run longer than `Limits::timeout`.
+    Timeout,
+    /// More memory than `Limits::max_memory`.
+    MemoryLimit,
+    /// A call of `exit`, which ends the program with the status rather than
+    /// failing. Scripts cannot catch it.
+    Exit(i32),
+}
+
+impl ErrorKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Error => "Error",
+            ErrorKind::Type => "TypeError",
+            ErrorKind::Name => "NameError",
+            ErrorKind::Property => "PropertyError",
+            ErrorKind::Arity => "ArityError",
+            ErrorKind::Index => "IndexError",
+            ErrorKind::Key => "KeyError",
+            ErrorKind::StackOverflow => "StackOverflowError",
+            ErrorKind::StepLimit => "StepLimitError",
+            ErrorKind::Timeout => "TimeoutError",
+            ErrorKind::MemoryLimit => "MemoryError",
+            ErrorKind::Exit(_) => "Exit",
+        }
+    }
+
+    /// Whether `try` statements may catch the error. Exceeded limits and
+    /// `exit` end the run however the script handles errors.
+    pub fn catchable(&self) -> bool {
+        !matches!(
+            self,
+            ErrorKind::StepLimit | ErrorKind::Timeout | ErrorKind::MemoryLimit | ErrorKind::Exit(_)
+        )
+    }
+}
+
+/// A runtime error message with its kind, for helpers that do not know
+/// where in the program the error happens.
+pub type Failure = (ErrorKind, &'static str);
+
+/// An error found in a program, from scanning through running it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub message: String,
+    pub kind: ErrorKind,
+    pub span: Span,
+    /// Functions a runtime error unwound through, innermost first, each with
+    /// the span of the call that entered it.
+    pub trace: Vec<(String, Span)>,
+}
+
+impl Diagnostic {
+    pub fn new(span: &Span, message: impl Into<String>) -> Self {
+        Diagnostic {
+            message: message.into(),
+            kind: ErrorKind::Error,
+            span: span.clone(),
+            trace: Vec::new(),
+        }
+    }
+
+    /// A runtime error of the given kind.
+    pub fn runtime(span: &Span, kind: ErrorKind, message: impl Into<String>) -> Self {
+        Diagnostic {
+            kind,
+            ..Diagnostic::new(span, message)
+        }
+    }
+
+    pub fn failure(span: &Span, (kind, message): Failure) -> Self {
+        Diagnostic::runtime(span, kind, message)
+    }
+
+    /// Lines of the stack trace: every frame shows the line it was executing
+    /// when the error happened, ending with the top-level script. Traces
+    /// longer than twice `TRACE_EDGE` frames, such as those of a stack
+    /// overflow, only show their first and last `TRACE_EDGE` frames.
+    fn trace_lines(&self) -> Vec<String> {
+        if self.trace.is_empty() {
+            return Vec::new();
+        }
+
+        // Every frame but the first is executing the call of the one before.
+        let line = |frame: usize| match frame {
+            0 => self.span.line,
+            _ => self.trace[frame - 1].1.line,
+        };
+        let frame_line = |frame: usize| match self.trace.get(frame) {
+            Some((name, _)) if name.starts_with(MODULE_FRAME) => {
+                format!("[line {}] in {name}", line(frame))
+            }
+            Some((name, _)) => format!("[line {}] in {name}()", line(frame)),
+            None => format!("[line {}] in script", line(frame)),
+        };
+
+        let frames = self.trace.len() + 1;
+        if frames <= 2 * TRACE_EDGE + 1 {
+            return (0..frames).map(frame_line).collect();
+        }
+        let mut lines: Vec<String> = (0..TRACE_EDGE).map(frame_line).collect();
+        lines.push(format!("... {} more frames", frames - 2 * TRACE_EDGE));
+        lines.extend((frames - TRACE_EDGE..frames).map(frame_line));
+        lines
+    }
+}
+
+const MODULE_FRAME: &str = "module ";
+
+/// Frames shown at each end of a long stack trace.
+const TRACE_EDGE: usize = 10;
+
+/// The name stack traces show for the top-level code of the module `file`.
+pub fn module_frame(file: &str) -> String {
+    format!("{MODULE_FRAME}{file}")
+}
+
+/// The message for calling a function that takes `arity` arguments, or at
+/// least that many when `variadic`, with `count` arguments.
+pub fn arity_message(arity: usize, variadic: bool, count: usize) -> String {
+    let at_least = if variadic { "at least " } else { "" };
+    let arguments = if arity == 1 { "argument" } else { "arguments" };
+    format!("Expected {at_least}{arity} {arguments}, but got {count}.")
+}
+
+/// The one-line `[line N] message` form, followed by the stack trace.
+impl Display for Diagnostic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "[line {}] {}", self.span.line, self.message)?;
+        for line in self.trace_lines() {
+            write!(f, "\n{line}")?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ErrorFormat {
+    /// The message followed by the source line with the span underlined.
+    #[default]
+    Human,
+    /// Only the `[line N] message` line.
+    Short,
+    /// One JSON object per diagnostic, for editors and other tools.
+    Json,
+}
+
+impl ErrorFormat {
+    pub fn parse(name: &str) -> Option<ErrorFormat> {
+        match name {
+            "human" => Some(ErrorFormat::Human),
+            "short" => Some(ErrorFormat::Short),
+            "json" => Some(ErrorFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Renders diagnostics against the sources they point into.
+#[derive(Default)]
+pub struct Reporter {
+    pub format: ErrorFormat,
+    /// Text of every file diagnostics may point into; modules are added
+    /// while the program is being resolved.
+    sources: RefCell<HashMap<Rc<str>, Rc<str>>>,
+}
+
+impl Reporter {
+    pub fn new(format: ErrorFormat) -> Self {
+        Reporter {
+            format,
+            sources: RefCell::default(),
+        }
+    }
+
+    pub fn add_source(&self, file: &str, source: &str) {
+        self.sources
+            .borrow_mut()
+            .insert(Rc::from(file), Rc::from(source));
+    }
+
+    fn source(&self, file: &str) -> Option<Rc<str>> {
+        self.sources.borrow().get(file).cloned()
+    }
+
+    pub fn render(&self, diagnostic: &Diagnostic) -> String {
+        match self.format {
+            ErrorFormat::Human => self.human(diagnostic),
+            ErrorFormat::Short => diagnostic.to_string(),
+            ErrorFormat::Json => json(diagnostic),
+        }
+    }
+
+    /// Renders every diagnostic of `error`, or its message when it has none.
+    pub fn render_error(&self, error: &Error) -> String {
+        match error {
+            Error::Compile(diagnostics) => diagnostics
+                .iter()
+                .map(|diagnostic| self.render(diagnostic))
+                .collect::<Vec<_>>()
+                .join("\n"),
+            Error::Runtime(diagnostic) => self.render(diagnostic),
+            error => error.to_string(),
+        }
+    }
+
+    /// Prints every diagnostic to stderr.
+    pub fn report(&self, diagnostics: &[Diagnostic]) {
+        for diagnostic in diagnostics {
+            eprintln!("{}", self.render(diagnostic));
+        }
+    }
+
+    fn human(&self, diagnostic: &Diagnostic) -> String {
+        let span = &diagnostic.span;
+        let mut out = format!("[line {}] {}", span.line, diagnostic.message);
+
+        if let Some(text) = self.line_text(span) {
+            let gutter = " ".repeat(span.line.to_string().len());
+            let prefix: String = text
+                .chars()
+                .take(span.column.saturating_sub(1))
+                .map(|c| if c == '\t' { '\t' } else { ' ' })
+                .collect();
+            let underline = "^".repeat(self.underline_width(span, &text));
+
+            write!(
+                out,
+                "\n{gutter}--> {}:{}:{}",
+                span.file, span.line, span.column
+            )
+            .unwrap();
+            write!(out, "\n{gutter} |").unwrap();
+            write!(out, "\n{} | {text}", span.line).unwrap();
+            write!(out, "\n{gutter} | {prefix}{underline}").unwrap();
+        }
+
+        for line in diagnostic.trace_lines() {
+            write!(out, "\n{line}").unwrap();
+        }
+        out
+    }
+
+    /// The text of the line `span` starts on, if its source is known.
+    fn line_text(&self, span: &Span) -> Option<String> {
+        if span.line == 0 {
+            return None;
+        }
+        let source = self.source(&span.file)?;
+        source
+            .split('\n')
+            .nth(span.line - 1)
+            .map(|line| line.trim_end_matches('\r').to_string())
+    }
+
+    /// Number of characters of `span` on its first line, at least one so that
+    /// empty spans such as the end of the file still get a caret.
+    fn underline_width(&self, span: &Span, text: &str) -> usize {
+        let Some(source) = self.source(&span.file) else {
+            return 1;
+        };
+        let end = (span.start + span.len).min(source.len());
+        let covered = source.get(span.start..end).unwrap_or_default();
+        let on_line = covered.split('\n').next().unwrap_or_default();
+        let remaining = text.chars().count().saturating_sub(span.column - 1);
+        on_line.chars().count().min(remaining).max(1)
+    }
+}
+
+fn json(diagnostic: &Diagnostic) -> String {
+    let trace: Vec<String> = diagnostic
+        .trace
+        .iter()
+        .map(|(function, call)| {
+            format!(
+                "{{\"function\":{},\"call\":{}}}",
+                json_string(function),
+                json_span(call)
+            )
+        })
+        .collect();
+
+    format!(
+        "{{\"severity\":\"error\",\"message\":{},\"span\":{},\"trace\":[{}]}}",
+        json_string(&diagnostic.message),
+        json_span(&diagnostic.span),
+        trace.join(",")
+    )
+}
+
+fn json_span(span: &Span) -> String {
+    format!(
+        "{{\"file\":{},\"line\":{},\"column\":{},\"start\":{},\"length\":{}}}",
+        json_string(&span.file),
+        span.line,
+        span.column,
+        span.start,
+        span.len
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,387 @@
+use crate::diagnostics::{ErrorFormat, Reporter};
+use crate::errors::{Error, ValueError};
+use crate::host::{IntoHostFunction, NativeModule};
+use crate::interpreter::Interpreter;
+use crate::limits::Limits;
+use crate::models::span::Span;
+use crate::models::statements::Stmt;
+use crate::modules::{ModuleLoader, Modules, NativeExports};
+use crate::native_funcs::builtin_exports;
+use crate::native_funcs::io::fs_functions;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use crate::value::Value;
+use crate::vm::compiler::Compiler;
+use crate::vm::machine::Vm;
+use crate::vm::serializer::{deserialize, is_compiled};
+use crate::vm::value::Function;
+use std::collections::HashMap;
+use std::path::Path;
+use std::rc::Rc;
+use std::{fs, io};
+
+/// Which implementation runs the programs of an `Engine`.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum Backend {
+    /// The tree-walking interpreter.
+    #[default]
+    Tree,
+    /// The bytecode compiler and virtual machine.
+    Vm,
+}
+
+/// Garbage collector settings.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct GcOptions {
+    /// Collect before every statement, or every instruction of the virtual
+    /// machine.
+    pub stress: bool,
+    /// Report every collection on stderr.
+    pub log: bool,
+}
+
+enum Runtime {
+    Tree(Interpreter),
+    Vm(Vm),
+}
+
+/// A Lox interpreter embedded in a Rust program. The globals defined by one
+/// `eval` or `run_file` stay visible to the next one, and to the host
+/// through `get_global`, `set_global` and `call_function`.
+pub struct Engine {
+    runtime: Runtime,
+    /// Renders errors with the text of every file the engine has read.
+    reporter: Reporter,
+    /// The functions of the registered native modules, for resolving
+    /// imports of them.
+    natives: Rc<NativeExports>,
+    /// The sources `eval` ran, each reported as its own file.
+    evals: usize,
+    /// Whether programs may use the filesystem, through natives and by
+    /// importing module files.
+    fs_access: bool,
+}
+
+impl Default for Engine {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Engine {
+    /// An engine running programs on the tree-walking interpreter.
+    pub fn new() -> Self {
+        Self::with_backend(Backend::Tree)
+    }
+
+    pub fn with_backend(backend: Backend) -> Self {
+        let runtime = match backend {
+            Backend::Tree => Runtime::Tree(Interpreter::new()),
+            Backend::Vm => Runtime::Vm(Vm::new()),
+        };
+        Engine {
+            runtime,
+            reporter: Reporter::default(),
+            natives: Rc::new(builtin_exports()),
+            evals: 0,
+            fs_access: true,
+        }
+    }
+
+    pub fn backend(&self) -> Backend {
+        match self.runtime {
+            Runtime::Tree(_) => Backend::Tree,
+            Runtime::Vm(_) => Backend::Vm,
+        }
+    }
+
+    /// Configures the garbage collector.
+    pub fn set_gc_options(&mut self, options: GcOptions) {
+        let heap = match &mut self.runtime {
+            Runtime::Tree(interpreter) => &mut interpreter.heap,
+            Runtime::Vm(vm) => &mut vm.heap,
+        };
+        heap.stress = options.stress;
+        heap.log = options.log;
+    }
+
+    /// Limits what each run of a program, such as an `eval` or a `call`,
+    /// may use.
+    pub fn set_limits(&mut self, limits: Limits) {
+        match &mut self.runtime {
+            Runtime::Tree(interpreter) => interpreter.set_limits(limits),
+            Runtime::Vm(vm) => vm.set_limits(limits),
+        }
+    }
+
+    /// Sets the format `render` shows errors in.
+    pub fn set_error_format(&mut self, format: ErrorFormat) {
+        self.reporter.format = format;
+    }
+
+    /// Renders `error` like the command line interpreter reports it.
+    pub fn render(&self, error: &Error) -> String {
+        self.reporter.render_error(error)
+    }
+
+    /// Defines the global function `name`, which runs `function`. Its
+    /// parameters and return value are converted from and to script
+    /// values; a script passing the wrong number of arguments, or one that
+    /// does not convert, gets a runtime error. The function is also a
+    /// global of every module imported afterwards.
+    ///
+    /// ```
+    /// use interpreter_starter_rust::{Engine, Rest, Value};
+    ///
+    /// let mut engine = Engine::new();
+    /// engine.register("add", |a: f64, b: f64| a + b);
+    /// engine.register("sum", |values: Rest<f64>| values.0.iter().sum::<f64>());
+    /// assert_eq!(engine.eval("add(1, sum(2, 3, 4));").unwrap(), Value::Number(10.0));
+    /// ```
+    pub fn register<Args>(&mut self, name: &str, function: impl IntoHostFunction<Args>) {
+        let function = function.into_host_function(name);
+        match &mut self.runtime {
+            Runtime::Tree(interpreter) => interpreter.define_native(function),
+            Runtime::Vm(vm) => vm.define_native(function),
+        }
+    }
+
+    /// Defines or removes the natives that read and write files, such as
+    /// `read_file` and `list_dir`, and allows or forbids importing module
+    /// files; native modules stay importable. Scripts have filesystem access
+    /// unless disabled, which `--no-fs` does for untrusted ones.
+    pub fn set_fs_access(&mut self, allowed: bool) {
+        self.fs_access = allowed;
+        for function in fs_functions() {
+            match (&mut self.runtime, allowed) {
+                (Runtime::Tree(interpreter), true) => interpreter.define_native(function),
+                (Runtime::Tree(interpreter), false) => interpreter.undefine_native(&function.name),
+                (Runtime::Vm(vm), true) => vm.define_native(function),
+                (Runtime::Vm(vm), false) => vm.undefine_native(&function.name),
+            }
+        }
+    }
+
+    /// Makes `module` importable by its name, like a module file:
+    /// `import "name" as m;` or `from "name" import f;`. A native module
+    /// shadows any file of the same name.
+    pub fn register_module(&mut self, module: NativeModule) {
+        Rc::make_mut(&mut self.natives).insert(module.name.clone(), module.exports());
+        match &mut self.runtime {
+            Runtime::Tree(interpreter) => interpreter.add_native_module(module),
+            Runtime::Vm(vm) => vm.add_native_module(module),
+        }
+    }
+
+    /// Runs `source` as a program. A program made of a single expression
+    /// statement returns the value of the expression; any other returns
+    /// `Value::Nil`. Modules are looked up from the current directory.
+    /// Diagnostics name the source of the nth `eval` `<eval-n>`.
+    pub fn eval(&mut self, source: &str) -> Result<Value, Error> {
+        self.evals += 1;
+        let file = format!("<eval-{}>", self.evals);
+        self.run_source(&file, source)
+    }
+
+    /// Runs the program in the file at `path`. A compiled `.loxc` file can
+    /// only run on the virtual machine.
+    pub fn run_file(&mut self, path: impl AsRef<Path>) -> Result<(), Error> {
+        let path = path.as_ref();
+        let bytes = fs::read(path).map_err(|error| Error::Read(path.to_path_buf(), error))?;
+
+        if is_compiled(&bytes) {
+            let function = deserialize(&bytes).map_err(Error::Bytecode)?;
+            let Runtime::Vm(vm) = &mut self.runtime else {
+                let msg = "Compiled files can only run on the vm backend.";
+                return Err(Error::Bytecode(msg.to_string()));
+            };
+            vm.restart_meter();
+            return execute(vm, function).map(|_| ());
+        }
+
+        let source = String::from_utf8(bytes).map_err(|error| {
+            let error = io::Error::new(io::ErrorKind::InvalidData, error);
+            Error::Read(path.to_path_buf(), error)
+        })?;
+        self.run_source(&path.to_string_lossy(), &source)
+            .map(|_| ())
+    }
+
+    /// The value of the global variable `name`, if it is defined.
+    pub fn get_global(&self, name: &str) -> Option<Value> {
+        match &self.runtime {
+            Runtime::Tree(interpreter) => {
+                let globals = interpreter.globals.borrow();
+                globals.values.get(name).map(Value::from_tree)
+            }
+            Runtime::Vm(vm) => vm.globals().borrow().get(name).map(Value::from_vm),
+        }
+    }
+
+    /// Defines the global variable `name`, or replaces its value.
+    pub fn set_global(&mut self, name: &str, value: impl Into<Value>) -> Result<(), Error> {
+        let value = value.into();
+        match &mut self.runtime {
+            Runtime::Tree(interpreter) => {
+                let value = value.to_tree(&mut interpreter.heap)?;
+                interpreter
+                    .globals
+                    .borrow_mut()
+                    .define(name.to_string(), value);
+            }
+            Runtime::Vm(vm) => {
+                let value = value.to_vm(&mut vm.heap)?;
+                vm.globals().borrow_mut().insert(Rc::from(name), value);
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls the function or class held by the global variable `name`.
+    pub fn call_function(&mut self, name: &str, args: &[Value]) -> Result<Value, Error> {
+        let Some(callee) = self.get_global(name) else {
+            return Err(Error::UndefinedGlobal(name.to_string()));
+        };
+        self.call(&callee, args)
+    }
+
+    /// Calls `callee`, such as a function a script returned.
+    pub fn call(&mut self, callee: &Value, args: &[Value]) -> Result<Value, Error> {
+        self.restart_meter();
+        match &mut self.runtime {
+            Runtime::Tree(interpreter) => {
+                let callee = callee.to_tree(&mut interpreter.heap)?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.to_tree(&mut interpreter.heap))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match interpreter.call_value(&callee, &args, &Span::default()) {
+                    Ok(value) => Ok(Value::from_tree(&value)),
+                    Err(ValueError::Error(error)) => {
+                        Err(Error::runtime(interpreter.stack_trace(error)))
+                    }
+                    Err(ValueError::Return(_) | ValueError::Break | ValueError::Continue) => {
+                        Ok(Value::Nil)
+                    }
+                }
+            }
+            Runtime::Vm(vm) => {
+                let callee = callee.to_vm(&mut vm.heap)?;
+                let args = args
+                    .iter()
+                    .map(|arg| arg.to_vm(&mut vm.heap))
+                    .collect::<Result<Vec<_>, _>>()?;
+                match vm.call_from_host(&callee, &args) {
+                    Ok(value) => Ok(Value::from_vm(&value)),
+                    Err(error) => Err(Error::runtime(error)),
+                }
+            }
+        }
+    }
+
+    fn run_source(&mut self, file: &str, source: &str) -> Result<Value, Error> {
+        self.reporter.add_source(file, source);
+        let Program {
+            stmts,
+            locals,
+            modules,
+        } = front_end(file, source, &self.reporter, &self.natives, self.fs_access)?;
+        self.restart_meter();
+
+        match &mut self.runtime {
+            Runtime::Tree(interpreter) => {
+                interpreter.add_locals(locals);
+                interpreter.add_modules(modules);
+                if let [Stmt::Expression(expr)] = stmts.as_slice() {
+                    return match interpreter.evaluate(expr) {
+                        Ok(value) => Ok(Value::from_tree(&value)),
+                        Err(ValueError::Error(error)) => {
+                            Err(Error::runtime(interpreter.stack_trace(error)))
+                        }
+                        Err(ValueError::Return(_) | ValueError::Break | ValueError::Continue) => {
+                            Ok(Value::Nil)
+                        }
+                    };
+                }
+
+                interpreter.interpret(&stmts);
+                match interpreter.errors.drain(..).next() {
+                    Some(error) => Err(Error::runtime(error)),
+                    None => Ok(Value::Nil),
+                }
+            }
+            Runtime::Vm(vm) => {
+                let compiler = Compiler::with_modules(modules);
+                let function = match stmts.as_slice() {
+                    [Stmt::Expression(expr)] => compiler.compile_expression(expr),
+                    stmts => compiler.compile(stmts),
+                };
+                execute(vm, function.map_err(Error::Compile)?)
+            }
+        }
+    }
+
+    fn restart_meter(&mut self) {
+        match &mut self.runtime {
+            Runtime::Tree(interpreter) => interpreter.restart_meter(),
+            Runtime::Vm(vm) => vm.restart_meter(),
+        }
+    }
+}
+
+fn execute(vm: &mut Vm, function: Rc<Function>) -> Result<Value, Error> {
+    let result = vm.interpret(function);
+    match vm.errors.drain(..).next() {
+        Some(error) => Err(Error::runtime(error)),
+        None => Ok(Value::from_vm(&result)),
+    }
+}
+
+/// A program ready for either backend.
+pub(crate) struct Program {
+    pub stmts: Vec<Stmt>,
+    /// Scope depths of the local variables, for the tree-walking interpreter.
+    pub locals: HashMap<usize, usize>,
+    pub modules: Modules,
+}
+
+/// Scans, parses and resolves a program, stopping at the first phase that
+/// reports errors. The text of every module it imports is added to
+/// `reporter`; `natives` are the native modules it can import, and it can
+/// import module files only with `fs_access`.
+pub(crate) fn front_end(
+    file: &str,
+    source: &str,
+    reporter: &Reporter,
+    natives: &Rc<NativeExports>,
+    fs_access: bool,
+) -> Result<Program, Error> {
+    let (tokens, errors) = Scanner::new(file, source).scan_tokens();
+    if !errors.is_empty() {
+        return Err(Error::Compile(errors));
+    }
+
+    let mut parser = Parser::new(&tokens);
+    parser.parse();
+    if !parser.errors.is_empty() {
+        return Err(Error::Compile(parser.errors));
+    }
+
+    let mut loader = ModuleLoader::with_natives(Rc::clone(natives));
+    loader.no_files = !fs_access;
+    let mut resolver = Resolver::with_loader(loader);
+    resolver.resolve(&parser.stmts);
+    for (file, text) in &resolver.loader.sources {
+        reporter.add_source(file, text);
+    }
+    if !resolver.errors.is_empty() {
+        return Err(Error::Compile(resolver.errors));
+    }
+
+    Ok(Program {
+        stmts: parser.stmts,
+        locals: resolver.locals,
+        modules: resolver.loader.modules,
+    })
+}
@@ -1,3 +1,5 @@
+use crate::diagnostics::{Diagnostic, ErrorKind};
+use crate::gc::{Trace, Tracer};
 use crate::models::values::Value;
 use std::cell::RefCell;
 
@@ -12,10 +14,12 @@ pub struct Environment {
     pub values: HashMap<String, Value>,
 }
 
-impl<'a> Display for Environment {
+impl Display for Environment {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        for (key, value) in &self.values {
-            writeln!(f, "{key}={value}")?;
+        let mut keys: Vec<&String> = self.values.keys().collect();
+        keys.sort();
+        for key in keys {
+            writeln!(f, "{key}={}", self.values[key])?;
         }
         Ok(())
     }
@@ -33,30 +37,87 @@ impl Environment {
         self.values.insert(name, value);
     }
 
-    pub fn assign(&mut self, token: &Token, value: Value) -> Result<Value, String> {
+    pub fn assign(&mut self, token: &Token, value: Value) -> Result<Value, Diagnostic> {
         if self.values.contains_key(&token.name) {
             self.values.insert(token.name.clone(), value.clone());
             Ok(value)
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow_mut().assign(token, value)
         } else {
-            Err(format!(
-                "[line {}] Undefined variable '{}'.",
-                token.line_number, token.name
-            ))
+            Err(undefined(token))
         }
     }
 
-    pub fn get(&self, token: &Token) -> Result<Value, String> {
+    pub fn get(&self, token: &Token) -> Result<Value, Diagnostic> {
         if let Some(value) = self.values.get(token.name.as_str()) {
             Ok(value.clone())
         } else if let Some(enclosing) = &self.enclosing {
             enclosing.borrow().get(token)
         } else {
-            Err(format!(
-                "[line {}] Undefined variable '{}'.",
-                token.line_number, token.name
-            ))
+            Err(undefined(token))
         }
     }
+
+    /// Reads `token` from the environment exactly `distance` hops up the chain.
+    pub fn get_at(&self, distance: usize, token: &Token) -> Result<Value, Diagnostic> {
+        if distance == 0 {
+            return self
+                .values
+                .get(&token.name)
+                .cloned()
+                .ok_or_else(|| undefined(token));
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow().get_at(distance - 1, token),
+            None => Err(undefined(token)),
+        }
+    }
+
+    /// Assigns `token` in the environment exactly `distance` hops up the chain.
+    pub fn assign_at(
+        &mut self,
+        distance: usize,
+        token: &Token,
+        value: Value,
+    ) -> Result<Value, Diagnostic> {
+        if distance == 0 {
+            self.values.insert(token.name.clone(), value.clone());
+            return Ok(value);
+        }
+
+        match &self.enclosing {
+            Some(enclosing) => enclosing.borrow_mut().assign_at(distance - 1, token, value),
+            None => Err(undefined(token)),
+        }
+    }
+}
+
+fn undefined(token: &Token) -> Diagnostic {
+    let msg = format!("Undefined variable '{}'.", token.name);
+    Diagnostic::runtime(&token.span, ErrorKind::Name, msg)
+}
+
+impl Trace for RefCell<Environment> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(env) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        if let Some(enclosing) = &env.enclosing {
+            tracer.object(enclosing);
+        }
+        for value in env.values.values() {
+            tracer.value(value);
+        }
+    }
+
+    fn clear(&self) {
+        let (enclosing, values) = match self.try_borrow_mut() {
+            Ok(mut env) => (env.enclosing.take(), std::mem::take(&mut env.values)),
+            Err(_) => return,
+        };
+        drop(enclosing);
+        drop(values);
+    }
 }
@@ -1,17 +1,38 @@
+use crate::diagnostics::{Diagnostic, ErrorKind};
 use crate::models::values::Value;
-use std::error::Error;
 use std::fmt::{Display, Formatter};
+use std::io;
+use std::path::PathBuf;
 
-#[derive(Debug)]
+/// Process exit codes, following the `sysexits.h` values used by the book.
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum ExitCode {
+    /// The command line is malformed: unknown command or missing file name.
+    Usage = 64,
+    /// The source was rejected before running: scanning, parsing or resolving.
     ExitError = 65,
+    /// The program was valid but failed while being evaluated or run.
     RuntimeError = 70,
+    /// The source file could not be read.
+    IoError = 74,
+    /// The program nested calls deeper than its call depth limit.
+    StackOverflow = 80,
+    /// The program ran more steps than its step limit.
+    StepLimit = 81,
+    /// The program ran longer than its timeout.
+    Timeout = 82,
+    /// The program allocated more memory than its memory limit.
+    MemoryLimit = 83,
 }
 
+/// Why the execution of a statement stopped early. Only `Error` is a
+/// failure; the others unwind to the function call or loop that handles them.
 #[derive(Debug)]
 pub enum ValueError {
-    Error(String),
+    Error(Diagnostic),
     Return(Value),
+    Break,
+    Continue,
 }
 
 impl Display for ValueError {
@@ -20,4 +41,100 @@ impl Display for ValueError {
     }
 }
 
-impl Error for ValueError {}
+impl std::error::Error for ValueError {}
+
+/// Why the library could not prepare or run a program.
+#[derive(Debug)]
+pub enum Error {
+    /// The source was rejected before running: scanner, parser, resolver or
+    /// compiler errors.
+    Compile(Vec<Diagnostic>),
+    /// The program failed while running.
+    Runtime(Diagnostic),
+    /// A `.loxc` file is corrupt, was written by another format version or
+    /// cannot run on the chosen backend.
+    Bytecode(String),
+    /// A file could not be read; invalid UTF-8 is reported as
+    /// `io::ErrorKind::InvalidData`.
+    Read(PathBuf, io::Error),
+    /// A file could not be written.
+    Write(PathBuf, io::Error),
+    /// A value could not be converted between Rust and the scripts.
+    Conversion(String),
+    /// The host called a function through a global variable that is not
+    /// defined.
+    UndefinedGlobal(String),
+    /// The program called `exit` with the status; not a failure.
+    Exit(i32),
+}
+
+impl Error {
+    /// A runtime error, or the `Exit` a call of `exit` unwinds as one.
+    pub(crate) fn runtime(diagnostic: Diagnostic) -> Self {
+        match diagnostic.kind {
+            ErrorKind::Exit(status) => Error::Exit(status),
+            _ => Error::Runtime(diagnostic),
+        }
+    }
+
+    /// The exit code the command line interpreter stops with on this error.
+    /// An `Exit` stops with its own status instead, which `status` gives.
+    pub fn exit_code(&self) -> ExitCode {
+        match self {
+            Error::Compile(_) | Error::Bytecode(_) => ExitCode::ExitError,
+            Error::Runtime(diagnostic) => match diagnostic.kind {
+                ErrorKind::StackOverflow => ExitCode::StackOverflow,
+                ErrorKind::StepLimit => ExitCode::StepLimit,
+                ErrorKind::Timeout => ExitCode::Timeout,
+                ErrorKind::MemoryLimit => ExitCode::MemoryLimit,
+                _ => ExitCode::RuntimeError,
+            },
+            Error::Conversion(_) | Error::UndefinedGlobal(_) | Error::Exit(_) => {
+                ExitCode::RuntimeError
+            }
+            Error::Read(..) | Error::Write(..) => ExitCode::IoError,
+        }
+    }
+
+    /// The process status the command line interpreter stops with.
+    pub fn status(&self) -> i32 {
+        match self {
+            Error::Exit(status) => *status,
+            error => error.exit_code() as i32,
+        }
+    }
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Compile(diagnostics) => {
+                for (i, diagnostic) in diagnostics.iter().enumerate() {
+                    if i > 0 {
+                        writeln!(f)?;
+                    }
+                    write!(f, "{diagnostic}")?;
+                }
+                Ok(())
+            }
+            Error::Runtime(diagnostic) => write!(f, "{diagnostic}"),
+            Error::Bytecode(message) | Error::Conversion(message) => write!(f, "{message}"),
+            Error::UndefinedGlobal(name) => write!(f, "Undefined variable '{name}'."),
+            Error::Read(path, error) if error.kind() == io::ErrorKind::InvalidData => {
+                write!(f, "Failed to read file {} as UTF-8", path.display())
+            }
+            Error::Read(path, _) => write!(f, "Failed to read file {}", path.display()),
+            Error::Write(path, _) => write!(f, "Failed to write file {}", path.display()),
+            Error::Exit(status) => write!(f, "Exited with status {status}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::Read(_, error) | Error::Write(_, error) => Some(error),
+            _ => None,
+        }
+    }
+}
@@ -0,0 +1,203 @@
+use crate::models::values::Value;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::{Rc, Weak};
+
+/// Collections start once this many objects are tracked.
+const INITIAL_THRESHOLD: usize = 1024;
+/// After a collection the threshold grows to this multiple of the survivors.
+const GROWTH_FACTOR: usize = 2;
+
+/// A heap object that can take part in a reference cycle: environments,
+/// functions, classes, instances, lists and maps, and the upvalues and bound
+/// methods of the virtual machine.
+pub trait Trace {
+    /// Reports every object this one holds a strong reference to.
+    fn trace(&self, tracer: &mut Tracer);
+
+    /// Drops the references of an unreachable object so that the cycles it is
+    /// part of fall apart.
+    fn clear(&self);
+}
+
+/// Collects the outgoing references of one object during the mark phase.
+#[derive(Default)]
+pub struct Tracer {
+    edges: Vec<usize>,
+    pinned: bool,
+}
+
+impl Tracer {
+    pub fn value(&mut self, value: &Value) {
+        match value {
+            Value::Callable(callable) => self.object(callable),
+            Value::Class(class) => self.object(class),
+            Value::Instance(instance) => self.object(instance),
+            Value::List(list) => self.object(list),
+            Value::Map(map) => self.object(map),
+            _ => {}
+        }
+    }
+
+    pub fn object<T: ?Sized>(&mut self, object: &Rc<T>) {
+        self.edges.push(address(object));
+    }
+
+    /// Marks the object as reachable because its references cannot be
+    /// inspected right now, e.g. while it is mutably borrowed.
+    pub fn pin(&mut self) {
+        self.pinned = true;
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy)]
+pub struct GcStats {
+    pub allocations: usize,
+    pub collections: usize,
+    pub freed: usize,
+    pub live: usize,
+}
+
+/// The summary `gc_stats()` returns.
+impl Display for GcStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "allocations: {}, collections: {}, freed: {}, live: {}",
+            self.allocations, self.collections, self.freed, self.live
+        )
+    }
+}
+
+/// Registry of every traced object the interpreter allocated.
+///
+/// Objects stay reference counted, so anything that is not part of a cycle
+/// is freed as soon as it is dropped. The collector only has to find cycles
+/// nothing else points to: strong references that do not come from another
+/// traced object belong to the interpreter (its environments, the Rust stack)
+/// and make the object a root. Everything not reachable from a root is
+/// garbage and gets cleared, which breaks its cycles.
+#[derive(Default)]
+pub struct Heap {
+    objects: Vec<Weak<dyn Trace>>,
+    threshold: usize,
+    allocated_since_collection: usize,
+    stats: GcStats,
+    /// Collect at every safe point instead of waiting for the threshold.
+    pub stress: bool,
+    /// Print a line to stderr for every collection.
+    pub log: bool,
+}
+
+impl Heap {
+    pub fn new() -> Self {
+        Heap {
+            threshold: INITIAL_THRESHOLD,
+            ..Default::default()
+        }
+    }
+
+    pub fn alloc<T: Trace + 'static>(&mut self, object: T) -> Rc<T> {
+        let object = Rc::new(object);
+        let weak: Weak<T> = Rc::downgrade(&object);
+        self.objects.push(weak);
+        self.allocated_since_collection += 1;
+        self.stats.allocations += 1;
+        object
+    }
+
+    pub fn should_collect(&self) -> bool {
+        if self.stress {
+            self.allocated_since_collection > 0
+        } else {
+            self.objects.len() >= self.threshold
+        }
+    }
+
+    pub fn stats(&self) -> GcStats {
+        GcStats {
+            live: self.objects.iter().filter(|o| o.strong_count() > 0).count(),
+            ..self.stats
+        }
+    }
+
+    /// Frees every object that is only kept alive by reference cycles. Must
+    /// only run while the interpreter holds no borrow of a traced object.
+    pub fn collect(&mut self) {
+        self.objects.retain(|object| object.strong_count() > 0);
+        let before = self.objects.len();
+
+        let index: HashMap<usize, usize> = self
+            .objects
+            .iter()
+            .enumerate()
+            .map(|(i, object)| (object.as_ptr() as *const () as usize, i))
+            .collect();
+
+        // Count the references each object receives from other traced
+        // objects; the rest of its strong count comes from outside.
+        let strong: Vec<usize> = self.objects.iter().map(Weak::strong_count).collect();
+        let mut internal = vec![0; before];
+        let mut edges = Vec::with_capacity(before);
+        let mut marked = vec![false; before];
+        for (i, object) in self.objects.iter().enumerate() {
+            let mut tracer = Tracer::default();
+            if let Some(object) = object.upgrade() {
+                object.trace(&mut tracer);
+            }
+            let targets: Vec<usize> = tracer
+                .edges
+                .iter()
+                .filter_map(|address| index.get(address).copied())
+                .collect();
+            for &target in &targets {
+                internal[target] += 1;
+            }
+            marked[i] = tracer.pinned;
+            edges.push(targets);
+        }
+
+        let mut worklist: Vec<usize> = (0..before)
+            .filter(|&i| marked[i] || strong[i] > internal[i])
+            .collect();
+        for &i in &worklist {
+            marked[i] = true;
+        }
+        while let Some(i) = worklist.pop() {
+            for &target in &edges[i] {
+                if !marked[target] {
+                    marked[target] = true;
+                    worklist.push(target);
+                }
+            }
+        }
+
+        for (i, object) in self.objects.iter().enumerate() {
+            if !marked[i] {
+                if let Some(object) = object.upgrade() {
+                    object.clear();
+                }
+            }
+        }
+
+        self.objects.retain(|object| object.strong_count() > 0);
+        let live = self.objects.len();
+        let freed = before - live;
+
+        self.stats.collections += 1;
+        self.stats.freed += freed;
+        self.allocated_since_collection = 0;
+        self.threshold = (live * GROWTH_FACTOR).max(INITIAL_THRESHOLD);
+
+        if self.log {
+            eprintln!(
+                "[gc] collection {}: {before} objects, {freed} freed, {live} live",
+                self.stats.collections
+            );
+        }
+    }
+}
+
+fn address<T: ?Sized>(object: &Rc<T>) -> usize {
+    Rc::as_ptr(object) as *const () as usize
+}
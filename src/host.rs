@@ -0,0 +1,328 @@
+//! Functions implemented in Rust that scripts call like their own.
+
+use crate::diagnostics::{arity_message, ErrorKind};
+use crate::value::Value;
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+/// The kind and message of the runtime error a failing host function raises.
+pub(crate) type HostError = (ErrorKind, String);
+
+/// The closure of a host function, given the arguments and the position
+/// error messages give the first of them.
+type HostFn = dyn Fn(Vec<Value>, usize) -> Result<Value, HostError>;
+
+/// A Rust function scripts can call, made from a closure by
+/// `Engine::register` or `NativeModule::register`.
+#[derive(Clone)]
+pub struct HostFunction {
+    pub(crate) name: String,
+    /// The number of parameters, or the least number when `variadic`.
+    params: usize,
+    pub(crate) variadic: bool,
+    function: Rc<HostFn>,
+    /// The value passed as the first parameter of a method, such as the
+    /// string of `"abc".upper`.
+    receiver: Option<Value>,
+}
+
+impl Debug for HostFunction {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<native fn {}>", self.name)
+    }
+}
+
+impl HostFunction {
+    pub(crate) fn new(
+        name: &str,
+        params: usize,
+        variadic: bool,
+        function: impl Fn(Vec<Value>, usize) -> Result<Value, HostError> + 'static,
+    ) -> Self {
+        HostFunction {
+            name: name.to_string(),
+            params,
+            variadic,
+            function: Rc::new(function),
+            receiver: None,
+        }
+    }
+
+    /// The function as a method of `receiver`, which it takes as its first
+    /// parameter.
+    pub(crate) fn bind(&self, receiver: Value) -> HostFunction {
+        HostFunction {
+            receiver: Some(receiver),
+            ..self.clone()
+        }
+    }
+
+    /// The number of arguments calls pass, or the least number when
+    /// `variadic`.
+    pub(crate) fn call_arity(&self) -> usize {
+        self.params - usize::from(self.receiver.is_some())
+    }
+
+    /// Calls the function with arguments the caller checked the number of.
+    pub(crate) fn invoke(&self, mut args: Vec<Value>) -> Result<Value, HostError> {
+        match &self.receiver {
+            Some(receiver) => {
+                args.insert(0, receiver.clone());
+                (self.function)(args, 0)
+            }
+            None => (self.function)(args, 1),
+        }
+    }
+
+    /// Whether the function takes `count` arguments.
+    pub(crate) fn accepts(&self, count: usize) -> bool {
+        let arity = self.call_arity();
+        count == arity || (self.variadic && count > arity)
+    }
+
+    /// The message for calling the function with `count` arguments.
+    pub(crate) fn arity_message(&self, count: usize) -> String {
+        arity_message(self.call_arity(), self.variadic, count)
+    }
+}
+
+/// A Rust type a host function can take as a parameter.
+pub trait FromValue: Sized {
+    /// The type as argument errors describe it, such as "a number".
+    const EXPECTED: &'static str;
+
+    fn from_value(value: Value) -> Option<Self>;
+}
+
+impl FromValue for Value {
+    const EXPECTED: &'static str = "a value";
+
+    fn from_value(value: Value) -> Option<Self> {
+        Some(value)
+    }
+}
+
+impl FromValue for bool {
+    const EXPECTED: &'static str = "a boolean";
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for f64 {
+    const EXPECTED: &'static str = "a number";
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+}
+
+/// Integers beyond the range of `i64` saturate to its bounds, so that
+/// functions report them as out of their own range, not as non-integers.
+impl FromValue for i64 {
+    const EXPECTED: &'static str = "an integer";
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Number(n) if n.fract() == 0.0 => Some(n as i64),
+            _ => None,
+        }
+    }
+}
+
+impl FromValue for String {
+    const EXPECTED: &'static str = "a string";
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+}
+
+impl<T: FromValue> FromValue for Vec<T> {
+    const EXPECTED: &'static str = "a list";
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::List(items) => items.into_iter().map(T::from_value).collect(),
+            _ => None,
+        }
+    }
+}
+
+/// `nil` becomes `None`.
+impl<T: FromValue> FromValue for Option<T> {
+    const EXPECTED: &'static str = T::EXPECTED;
+
+    fn from_value(value: Value) -> Option<Self> {
+        match value {
+            Value::Nil => Some(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+/// The last parameter of a variadic host function, holding the arguments
+/// left after the others.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rest<T>(pub Vec<T>);
+
+/// What a host function can return: a value, or a `Result` whose error
+/// becomes a runtime error with the error's message.
+pub trait IntoHostResult {
+    fn into_host_result(self) -> Result<Value, HostError>;
+}
+
+impl<T: Into<Value>> IntoHostResult for T {
+    fn into_host_result(self) -> Result<Value, HostError> {
+        Ok(self.into())
+    }
+}
+
+impl<T: Into<Value>, E: Display> IntoHostResult for Result<T, E> {
+    fn into_host_result(self) -> Result<Value, HostError> {
+        self.map(Into::into)
+            .map_err(|error| (ErrorKind::Error, error.to_string()))
+    }
+}
+
+/// A closure that can become a host function: one taking up to six
+/// parameters of `FromValue` types, optionally followed by a `Rest`. `Args`
+/// lists the parameter types, so that the arity follows from the closure's
+/// signature.
+pub trait IntoHostFunction<Args> {
+    fn into_host_function(self, name: &str) -> HostFunction;
+}
+
+/// Converts argument `index` of the host function `name`. Arguments count
+/// from 1; the receiver of a method is argument 0.
+pub(crate) fn argument<T: FromValue>(
+    name: &str,
+    index: usize,
+    value: Value,
+) -> Result<T, HostError> {
+    let type_name = value.type_name();
+    T::from_value(value).ok_or_else(|| {
+        let msg = format!(
+            "Expected {} for argument {index} of '{name}', got {type_name}.",
+            T::EXPECTED
+        );
+        (ErrorKind::Type, msg)
+    })
+}
+
+macro_rules! into_host_function {
+    ($($arg:ident: $param:ident),*) => {
+        impl<F, R, $($param),*> IntoHostFunction<($($param,)*)> for F
+        where
+            F: Fn($($param),*) -> R + 'static,
+            R: IntoHostResult,
+            $($param: FromValue,)*
+        {
+            #[allow(unused_mut, unused_variables)]
+            fn into_host_function(self, name: &str) -> HostFunction {
+                let arity = <[&str]>::len(&[$(stringify!($arg)),*]);
+                let function_name = name.to_string();
+                HostFunction::new(name, arity, false, move |args, first| {
+                    let mut args = args.into_iter().zip(first..);
+                    $(
+                        let (value, index) = args.next().unwrap();
+                        let $arg = argument::<$param>(&function_name, index, value)?;
+                    )*
+                    self($($arg),*).into_host_result()
+                })
+            }
+        }
+
+        impl<F, R, $($param,)* T> IntoHostFunction<($($param,)* Rest<T>,)> for F
+        where
+            F: Fn($($param,)* Rest<T>) -> R + 'static,
+            R: IntoHostResult,
+            $($param: FromValue,)*
+            T: FromValue,
+        {
+            #[allow(unused_mut)]
+            fn into_host_function(self, name: &str) -> HostFunction {
+                let arity = <[&str]>::len(&[$(stringify!($arg)),*]);
+                let function_name = name.to_string();
+                HostFunction::new(name, arity, true, move |args, first| {
+                    let mut args = args.into_iter().zip(first..);
+                    $(
+                        let (value, index) = args.next().unwrap();
+                        let $arg = argument::<$param>(&function_name, index, value)?;
+                    )*
+                    let rest = args
+                        .map(|(value, index)| argument(&function_name, index, value))
+                        .collect::<Result<_, _>>()?;
+                    self($($arg,)* Rest(rest)).into_host_result()
+                })
+            }
+        }
+    };
+}
+
+into_host_function!();
+into_host_function!(a: A);
+into_host_function!(a: A, b: B);
+into_host_function!(a: A, b: B, c: C);
+into_host_function!(a: A, b: B, c: C, d: D);
+into_host_function!(a: A, b: B, c: C, d: D, e: E);
+into_host_function!(a: A, b: B, c: C, d: D, e: E, f: G);
+
+/// Host functions and constants that scripts import by name, like a
+/// module file: `import "name" as m;` or `from "name" import f;`.
+#[derive(Debug, Clone)]
+pub struct NativeModule {
+    pub(crate) name: String,
+    pub(crate) functions: Vec<HostFunction>,
+    pub(crate) constants: Vec<(String, Value)>,
+}
+
+impl NativeModule {
+    pub fn new(name: &str) -> Self {
+        NativeModule {
+            name: name.to_string(),
+            functions: Vec::new(),
+            constants: Vec::new(),
+        }
+    }
+
+    /// Adds the function `name`, replacing any other of that name.
+    pub fn register<Args>(
+        &mut self,
+        name: &str,
+        function: impl IntoHostFunction<Args>,
+    ) -> &mut Self {
+        self.functions.retain(|f| f.name != name);
+        self.functions.push(function.into_host_function(name));
+        self
+    }
+
+    /// Adds the variable `name`, holding `value` in the module's globals.
+    pub fn constant(&mut self, name: &str, value: impl Into<Value>) -> &mut Self {
+        self.constants.retain(|(constant, _)| constant != name);
+        self.constants.push((name.to_string(), value.into()));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// The names of the module's functions and constants, which it all
+    /// exports.
+    pub(crate) fn exports(&self) -> Vec<String> {
+        let functions = self.functions.iter().map(|f| f.name.clone());
+        let constants = self.constants.iter().map(|(name, _)| name.clone());
+        functions.chain(constants).collect()
+    }
+}
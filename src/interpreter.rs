@@ -1,58 +1,181 @@
+use crate::diagnostics::{arity_message, module_frame, Diagnostic, ErrorKind};
 use crate::environments::Environment;
 use crate::errors::ValueError;
+use crate::gc::Heap;
+use crate::host::{HostFunction, NativeModule};
+use crate::limits::{Limits, Meter, STACK_OVERFLOW};
+use crate::models::callable::Callable;
 use crate::models::expressions::Expr;
+use crate::models::lox_class::LoxClass;
+use crate::models::lox_error::{uncaught, ErrorProperty, LoxError};
 use crate::models::lox_func::LoxFunction;
+use crate::models::lox_instance::LoxInstance;
+use crate::models::lox_list::{element_index, slice_range, ListMethod};
+use crate::models::lox_map::{key_not_found, map_key, Entries, MapMethod, NOT_INDEXABLE};
+use crate::models::lox_module::LoxModule;
+use crate::models::lox_string::{char_at, char_slice, IMMUTABLE};
+use crate::models::span::Span;
 use crate::models::statements::Stmt;
 use crate::models::token_types::TokenType;
 use crate::models::tokens::Token;
 use crate::models::values::Value;
-use crate::native_funcs::clock_func::ClockFunction;
+use crate::modules::{imported, Modules};
+use crate::native_funcs::gc_stats_func::GcStatsFunction;
+use crate::native_funcs::{builtin_modules, builtins, string};
 use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::rc::Rc;
-use std::sync::Arc;
 
 #[allow(dead_code)]
-pub struct Interpreter<'a> {
+pub struct Interpreter {
     pub globals: Rc<RefCell<Environment>>,
     pub environment: Rc<RefCell<Environment>>,
-    pub stmts: &'a [Stmt],
-    pub errors: Vec<String>,
+    pub errors: Vec<Diagnostic>,
+    pub heap: Heap,
+    locals: HashMap<usize, usize>,
+    /// Span of the call expression being evaluated, read by the callee.
+    pub call_span: Span,
+    /// Functions the current runtime error unwound through, innermost first,
+    /// with the span of the call that entered each one.
+    pub call_trace: Vec<(String, Span)>,
+    /// The value of the `throw` statement being unwound; unset while the
+    /// error in flight is a runtime error.
+    thrown: Option<Value>,
+    /// Modules the program may import, by canonical path.
+    modules: Modules,
+    /// Modules that have run, by canonical path.
+    loaded: HashMap<PathBuf, Rc<LoxModule>>,
+    /// Host functions defined in the globals of the program and of every
+    /// module.
+    natives: Vec<HostFunction>,
+    /// Native modules, by name.
+    native_modules: HashMap<String, NativeModule>,
+    meter: Meter,
+    /// Calls active at once.
+    call_depth: usize,
 }
 
-impl<'a> Interpreter<'a> {
-    pub fn new(stmts: &'a [Stmt]) -> Self {
-        let globals = Rc::new(RefCell::new(Environment::new(None)));
-        globals.borrow_mut().define(
-            ClockFunction.to_string(),
-            Value::Callable(Arc::new(ClockFunction)),
-        );
+impl Default for Interpreter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        let mut heap = Heap::new();
+        let natives = builtins();
+        let globals = new_globals(&mut heap, &natives);
 
         Interpreter {
             globals: Rc::clone(&globals),
             environment: Rc::clone(&globals),
-            stmts,
             errors: Vec::new(),
+            heap,
+            locals: HashMap::new(),
+            call_span: Span::default(),
+            call_trace: Vec::new(),
+            thrown: None,
+            modules: Modules::new(),
+            loaded: HashMap::new(),
+            natives,
+            native_modules: builtin_modules()
+                .into_iter()
+                .map(|module| (module.name.clone(), module))
+                .collect(),
+            meter: Meter::new(Limits::default()),
+            call_depth: 0,
         }
     }
 
-    pub fn interpret(&mut self) {
-        for stmt in self.stmts {
-            match self.execute(stmt) {
-                Ok(_) => {}
-                Err(e) => match e {
-                    ValueError::Error(m) => self.errors.push(m),
-                    ValueError::Return(_) => {}
-                },
+    /// Sets the limits of the runs that follow, starting a new run.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    /// Starts counting the steps, time and memory of a new run.
+    pub fn restart_meter(&mut self) {
+        self.meter.restart();
+    }
+
+    /// Defines a host function in the globals of the program and of the
+    /// modules it imports from now on.
+    pub fn define_native(&mut self, function: HostFunction) {
+        define_host_function(&self.globals, &function);
+        self.natives.retain(|native| native.name != function.name);
+        self.natives.push(function);
+    }
+
+    /// Removes the host function `name` from the globals of the program and
+    /// of the modules it imports from now on.
+    pub fn undefine_native(&mut self, name: &str) {
+        self.globals.borrow_mut().values.remove(name);
+        self.natives.retain(|native| native.name != name);
+    }
+
+    /// Makes the functions and constants of `module` the globals of the
+    /// module that imports of its name run.
+    pub fn add_native_module(&mut self, module: NativeModule) {
+        self.native_modules.insert(module.name.clone(), module);
+    }
+
+    /// Takes the scope depths the resolver computed for local variables.
+    pub fn add_locals(&mut self, locals: HashMap<usize, usize>) {
+        self.locals.extend(locals);
+    }
+
+    /// Takes the modules the resolver loaded for the program's imports.
+    pub fn add_modules(&mut self, modules: Modules) {
+        self.modules.extend(modules);
+    }
+
+    /// Runs `stmts` until the first runtime error, which is recorded in
+    /// `errors` together with its stack trace.
+    pub fn interpret(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            if let Err(ValueError::Error(error)) = self.execute(stmt) {
+                let error = self.stack_trace(error);
+                self.errors.push(error);
+                return;
             }
         }
     }
 
+    /// Attaches the trace collected while `error` unwound to the top level.
+    pub fn stack_trace(&mut self, mut error: Diagnostic) -> Diagnostic {
+        self.thrown = None;
+        error.trace = std::mem::take(&mut self.call_trace);
+        error
+    }
+
     fn execute(&mut self, stmt: &Stmt) -> Result<(), ValueError> {
+        // Statement boundaries are safe points: no environment or instance is
+        // borrowed while a statement starts.
+        if self.heap.should_collect() {
+            self.heap.collect();
+        }
+        // Blocks have no span of their own; the loops running empty ones
+        // count a step per iteration instead.
+        if !matches!(stmt, Stmt::Block(_)) {
+            self.step(&stmt.span())?;
+        }
         self.visit_stmt(stmt)
     }
 
+    /// Counts a step of the run at `span`, failing when the run exceeds one
+    /// of its limits.
+    fn step(&mut self, span: &Span) -> Result<(), ValueError> {
+        self.meter
+            .step()
+            .map_err(|(kind, msg)| ValueError::Error(Diagnostic::runtime(span, kind, msg)))
+    }
+
     fn visit_stmt(&mut self, stmt: &Stmt) -> Result<(), ValueError> {
         match stmt {
+            Stmt::Class(name, superclass, methods) => {
+                self.visit_class_stmt(name, superclass, methods)
+            }
             Stmt::Expression(e) => {
                 self.evaluate(e)?;
                 Ok(())
@@ -67,35 +190,183 @@ impl<'a> Interpreter<'a> {
             Stmt::Return(_keyword, value) => self.visit_return_stmt(value),
             Stmt::Var(t, e) => {
                 let mut value = Value::Nil;
-                match e {
-                    Some(e) => {
-                        value = self.evaluate(e)?;
-                    }
-                    None => {}
+                if let Some(e) = e {
+                    value = self.evaluate(e)?;
                 }
                 self.environment.borrow_mut().define(t.clone().name, value);
                 Ok(())
             }
-            Stmt::While(e, s) => self.visit_while_stmt(e, s),
+            Stmt::While(e, s, i) => self.visit_while_stmt(e, s, i),
+            Stmt::Break(_) => Err(ValueError::Break),
+            Stmt::Continue(_) => Err(ValueError::Continue),
+            Stmt::Export(_, declaration) => self.visit_stmt(declaration),
+            Stmt::Import(_, path, name) => {
+                let module = self.import(path)?;
+                self.environment
+                    .borrow_mut()
+                    .define(name.name.clone(), Value::Module(module));
+                Ok(())
+            }
+            Stmt::FromImport(_, path, names) => {
+                let module = self.import(path)?;
+                for name in names {
+                    let value = module.get(name).map_err(ValueError::Error)?;
+                    self.environment
+                        .borrow_mut()
+                        .define(name.name.clone(), value);
+                }
+                Ok(())
+            }
+            Stmt::Throw(keyword, value) => self.visit_throw_stmt(keyword, value),
+            Stmt::Try(_, body, catch, finally) => self.visit_try_stmt(body, catch, finally),
             Stmt::Block(s) => {
-                let previous = Rc::clone(&self.environment);
-                let new_env = Rc::new(RefCell::new(Environment::new(Some(previous))));
+                let new_env = self.nested_environment();
                 self.execute_block(s, new_env)
             }
         }
     }
 
+    fn nested_environment(&mut self) -> Rc<RefCell<Environment>> {
+        let previous = Rc::clone(&self.environment);
+        self.heap
+            .alloc(RefCell::new(Environment::new(Some(previous))))
+    }
+
+    /// Returns the module `path` refers to, running it in its own globals
+    /// the first time it is imported.
+    fn import(&mut self, path: &Token) -> Result<Rc<LoxModule>, ValueError> {
+        let Some((canonical, module)) = imported(&self.modules, path) else {
+            let msg = format!("Cannot find module {}.", path.name);
+            return Err(ValueError::Error(Diagnostic::new(&path.span, msg)));
+        };
+        if let Some(loaded) = self.loaded.get(&canonical) {
+            return Ok(Rc::clone(loaded));
+        }
+
+        let globals = new_globals(&mut self.heap, &self.natives);
+        if let Some(native) = self.native_modules.get(&module.file) {
+            for function in &native.functions {
+                define_host_function(&globals, function);
+            }
+            for (name, value) in &native.constants {
+                let value = value.to_tree(&mut self.heap).map_err(|error| {
+                    let error = Diagnostic::runtime(&path.span, ErrorKind::Type, error.to_string());
+                    ValueError::Error(error)
+                })?;
+                globals.borrow_mut().define(name.clone(), value);
+            }
+        }
+        let loaded = Rc::new(LoxModule {
+            file: module.file.clone(),
+            globals: Rc::clone(&globals),
+            exports: module.exports.clone(),
+        });
+        self.loaded.insert(canonical, Rc::clone(&loaded));
+        self.add_locals(module.locals.clone());
+
+        let previous = std::mem::replace(&mut self.environment, globals);
+        let result = module.stmts.iter().try_for_each(|stmt| self.execute(stmt));
+        self.environment = previous;
+        if let Err(ValueError::Error(error)) = result {
+            let frame = module_frame(&module.file);
+            self.call_trace.push((frame, path.span.clone()));
+            return Err(ValueError::Error(error));
+        }
+        Ok(loaded)
+    }
+
+    /// The globals of the module whose code is running, which are at the
+    /// end of every environment chain.
+    fn global_scope(&self) -> Rc<RefCell<Environment>> {
+        let mut env = Rc::clone(&self.environment);
+        loop {
+            let enclosing = env.borrow().enclosing.clone();
+            match enclosing {
+                Some(enclosing) => env = enclosing,
+                None => return env,
+            }
+        }
+    }
+
+    fn visit_throw_stmt(&mut self, keyword: &Token, expr: &Expr) -> Result<(), ValueError> {
+        let value = self.evaluate(expr)?;
+        let error = match &value {
+            Value::Error(error) => error.diagnostic.clone(),
+            value => uncaught(&keyword.span.to(&expr.span()), value),
+        };
+        self.thrown = Some(value);
+        Err(ValueError::Error(error))
+    }
+
+    fn visit_class_stmt(
+        &mut self,
+        token: &Token,
+        superclass: &Option<Expr>,
+        methods: &[Stmt],
+    ) -> Result<(), ValueError> {
+        let superclass = match superclass {
+            None => None,
+            Some(expr) => match self.evaluate(expr)? {
+                Value::Class(class) => Some(class),
+                _ => {
+                    let msg = Diagnostic::runtime(
+                        &expr.span(),
+                        ErrorKind::Type,
+                        "Superclass must be a class.",
+                    );
+                    return Err(ValueError::Error(msg));
+                }
+            },
+        };
+
+        self.environment
+            .borrow_mut()
+            .define(token.name.clone(), Value::Nil);
+
+        // Methods of a subclass close over an extra scope that binds `super`.
+        let previous = Rc::clone(&self.environment);
+        if let Some(class) = &superclass {
+            let mut env = Environment::new(Some(Rc::clone(&previous)));
+            env.define("super".to_string(), Value::Class(Rc::clone(class)));
+            self.environment = self.heap.alloc(RefCell::new(env));
+        }
+
+        let mut class_methods = HashMap::new();
+        for method in methods {
+            if let Stmt::Function(name, params, body) = method {
+                let func = LoxFunction::new(
+                    name.clone(),
+                    params.clone(),
+                    body.clone(),
+                    Rc::clone(&self.environment),
+                    name.name == "init",
+                );
+                class_methods.insert(name.name.clone(), self.heap.alloc(func));
+            }
+        }
+
+        self.environment = previous;
+
+        let class = LoxClass::new(token.name.clone(), superclass, class_methods);
+        self.environment
+            .borrow_mut()
+            .define(token.name.clone(), Value::Class(self.heap.alloc(class)));
+
+        Ok(())
+    }
+
     fn visit_function_stmt(
         &mut self,
         token: &Token,
         params: &[Token],
         body: &[Stmt],
     ) -> Result<(), ValueError> {
-        let func = Value::Callable(Arc::new(LoxFunction::new(
+        let func = Value::Callable(self.heap.alloc(LoxFunction::new(
             token.clone(),
             Vec::from(params),
             Vec::from(body),
             Rc::clone(&self.environment),
+            false,
         )));
         self.environment
             .borrow_mut()
@@ -107,7 +378,7 @@ impl<'a> Interpreter<'a> {
     fn visit_if_stmt(
         &mut self,
         condition: &Expr,
-        then_branch: &Box<Stmt>,
+        then_branch: &Stmt,
         else_branch: &Option<Box<Stmt>>,
     ) -> Result<(), ValueError> {
         if self.evaluate(condition)?.is_truthy() {
@@ -125,61 +396,149 @@ impl<'a> Interpreter<'a> {
     }
 
     fn visit_return_stmt(&mut self, value: &Option<Expr>) -> Result<(), ValueError> {
-        match value {
-            None => Ok(()),
-            Some(e) => {
-                let ret = self.evaluate(e)?;
-                Err(ValueError::Return(ret))
-            }
-        }
+        let ret = match value {
+            None => Value::Nil,
+            Some(e) => self.evaluate(e)?,
+        };
+        Err(ValueError::Return(ret))
     }
 
-    fn visit_while_stmt(&mut self, condition: &Expr, body: &Stmt) -> Result<(), ValueError> {
+    fn visit_while_stmt(
+        &mut self,
+        condition: &Expr,
+        body: &Stmt,
+        increment: &Option<Expr>,
+    ) -> Result<(), ValueError> {
         while self.evaluate(condition)?.is_truthy() {
-            self.execute(body)?;
+            self.step(&condition.span())?;
+            match self.execute(body) {
+                Ok(()) | Err(ValueError::Continue) => {}
+                Err(ValueError::Break) => break,
+                Err(error) => return Err(error),
+            }
+            if let Some(increment) = increment {
+                self.evaluate(increment)?;
+            }
         }
 
         Ok(())
     }
 
+    /// Runs a `try` statement. A runtime error or thrown value escaping
+    /// `body` is bound to the name of the `catch` clause, and `finally` runs
+    /// however the statement is left, except by `exit` or an exceeded limit.
+    /// An error, `return`, `break` or `continue` in `finally` replaces the
+    /// outcome it interrupted.
+    fn visit_try_stmt(
+        &mut self,
+        body: &[Stmt],
+        catch: &Option<(Token, Vec<Stmt>)>,
+        finally: &Option<Vec<Stmt>>,
+    ) -> Result<(), ValueError> {
+        let trace_len = self.call_trace.len();
+        let env = self.nested_environment();
+        let mut result = self.execute_block(body, env);
+
+        if let (Err(ValueError::Error(error)), Some((name, handler))) = (&result, catch) {
+            if !error.kind.catchable() {
+                return result;
+            }
+            let exception = match self.thrown.take() {
+                Some(value) => value,
+                None => Value::Error(Rc::new(LoxError::new(error))),
+            };
+            self.call_trace.truncate(trace_len);
+            let env = self.nested_environment();
+            env.borrow_mut().define(name.name.clone(), exception);
+            result = self.execute_block(handler, env);
+        }
+
+        if matches!(&result, Err(ValueError::Error(error)) if !error.kind.catchable()) {
+            return result;
+        }
+        if let Some(finally) = finally {
+            let thrown = self.thrown.take();
+            let trace = self.call_trace.split_off(trace_len);
+            let env = self.nested_environment();
+            self.execute_block(finally, env)?;
+            self.thrown = thrown;
+            self.call_trace.extend(trace);
+        }
+        result
+    }
+
     pub fn execute_block(
         &mut self,
         stmts: &[Stmt],
         env: Rc<RefCell<Environment>>,
     ) -> Result<(), ValueError> {
-        // let previous = Rc::clone(&self.environment);
-        let previous = Rc::clone(&env);
+        let previous = Rc::clone(&self.environment);
         self.environment = env;
 
-        for stmt in stmts {
-            match self.execute(stmt) {
-                Ok(_) => {}
-                Err(error) => match error {
-                    ValueError::Error(m) => self.errors.push(m),
-                    ValueError::Return(v) => {
-                        self.environment = previous;
-                        return Err(ValueError::Return(v));
-                    }
-                },
-            }
-        }
+        let result = stmts.iter().try_for_each(|stmt| self.execute(stmt));
         self.environment = previous;
-        Ok(())
+        result
     }
 
-    fn evaluate(&mut self, expr: &Expr) -> Result<Value, ValueError> {
+    pub fn evaluate(&mut self, expr: &Expr) -> Result<Value, ValueError> {
         match expr {
-            Expr::Literal(v) => Ok(v.clone()),
+            Expr::Literal(v, _) => Ok(v.clone()),
             Expr::Logical(l, t, r) => self.visit_logical_expr(l, t, r),
             Expr::Grouping(e) => self.evaluate(e),
             Expr::Unary(t, e) => self.visit_unary_expr(t, e),
             Expr::Binary(l, t, r) => self.visit_binary_expr(l, t, r),
-            Expr::Variable(t) => self.visit_variable_expr(t),
+            Expr::Variable(id, t) => self.lookup_variable(*id, t),
             Expr::Call(callee, paren, args) => self.visit_call_expr(callee, paren, args),
-            Expr::Assign(t, e) => self.visit_assign_expr(t, e),
+            Expr::Assign(id, t, e) => self.visit_assign_expr(*id, t, e),
+            Expr::Get(object, name) => self.visit_get_expr(object, name),
+            Expr::Set(object, name, value) => self.visit_set_expr(object, name, value),
+            Expr::Super(id, keyword, method) => self.visit_super_expr(*id, keyword, method),
+            Expr::This(id, keyword) => self.lookup_variable(*id, keyword),
+            Expr::List(elements, _) => {
+                let mut items = Vec::with_capacity(elements.len());
+                for element in elements {
+                    items.push(self.evaluate(element)?);
+                }
+                Ok(self.alloc_list(items))
+            }
+            Expr::Interpolation(parts, _) => {
+                let mut text = String::new();
+                for part in parts {
+                    text.push_str(&self.evaluate(part)?.to_string());
+                }
+                Ok(Value::String(text))
+            }
+            Expr::Map(entries, span) => {
+                let mut map = Entries::default();
+                for (key, value) in entries {
+                    let key = map_key(&self.evaluate(key)?)
+                        .map_err(|failure| ValueError::Error(Diagnostic::failure(span, failure)))?;
+                    map.insert(key, self.evaluate(value)?);
+                }
+                Ok(Value::Map(self.heap.alloc(RefCell::new(map))))
+            }
+            Expr::Subscript(object, _, index) => self.visit_subscript_expr(expr, object, index),
+            Expr::SetSubscript(object, bracket, index, value) => {
+                self.visit_set_subscript_expr(object, bracket, index, value)
+            }
+            Expr::Slice(object, _, start, end) => self.visit_slice_expr(expr, object, start, end),
+            Expr::Lambda(name, params, body) => {
+                Ok(Value::Callable(self.heap.alloc(LoxFunction::new(
+                    name.clone(),
+                    params.clone(),
+                    body.clone(),
+                    Rc::clone(&self.environment),
+                    false,
+                ))))
+            }
         }
     }
 
+    /// Creates a list on the heap, where cycles through it can be collected.
+    pub fn alloc_list(&mut self, items: Vec<Value>) -> Value {
+        Value::List(self.heap.alloc(RefCell::new(items)))
+    }
+
     fn visit_logical_expr(
         &mut self,
         left: &Expr,
@@ -209,36 +568,147 @@ impl<'a> Interpreter<'a> {
                 if let Some(n) = right.get_number() {
                     Ok(Value::Number(-n))
                 } else {
-                    let msg = format!(
-                        "[line {}] Not a number for MINUS operation.",
-                        token.line_number
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number for MINUS operation.",
                     );
                     Err(ValueError::Error(msg))
                 }
             }
             TokenType::Bang => Ok(Value::Bool(!right.is_truthy())),
             _ => {
-                let msg = format!(
-                    "[line {}] Invalid operation for unary expression.",
-                    token.line_number
+                let msg = Diagnostic::new(&token.span, "Invalid operation for unary expression.");
+                Err(ValueError::Error(msg))
+            }
+        }
+    }
+
+    fn lookup_variable(&self, id: usize, token: &Token) -> Result<Value, ValueError> {
+        let result = match self.locals.get(&id) {
+            Some(distance) => self.environment.borrow().get_at(*distance, token),
+            None => self.global_scope().borrow().get(token),
+        };
+        result.map_err(ValueError::Error)
+    }
+
+    fn visit_assign_expr(
+        &mut self,
+        id: usize,
+        token: &Token,
+        expr: &Expr,
+    ) -> Result<Value, ValueError> {
+        let value = self.evaluate(expr)?;
+        let result = match self.locals.get(&id) {
+            Some(distance) => self
+                .environment
+                .borrow_mut()
+                .assign_at(*distance, token, value),
+            None => self.global_scope().borrow_mut().assign(token, value),
+        };
+        result.map_err(ValueError::Error)
+    }
+
+    fn visit_get_expr(&mut self, object: &Expr, name: &Token) -> Result<Value, ValueError> {
+        match self.evaluate(object)? {
+            Value::Instance(instance) => {
+                LoxInstance::get(&instance, name, &mut self.heap).map_err(ValueError::Error)
+            }
+            Value::List(list) => match ListMethod::bind(list, name) {
+                Ok(method) => Ok(Value::Callable(Rc::new(method))),
+                Err(error) => Err(ValueError::Error(error)),
+            },
+            Value::Map(map) => match MapMethod::bind(map, name) {
+                Ok(method) => Ok(Value::Callable(Rc::new(method))),
+                Err(error) => Err(ValueError::Error(error)),
+            },
+            Value::Error(error) => match error
+                .get(&name.name, &name.span)
+                .map_err(ValueError::Error)?
+            {
+                ErrorProperty::String(s) => Ok(Value::String(s)),
+                ErrorProperty::Number(n) => Ok(Value::Number(n)),
+            },
+            Value::Module(module) => module.get(name).map_err(ValueError::Error),
+            Value::String(s) => match string::method(&s, &name.name) {
+                Some(method) => Ok(Value::Callable(Rc::new(method))),
+                None => Err(ValueError::Error(Diagnostic::runtime(
+                    &name.span,
+                    ErrorKind::Property,
+                    format!("Undefined property '{}'.", name.name),
+                ))),
+            },
+            _ => {
+                let msg = Diagnostic::runtime(
+                    &name.span,
+                    ErrorKind::Type,
+                    "Only instances have properties.",
                 );
                 Err(ValueError::Error(msg))
             }
         }
     }
 
-    fn visit_variable_expr(&self, token: &Token) -> Result<Value, ValueError> {
-        match self.environment.borrow().get(token) {
-            Ok(v) => Ok(v),
-            Err(e) => Err(ValueError::Error(e)),
+    fn visit_set_expr(
+        &mut self,
+        object: &Expr,
+        name: &Token,
+        value: &Expr,
+    ) -> Result<Value, ValueError> {
+        match self.evaluate(object)? {
+            Value::Instance(instance) => {
+                let value = self.evaluate(value)?;
+                instance.borrow_mut().set(name, value.clone());
+                Ok(value)
+            }
+            _ => {
+                let msg =
+                    Diagnostic::runtime(&name.span, ErrorKind::Type, "Only instances have fields.");
+                Err(ValueError::Error(msg))
+            }
         }
     }
 
-    fn visit_assign_expr(&mut self, token: &Token, expr: &Expr) -> Result<Value, ValueError> {
-        let value = self.evaluate(expr)?;
-        match self.environment.borrow_mut().assign(token, value.clone()) {
-            Ok(v) => Ok(v),
-            Err(e) => Err(ValueError::Error(e)),
+    fn visit_super_expr(
+        &mut self,
+        id: usize,
+        keyword: &Token,
+        method: &Token,
+    ) -> Result<Value, ValueError> {
+        let distance = self.locals.get(&id).copied().unwrap_or_default();
+        let superclass = self
+            .environment
+            .borrow()
+            .get_at(distance, keyword)
+            .map_err(ValueError::Error)?;
+
+        // `this` is always bound one scope inside the one that holds `super`.
+        let this = Token::new(
+            TokenType::This,
+            "this".to_string(),
+            Value::Nil,
+            keyword.span.clone(),
+        );
+        let instance = self
+            .environment
+            .borrow()
+            .get_at(distance - 1, &this)
+            .map_err(ValueError::Error)?;
+
+        let found = match superclass {
+            Value::Class(class) => class.find_method(&method.name),
+            _ => None,
+        };
+        match found {
+            Some(func) => Ok(Value::Callable(func.bind(instance, &mut self.heap))),
+            None => {
+                let msg = Diagnostic::runtime(
+                    &method.span,
+                    ErrorKind::Property,
+                    format!("Undefined property '{}'.", method.name),
+                );
+                Err(ValueError::Error(msg))
+            }
         }
     }
 
@@ -255,9 +725,10 @@ impl<'a> Interpreter<'a> {
             TokenType::Minus => match (left, right) {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l - r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number for minus operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number for minus operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -265,9 +736,10 @@ impl<'a> Interpreter<'a> {
             TokenType::Slash => match (left, right) {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l / r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number for division operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number for division operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -275,9 +747,10 @@ impl<'a> Interpreter<'a> {
             TokenType::Star => match (left, right) {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l * r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number for multiply operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number for multiply operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -286,9 +759,10 @@ impl<'a> Interpreter<'a> {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Number(l + r)),
                 (Value::String(l), Value::String(r)) => Ok(Value::String(l + &r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number or string for plus operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number or string for plus operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -297,9 +771,10 @@ impl<'a> Interpreter<'a> {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l > r)),
                 (Value::String(l), Value::String(r)) => Ok(Value::Bool(l > r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number or string for greater operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number or string for greater operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -308,9 +783,10 @@ impl<'a> Interpreter<'a> {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l >= r)),
                 (Value::String(l), Value::String(r)) => Ok(Value::Bool(l >= r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number or string for greater equal operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number or string for greater equal operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -319,9 +795,10 @@ impl<'a> Interpreter<'a> {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l < r)),
                 (Value::String(l), Value::String(r)) => Ok(Value::Bool(l < r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number or string for less operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number or string for less operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -330,9 +807,10 @@ impl<'a> Interpreter<'a> {
                 (Value::Number(l), Value::Number(r)) => Ok(Value::Bool(l <= r)),
                 (Value::String(l), Value::String(r)) => Ok(Value::Bool(l <= r)),
                 _ => {
-                    let msg = format!(
-                        "[line {}] Not a number or string for less equal operation",
-                        token.line_number,
+                    let msg = Diagnostic::runtime(
+                        &token.span,
+                        ErrorKind::Type,
+                        "Not a number or string for less equal operation",
                     );
                     Err(ValueError::Error(msg))
                 }
@@ -340,9 +818,12 @@ impl<'a> Interpreter<'a> {
             TokenType::BangEqual => Ok(Value::Bool(!left.is_equal(right))),
             TokenType::EqualEqual => Ok(Value::Bool(left.is_equal(right))),
             _ => {
-                let msg = format!(
-                    "[line {}] Invalid operation {} for binary expression.",
-                    token.line_number, token.token_type
+                let msg = Diagnostic::new(
+                    &token.span,
+                    format!(
+                        "Invalid operation {} for binary expression.",
+                        token.token_type
+                    ),
                 );
                 Err(ValueError::Error(msg))
             }
@@ -353,7 +834,7 @@ impl<'a> Interpreter<'a> {
         &mut self,
         callee: &Expr,
         paren: &Token,
-        args: &Vec<Expr>,
+        args: &[Expr],
     ) -> Result<Value, ValueError> {
         let callee_func = self.evaluate(callee)?;
         let mut arguments = Vec::new();
@@ -361,24 +842,176 @@ impl<'a> Interpreter<'a> {
             arguments.push(self.evaluate(arg)?);
         }
 
-        if let Some(func) = callee_func.is_callable() {
-            if args.len() != func.arity() {
-                let msg = format!(
-                    "[line {}] Expected {} arguments, but got {}.",
-                    paren.line_number,
-                    func.arity(),
-                    args.len()
-                );
-                return Err(ValueError::Error(msg));
+        let call_span = callee.span().to(&paren.span);
+        self.call_value(&callee_func, &arguments, &call_span)
+    }
+
+    /// Calls `callee` on behalf of the call expression at `call_span`, which
+    /// is where errors about the call itself are reported.
+    pub fn call_value(
+        &mut self,
+        callee: &Value,
+        arguments: &[Value],
+        call_span: &Span,
+    ) -> Result<Value, ValueError> {
+        let Some(func) = callee.is_callable() else {
+            let msg = Diagnostic::runtime(
+                call_span,
+                ErrorKind::Type,
+                "Can only call functions and classes.",
+            );
+            return Err(ValueError::Error(msg));
+        };
+
+        let arity = func.arity();
+        if arguments.len() != arity && !(func.variadic() && arguments.len() > arity) {
+            let msg = arity_message(arity, func.variadic(), arguments.len());
+            return Err(ValueError::Error(Diagnostic::runtime(
+                call_span,
+                ErrorKind::Arity,
+                msg,
+            )));
+        }
+
+        let too_deep = self.call_depth >= self.meter.limits.max_call_depth;
+        if too_deep || self.meter.check_stack().is_err() {
+            let (kind, msg) = STACK_OVERFLOW;
+            return Err(ValueError::Error(Diagnostic::runtime(call_span, kind, msg)));
+        }
+
+        self.call_span = call_span.clone();
+        self.call_depth += 1;
+        let result = func.call(self, arguments);
+        self.call_depth -= 1;
+        result.map_err(|error| match error {
+            // Native functions do not know where they were called from.
+            ValueError::Error(error) if error.span.line == 0 => {
+                let span = call_span.clone();
+                ValueError::Error(Diagnostic { span, ..error })
             }
+            error => error,
+        })
+    }
 
-            func.call(self, &arguments)
-        } else {
-            let msg = format!(
-                "[line {}] Can only call functions and classes.",
-                paren.line_number
+    fn visit_subscript_expr(
+        &mut self,
+        expr: &Expr,
+        object: &Expr,
+        index: &Expr,
+    ) -> Result<Value, ValueError> {
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let span = expr.span();
+        let result = match object {
+            Value::List(list) => {
+                let items = list.borrow();
+                element_index(index.get_number(), items.len())
+                    .map(|index| items[index].clone())
+                    .map_err(|failure| Diagnostic::failure(&span, failure))
+            }
+            Value::Map(map) => map_key(&index)
+                .map_err(|failure| Diagnostic::failure(&span, failure))
+                .and_then(|key| {
+                    let item = map.borrow().get(&key).cloned();
+                    item.ok_or_else(|| key_not_found(&span, &key))
+                }),
+            Value::String(s) => char_at(&s, index.get_number())
+                .map(Value::String)
+                .map_err(|failure| Diagnostic::failure(&span, failure)),
+            _ => Err(Diagnostic::failure(&span, NOT_INDEXABLE)),
+        };
+        result.map_err(ValueError::Error)
+    }
+
+    fn visit_set_subscript_expr(
+        &mut self,
+        object: &Expr,
+        bracket: &Token,
+        index: &Expr,
+        value: &Expr,
+    ) -> Result<Value, ValueError> {
+        let span = object.span().to(&bracket.span);
+        let object = self.evaluate(object)?;
+        let index = self.evaluate(index)?;
+        let value = self.evaluate(value)?;
+        let result = match object {
+            Value::List(list) => {
+                let mut items = list.borrow_mut();
+                element_index(index.get_number(), items.len())
+                    .map(|index| items[index] = value.clone())
+            }
+            Value::Map(map) => {
+                map_key(&index).map(|key| map.borrow_mut().insert(key, value.clone()))
+            }
+            Value::String(_) => Err(IMMUTABLE),
+            _ => Err(NOT_INDEXABLE),
+        };
+        match result {
+            Ok(()) => Ok(value),
+            Err(failure) => Err(ValueError::Error(Diagnostic::failure(&span, failure))),
+        }
+    }
+
+    fn visit_slice_expr(
+        &mut self,
+        expr: &Expr,
+        object: &Expr,
+        start: &Option<Box<Expr>>,
+        end: &Option<Box<Expr>>,
+    ) -> Result<Value, ValueError> {
+        let object = self.evaluate(object)?;
+        let mut bounds = [None, None];
+        for (bound, bound_expr) in bounds.iter_mut().zip([start, end]) {
+            if let Some(bound_expr) = bound_expr {
+                *bound = match self.evaluate(bound_expr)? {
+                    Value::Nil => None,
+                    Value::Number(n) => Some(n),
+                    _ => Some(f64::NAN),
+                };
+            }
+        }
+        if let Value::String(s) = &object {
+            return char_slice(s, bounds[0], bounds[1])
+                .map(Value::String)
+                .map_err(|failure| ValueError::Error(Diagnostic::failure(&expr.span(), failure)));
+        }
+        let Value::List(list) = object else {
+            let msg = Diagnostic::runtime(
+                &expr.span(),
+                ErrorKind::Type,
+                "Only lists and strings can be sliced.",
             );
-            Err(ValueError::Error(msg))
+            return Err(ValueError::Error(msg));
+        };
+
+        let items = list.borrow().clone();
+        match slice_range(bounds[0], bounds[1], items.len()) {
+            Ok(range) => Ok(self.alloc_list(items[range].to_vec())),
+            Err(failure) => Err(ValueError::Error(Diagnostic::failure(
+                &expr.span(),
+                failure,
+            ))),
         }
     }
 }
+
+/// A global environment holding the native functions, for the program and
+/// for every module it imports.
+fn new_globals(heap: &mut Heap, natives: &[HostFunction]) -> Rc<RefCell<Environment>> {
+    let globals = heap.alloc(RefCell::new(Environment::new(None)));
+    for function in natives {
+        define_host_function(&globals, function);
+    }
+    globals.borrow_mut().define(
+        Callable::to_string(&GcStatsFunction).to_string(),
+        Value::Callable(Rc::new(GcStatsFunction)),
+    );
+    globals
+}
+
+fn define_host_function(globals: &Rc<RefCell<Environment>>, function: &HostFunction) {
+    globals.borrow_mut().define(
+        function.name.clone(),
+        Value::Callable(Rc::new(function.clone())),
+    );
+}
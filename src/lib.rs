@@ -0,0 +1,40 @@
+//! Rust interpreter for the Lox language, with a tree-walking interpreter
+//! and a bytecode virtual machine.
+//!
+//! An [`Engine`] runs programs and exchanges [`Value`]s with them:
+//!
+//! ```
+//! use interpreter_starter_rust::{Engine, Value};
+//!
+//! let mut engine = Engine::new();
+//! engine.eval("fun add(a, b) { return a + b; }").unwrap();
+//! let sum = engine.call_function("add", &[1.into(), 2.into()]).unwrap();
+//! assert_eq!(sum, Value::Number(3.0));
+//! ```
+
+pub mod cli;
+mod diagnostics;
+mod engine;
+mod environments;
+mod errors;
+mod gc;
+mod host;
+mod interpreter;
+mod limits;
+mod models;
+mod modules;
+mod native_funcs;
+mod parser;
+mod repl;
+mod resolver;
+mod scanner;
+mod value;
+mod vm;
+
+pub use diagnostics::{Diagnostic, ErrorFormat, ErrorKind, Reporter};
+pub use engine::{Backend, Engine, GcOptions};
+pub use errors::{Error, ExitCode};
+pub use host::{FromValue, HostFunction, IntoHostFunction, IntoHostResult, NativeModule, Rest};
+pub use limits::{CountingAllocator, Limits, DEFAULT_CALL_DEPTH, DEFAULT_STACK_SIZE};
+pub use models::span::Span;
+pub use value::{Object, Value};
@@ -0,0 +1,169 @@
+//! Limits on the resources a run of a program may use, for running untrusted
+//! scripts.
+
+use crate::diagnostics::{ErrorKind, Failure};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+/// Calls that may be active at once unless `Limits` says otherwise.
+pub const DEFAULT_CALL_DEPTH: usize = 4096;
+
+/// Bytes of stack a run may use unless `Limits` says otherwise: half of the
+/// stack Rust gives the threads it spawns.
+pub const DEFAULT_STACK_SIZE: usize = 1024 * 1024;
+
+/// Steps between two readings of the clock.
+const CLOCK_INTERVAL: u64 = 1024;
+
+pub(crate) const STACK_OVERFLOW: Failure = (ErrorKind::StackOverflow, "Stack overflow.");
+const STEP_LIMIT: Failure = (ErrorKind::StepLimit, "Step limit exceeded.");
+const TIMEOUT: Failure = (ErrorKind::Timeout, "Time limit exceeded.");
+const MEMORY_LIMIT: Failure = (ErrorKind::MemoryLimit, "Memory limit exceeded.");
+
+/// What one run of a program, one `Engine::eval`, `run_file` or `call`, may
+/// use. Exceeding the call depth is a `StackOverflowError` scripts can
+/// catch; exceeding the others ends the run however the script handles
+/// errors.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Limits {
+    /// Statements the tree-walking interpreter, or instructions the virtual
+    /// machine, may execute.
+    pub max_steps: Option<u64>,
+    /// Calls that may be active at once. The tree-walking interpreter
+    /// recurses on the Rust stack, a few kilobytes per call, so deep limits
+    /// also need a larger `max_stack`.
+    pub max_call_depth: usize,
+    /// Bytes of the Rust stack of the calling thread the run may use. The
+    /// tree-walking interpreter recurses on it for every call, and both
+    /// backends for every callback of a native such as `map`; running out
+    /// is a stack overflow, like exceeding `max_call_depth`. Raise it only
+    /// when running programs on a thread with a stack larger than this.
+    pub max_stack: usize,
+    /// Wall-clock time the run may take, checked between steps: a native
+    /// function waiting for input is not interrupted.
+    pub timeout: Option<Duration>,
+    /// Bytes the run may allocate on top of those in use when it started.
+    /// Only counted when `CountingAllocator` is the global allocator, as in
+    /// the command line interpreter.
+    pub max_memory: Option<usize>,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_steps: None,
+            max_call_depth: DEFAULT_CALL_DEPTH,
+            max_stack: DEFAULT_STACK_SIZE,
+            timeout: None,
+            max_memory: None,
+        }
+    }
+}
+
+/// Checks the limits of a run as it executes.
+#[derive(Debug)]
+pub(crate) struct Meter {
+    pub limits: Limits,
+    steps: u64,
+    started: Instant,
+    /// Bytes allocated when the run started.
+    memory_base: usize,
+    /// Address of the stack when the run started.
+    stack_base: usize,
+}
+
+impl Meter {
+    pub fn new(limits: Limits) -> Self {
+        Meter {
+            limits,
+            steps: 0,
+            started: Instant::now(),
+            memory_base: allocated(),
+            stack_base: stack_address(),
+        }
+    }
+
+    /// Starts counting the steps, time and memory of a new run.
+    pub fn restart(&mut self) {
+        *self = Meter::new(self.limits);
+    }
+
+    /// Counts a step, failing when the run exceeds one of its limits.
+    pub fn step(&mut self) -> Result<(), Failure> {
+        self.steps += 1;
+        if self.limits.max_steps.is_some_and(|max| self.steps > max) {
+            return Err(STEP_LIMIT);
+        }
+        if let Some(timeout) = self.limits.timeout {
+            if self.steps % CLOCK_INTERVAL == 0 && self.started.elapsed() > timeout {
+                return Err(TIMEOUT);
+            }
+        }
+        if let Some(max) = self.limits.max_memory {
+            if allocated().saturating_sub(self.memory_base) > max {
+                return Err(MEMORY_LIMIT);
+            }
+        }
+        Ok(())
+    }
+
+    /// Fails when the run has used more of the stack than its limits allow,
+    /// before a call that recurses on it.
+    pub fn check_stack(&self) -> Result<(), Failure> {
+        if stack_address().abs_diff(self.stack_base) > self.limits.max_stack {
+            return Err(STACK_OVERFLOW);
+        }
+        Ok(())
+    }
+}
+
+/// An address in the current frame of the stack.
+#[inline(never)]
+fn stack_address() -> usize {
+    let marker = 0u8;
+    std::hint::black_box(&marker) as *const u8 as usize
+}
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+
+/// Bytes allocated through `CountingAllocator` and not freed yet.
+fn allocated() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// The system allocator, counting the bytes in use for `Limits::max_memory`.
+/// Install it with `#[global_allocator]`.
+pub struct CountingAllocator;
+
+unsafe impl GlobalAlloc for CountingAllocator {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc(layout) };
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = unsafe { System.alloc_zeroed(layout) };
+        if !ptr.is_null() {
+            ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed);
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) };
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = unsafe { System.realloc(ptr, layout, new_size) };
+        if !new_ptr.is_null() {
+            ALLOCATED.fetch_add(new_size, Ordering::Relaxed);
+            ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        }
+        new_ptr
+    }
+}
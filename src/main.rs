@@ -1,54 +1,258 @@
-mod environments;
-mod errors;
-mod interpreter;
-mod models;
-mod native_funcs;
-mod parser;
-mod scanner;
-
-use crate::errors::ExitCode;
-use crate::interpreter::Interpreter;
-use crate::parser::Parser;
-use crate::scanner::parse_tokens;
-use std::{
-    env, fs,
-    io::{self, Write},
-    process::exit,
+use interpreter_starter_rust::cli::{self, is_compiled, Repl, Source};
+use interpreter_starter_rust::{
+    Backend, CountingAllocator, Engine, Error, ErrorFormat, ExitCode, GcOptions, Limits, Reporter,
 };
+use std::path::Path;
+use std::time::Duration;
+use std::{env, fs, process::exit, thread};
+
+/// Counts the memory in use for `--max-memory`.
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator;
+
+/// Stack of the thread running the interpreter. The tree-walking interpreter
+/// recurses on it, a few kilobytes per call, and must reach the default call
+/// depth limit before overflowing it.
+const STACK_SIZE: usize = 512 * 1024 * 1024;
+
+/// Part of the stack programs may not use, left for the frames below the
+/// run and the work of the interpreter between two checks of the stack.
+const STACK_RESERVE: usize = 16 * 1024 * 1024;
+
+const USAGE: &str = "\
+Usage: {program} [options] <command> [filename]
+
+Commands:
+  tokenize <filename>  print the tokens of the file
+  parse <filename>     print the syntax tree of a single expression
+  evaluate <filename>  print the value of a single expression
+  run <filename>       run the program in the file (.lox or compiled .loxc)
+  compile <filename>   compile the program to a .loxc bytecode file
+  disasm <filename>    print the bytecode of a .lox or .loxc file
+  repl                 start an interactive session (default without arguments)
+
+A file name given without a command is run if it ends in .lox or .loxc or
+names an existing file. The arguments after the file name of a program that
+runs are the `args` list of the program.
+
+Options:
+  --backend=<tree|vm>  execute with the tree-walking interpreter (default)
+                       or compile to bytecode for the virtual machine
+  --gc-stress          collect garbage before every statement, or every
+                       instruction of the virtual machine
+  --gc-log             report every garbage collection on stderr
+  --no-fs              leave undefined the natives that read and write
+                       files and only allow importing native modules, for
+                       running untrusted scripts
+  --max-steps=<n>      stop a program after n statements (tree) or
+                       instructions (vm), with exit code 81
+  --max-depth=<n>      allow n nested calls (default 4096); deeper ones are
+                       a StackOverflowError, with exit code 80
+  --timeout=<seconds>  stop a program running longer, with exit code 82
+  --max-memory=<bytes> stop a program allocating more, with exit code 83;
+                       the size may end with K, M or G
+  --error-format=<human|short|json>
+                       show errors with the offending source underlined
+                       (default), as one line each, or as JSON records
+  --output=<path>      where `compile` writes the bytecode (default: the
+                       input file name with a .loxc extension)";
 
 fn main() {
+    let interpreter = thread::Builder::new()
+        .stack_size(STACK_SIZE)
+        .spawn(run)
+        .unwrap_or_else(|error| {
+            fail(
+                &format!("Cannot start the interpreter: {error}"),
+                ExitCode::RuntimeError as i32,
+            )
+        });
+    if interpreter.join().is_err() {
+        // The panic message is already printed; exit like a panicking main.
+        exit(101);
+    }
+}
+
+fn run() {
     let args: Vec<String> = env::args().collect();
-    if args.len() < 2 {
-        writeln!(io::stderr(), "Usage: {} tokenize <filename>", args[0]).unwrap();
-        exit(ExitCode::ExitError as i32);
+    let program = &args[0];
+
+    let mut backend = Backend::Tree;
+    let mut output: Option<&str> = None;
+    let mut gc_options = GcOptions::default();
+    let mut error_format = ErrorFormat::default();
+    let mut fs_access = true;
+    let mut limits = Limits {
+        max_stack: STACK_SIZE - STACK_RESERVE,
+        ..Limits::default()
+    };
+    let mut positional: Vec<&str> = Vec::new();
+    let mut script_args: Vec<String> = Vec::new();
+    let mut rest = args[1..].iter();
+    while let Some(arg) = rest.next() {
+        if let Some(value) = arg.strip_prefix("--backend=") {
+            backend = match value {
+                "tree" => Backend::Tree,
+                "vm" => Backend::Vm,
+                _ => usage(program),
+            };
+        } else if let Some(value) = arg.strip_prefix("--error-format=") {
+            error_format = ErrorFormat::parse(value).unwrap_or_else(|| usage(program));
+        } else if let Some(path) = arg.strip_prefix("--output=") {
+            output = Some(path);
+        } else if arg == "--gc-stress" {
+            gc_options.stress = true;
+        } else if arg == "--gc-log" {
+            gc_options.log = true;
+        } else if let Some(value) = arg.strip_prefix("--max-steps=") {
+            limits.max_steps = Some(value.parse().unwrap_or_else(|_| usage(program)));
+        } else if let Some(value) = arg.strip_prefix("--max-depth=") {
+            limits.max_call_depth = value.parse().unwrap_or_else(|_| usage(program));
+        } else if let Some(value) = arg.strip_prefix("--timeout=") {
+            let seconds = value.parse().ok();
+            let timeout = seconds.and_then(|s| Duration::try_from_secs_f64(s).ok());
+            limits.timeout = Some(timeout.unwrap_or_else(|| usage(program)));
+        } else if let Some(value) = arg.strip_prefix("--max-memory=") {
+            limits.max_memory = Some(parse_size(value).unwrap_or_else(|| usage(program)));
+        } else if arg == "--no-fs" {
+            fs_access = false;
+        } else if arg == "-h" || arg == "--help" {
+            println!("{}", USAGE.replace("{program}", program));
+            return;
+        } else if arg.starts_with("--") {
+            usage(program);
+        } else {
+            positional.push(arg);
+            if runs_program(&positional) {
+                script_args.extend(rest.by_ref().cloned());
+            }
+        }
     }
 
-    let filename = &args[1];
-    let file_contents = fs::read_to_string(filename).unwrap_or_else(|_| {
-        writeln!(io::stderr(), "Failed to read file {}", filename).unwrap();
-        exit(ExitCode::ExitError as i32);
+    let (command, filename) = match positional.as_slice() {
+        [] | ["repl"] => {
+            let mut repl = Repl::new();
+            repl.set_fs_access(fs_access);
+            repl.run();
+            return;
+        }
+        ["help"] => {
+            println!("{}", USAGE.replace("{program}", program));
+            return;
+        }
+        [command @ ("tokenize" | "parse" | "evaluate" | "run" | "compile" | "disasm"), filename] => {
+            (*command, *filename)
+        }
+        [filename] if is_script(filename) => ("run", *filename),
+        _ => usage(program),
+    };
+
+    let bytes = fs::read(filename).unwrap_or_else(|error| {
+        let error = Error::Read(filename.into(), error);
+        fail(&error.to_string(), error.status())
     });
 
-    let (tokens, exit_code) = parse_tokens(&file_contents);
-    if exit_code != 0 {
-        exit(exit_code);
+    if command == "run" {
+        // Compiled files always run on the virtual machine.
+        let backend = if is_compiled(&bytes) {
+            Backend::Vm
+        } else {
+            backend
+        };
+        let mut engine = Engine::with_backend(backend);
+        engine.set_gc_options(gc_options);
+        engine.set_error_format(error_format);
+        engine.set_fs_access(fs_access);
+        engine.set_limits(limits);
+        let result = engine
+            .set_global("args", script_args)
+            .and_then(|()| engine.run_file(filename));
+        match result {
+            Ok(()) => {}
+            Err(Error::Exit(status)) => exit(status),
+            Err(error) => fail(&engine.render(&error), error.status()),
+        }
+        return;
     }
 
-    let mut parser = Parser::new(&tokens);
-    parser.parse();
-    if !parser.errors.is_empty() {
-        for error in parser.errors {
-            eprintln!("{error}");
+    let reporter = Reporter::new(error_format);
+    let result = if is_compiled(&bytes) {
+        match command {
+            "disasm" => cli::disasm_compiled(&bytes),
+            _ => fail(
+                &format!("Cannot {command} a compiled file; use run or disasm."),
+                ExitCode::Usage as i32,
+            ),
         }
-        exit(ExitCode::RuntimeError as i32);
-    }
+    } else {
+        let file_contents = String::from_utf8(bytes).unwrap_or_else(|error| {
+            let error = std::io::Error::new(std::io::ErrorKind::InvalidData, error);
+            let error = Error::Read(filename.into(), error);
+            fail(&error.to_string(), error.status())
+        });
 
-    let mut interpreter = Interpreter::new(&parser.stmts);
-    interpreter.interpret();
-    if !interpreter.errors.is_empty() {
-        for error in interpreter.errors {
-            eprintln!("{error}");
+        reporter.add_source(filename, &file_contents);
+        let source = Source {
+            file: filename,
+            text: &file_contents,
+            reporter: &reporter,
+        };
+        match command {
+            "tokenize" => cli::tokenize(&source),
+            "parse" => cli::parse(&source),
+            "evaluate" => cli::evaluate(&source),
+            "compile" => cli::compile(&source, output),
+            _ => cli::disasm(&source),
         }
-        exit(ExitCode::RuntimeError as i32);
+    };
+
+    if let Err(error) = result {
+        fail(&reporter.render_error(&error), error.status());
     }
 }
+
+fn usage(program: &str) -> ! {
+    eprintln!("{}", USAGE.replace("{program}", program));
+    exit(ExitCode::Usage as i32);
+}
+
+/// Reports an error and stops with `exit_code`.
+fn fail(message: &str, status: i32) -> ! {
+    eprintln!("{message}");
+    exit(status);
+}
+
+/// A number of bytes such as `4096`, `512K` or `64M`.
+fn parse_size(value: &str) -> Option<usize> {
+    let (digits, unit) = match value.char_indices().last()? {
+        (i, 'K' | 'k') => (&value[..i], 1 << 10),
+        (i, 'M' | 'm') => (&value[..i], 1 << 20),
+        (i, 'G' | 'g') => (&value[..i], 1 << 30),
+        _ => (value, 1),
+    };
+    digits.parse::<usize>().ok()?.checked_mul(unit)
+}
+
+/// Whether `positional` names a program to run, after which the arguments
+/// are the program's own.
+fn runs_program(positional: &[&str]) -> bool {
+    match positional {
+        ["run", _] => true,
+        [command] => is_script(command),
+        _ => false,
+    }
+}
+
+/// Whether the first positional argument `name` is a program file given
+/// without a command, rather than a misspelled command.
+fn is_script(name: &str) -> bool {
+    let is_command = matches!(
+        name,
+        "tokenize" | "parse" | "evaluate" | "run" | "compile" | "disasm" | "repl" | "help"
+    );
+    let path = Path::new(name);
+    let is_lox = path
+        .extension()
+        .is_some_and(|extension| extension == "lox" || extension == "loxc");
+    !is_command && (is_lox || path.is_file())
+}
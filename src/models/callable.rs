@@ -7,5 +7,9 @@ use std::fmt::Debug;
 pub trait Callable: Debug {
     fn to_string(&self) -> &str;
     fn arity(&self) -> usize;
+    /// Whether calls may pass more arguments than `arity`.
+    fn variadic(&self) -> bool {
+        false
+    }
     fn call(&self, interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, ValueError>;
 }
@@ -1,32 +1,130 @@
+use crate::models::span::Span;
+use crate::models::statements::Stmt;
 use crate::models::tokens::Token;
-use crate::models::values::Value;
+use crate::models::values::{format_number_literal, Value};
 use std::fmt::{Display, Formatter};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static NEXT_EXPR_ID: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns a unique id for expressions the resolver has to bind to a scope.
+/// Ids stay unique across parsers, so cloned function bodies keep theirs.
+pub fn next_expr_id() -> usize {
+    NEXT_EXPR_ID.fetch_add(1, Ordering::Relaxed)
+}
 
 #[derive(Debug, Clone)]
 pub enum Expr {
-    Literal(Value),
+    Literal(Value, Span),
     Logical(Box<Expr>, Token, Box<Expr>),
     Unary(Token, Box<Expr>),
-    Variable(Token),
+    Variable(usize, Token),
     Binary(Box<Expr>, Token, Box<Expr>),
-    Assign(Token, Box<Expr>),
-    Call(Box<Expr>, Token, Box<Vec<Expr>>),
+    Assign(usize, Token, Box<Expr>),
+    Call(Box<Expr>, Token, Vec<Expr>),
+    Get(Box<Expr>, Token),
+    Set(Box<Expr>, Token, Box<Expr>),
+    Super(usize, Token, Token),
+    This(usize, Token),
     Grouping(Box<Expr>),
+    /// `[a, b]`, with the span from the opening to the closing bracket.
+    List(Vec<Expr>, Span),
+    /// `{key: value}`, with the span from the opening to the closing brace.
+    Map(Vec<(Expr, Expr)>, Span),
+    /// `object[index]`; the token is the closing bracket.
+    Subscript(Box<Expr>, Token, Box<Expr>),
+    /// `object[index] = value`.
+    SetSubscript(Box<Expr>, Token, Box<Expr>, Box<Expr>),
+    /// `"text ${expr} text"`: the literal parts and the interpolated
+    /// expressions in order, with the span of the whole string.
+    Interpolation(Vec<Expr>, Span),
+    /// `object[start:end]`, either bound may be omitted.
+    Slice(Box<Expr>, Token, Option<Box<Expr>>, Option<Box<Expr>>),
+    /// `fun (params) { body }` or `(params) => value`, whose body is a
+    /// single `return`. The name token is `anonymous`, placed at the start.
+    Lambda(Token, Vec<Token>, Vec<Stmt>),
+}
+
+impl Expr {
+    /// The source range covered by the tokens of this expression.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Literal(_, span) => span.clone(),
+            Self::Logical(l, _, r) | Self::Binary(l, _, r) => l.span().to(&r.span()),
+            Self::Unary(t, e) => t.span.to(&e.span()),
+            Self::Variable(_, t) | Self::This(_, t) => t.span.clone(),
+            Self::Assign(_, t, e) => t.span.to(&e.span()),
+            Self::Call(callee, paren, _) => callee.span().to(&paren.span),
+            Self::Get(object, name) => object.span().to(&name.span),
+            Self::Set(object, _, value) => object.span().to(&value.span()),
+            Self::Super(_, keyword, method) => keyword.span.to(&method.span),
+            Self::Grouping(e) => e.span(),
+            Self::List(_, span) | Self::Map(_, span) | Self::Interpolation(_, span) => span.clone(),
+            Self::Subscript(object, bracket, _) | Self::Slice(object, bracket, ..) => {
+                object.span().to(&bracket.span)
+            }
+            Self::SetSubscript(object, _, _, value) => object.span().to(&value.span()),
+            Self::Lambda(name, _, body) => body
+                .iter()
+                .fold(name.span.clone(), |span, stmt| span.to(&stmt.span())),
+        }
+    }
 }
 
 impl Display for Expr {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
-            Self::Literal(v) => write!(f, "{v}"),
-            Self::Logical(l, o, r) => write!(f, "({l} {o} {r})"),
+            Self::Literal(Value::Number(n), _) => write!(f, "{}", format_number_literal(*n)),
+            Self::Literal(v, _) => write!(f, "{v}"),
+            Self::Logical(l, o, r) => write!(f, "({} {l} {r})", o.name),
             Self::Unary(t, e) => write!(f, "({} {e})", t.name),
-            Self::Variable(t) => write!(f, "(variable {t})"),
+            Self::Variable(_, t) => write!(f, "{}", t.name),
             Self::Binary(l, o, r) => write!(f, "({} {l} {r})", o.name),
-            Self::Assign(t, e) => write!(f, "({} {e})", t.name),
+            Self::Assign(_, t, e) => write!(f, "({} {e})", t.name),
             Self::Call(callee, _paren, arguments) => {
                 write!(f, "func {callee} with args {:?}", arguments)
             }
+            Self::Get(object, name) => write!(f, "(. {object} {})", name.name),
+            Self::Set(object, name, value) => write!(f, "(= {object} {} {value})", name.name),
+            Self::Super(_, _, method) => write!(f, "(super {})", method.name),
+            Self::This(_, _) => write!(f, "this"),
             Self::Grouping(e) => write!(f, "(group {e})"),
+            Self::List(elements, _) => {
+                write!(f, "(list")?;
+                for element in elements {
+                    write!(f, " {element}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Map(entries, _) => {
+                write!(f, "(map")?;
+                for (key, value) in entries {
+                    write!(f, " ({key} {value})")?;
+                }
+                write!(f, ")")
+            }
+            Self::Interpolation(parts, _) => {
+                write!(f, "(interpolate")?;
+                for part in parts {
+                    write!(f, " {part}")?;
+                }
+                write!(f, ")")
+            }
+            Self::Subscript(object, _, index) => write!(f, "(index {object} {index})"),
+            Self::SetSubscript(object, _, index, value) => {
+                write!(f, "(= (index {object} {index}) {value})")
+            }
+            Self::Slice(object, _, start, end) => {
+                let bound = |bound: &Option<Box<Expr>>| match bound {
+                    Some(bound) => bound.to_string(),
+                    None => "nil".to_string(),
+                };
+                write!(f, "(slice {object} {} {})", bound(start), bound(end))
+            }
+            Self::Lambda(_, params, _) => {
+                let params: Vec<&str> = params.iter().map(|p| p.name.as_str()).collect();
+                write!(f, "(fun ({}))", params.join(" "))
+            }
         }
     }
 }
@@ -0,0 +1,92 @@
+use crate::errors::ValueError;
+use crate::gc::{Trace, Tracer};
+use crate::interpreter::Interpreter;
+use crate::models::callable::Callable;
+use crate::models::lox_func::LoxFunction;
+use crate::models::lox_instance::LoxInstance;
+use crate::models::values::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct LoxClass {
+    pub name: String,
+    superclass: Option<Rc<LoxClass>>,
+    methods: HashMap<String, Rc<LoxFunction>>,
+}
+
+impl LoxClass {
+    pub fn new(
+        name: String,
+        superclass: Option<Rc<LoxClass>>,
+        methods: HashMap<String, Rc<LoxFunction>>,
+    ) -> Self {
+        LoxClass {
+            name,
+            superclass,
+            methods,
+        }
+    }
+
+    /// Looks the method up on this class and then along the superclass chain.
+    pub fn find_method(&self, name: &str) -> Option<Rc<LoxFunction>> {
+        match self.methods.get(name) {
+            Some(method) => Some(Rc::clone(method)),
+            None => self
+                .superclass
+                .as_ref()
+                .and_then(|superclass| superclass.find_method(name)),
+        }
+    }
+}
+
+impl Display for LoxClass {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name)
+    }
+}
+
+// Implemented on the `Rc` so that the created instance points back to the
+// very same class object the script declared.
+impl Callable for Rc<LoxClass> {
+    fn to_string(&self) -> &str {
+        self.name.as_str()
+    }
+
+    fn arity(&self) -> usize {
+        match self.find_method("init") {
+            Some(initializer) => initializer.arity(),
+            None => 0,
+        }
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, ValueError> {
+        let instance = LoxInstance::new(Rc::clone(self));
+        let instance = Value::Instance(interpreter.heap.alloc(RefCell::new(instance)));
+
+        if let Some(initializer) = self.find_method("init") {
+            initializer
+                .bind(instance.clone(), &mut interpreter.heap)
+                .call(interpreter, args)?;
+        }
+
+        Ok(instance)
+    }
+}
+
+impl Trace for LoxClass {
+    fn trace(&self, tracer: &mut Tracer) {
+        if let Some(superclass) = &self.superclass {
+            tracer.object(superclass);
+        }
+        for method in self.methods.values() {
+            tracer.object(method);
+        }
+    }
+
+    // Classes are immutable, so any cycle through one also runs through an
+    // environment or instance, and clearing those is enough.
+    fn clear(&self) {}
+}
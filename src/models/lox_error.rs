@@ -0,0 +1,55 @@
+use crate::diagnostics::{Diagnostic, ErrorKind};
+use crate::models::span::Span;
+use std::fmt::{Display, Formatter};
+
+/// A runtime error caught by a `catch` clause. Scripts read its `message`,
+/// `kind` and `line` properties; throwing it again raises the original error.
+#[derive(Debug)]
+pub struct LoxError {
+    pub diagnostic: Diagnostic,
+}
+
+impl LoxError {
+    pub fn new(diagnostic: &Diagnostic) -> Self {
+        LoxError {
+            diagnostic: Diagnostic {
+                trace: Vec::new(),
+                ..diagnostic.clone()
+            },
+        }
+    }
+}
+
+/// The value of a property of a caught error, converted by each backend.
+pub enum ErrorProperty {
+    String(String),
+    Number(f64),
+}
+
+impl LoxError {
+    pub fn get(&self, name: &str, span: &Span) -> Result<ErrorProperty, Diagnostic> {
+        let diagnostic = &self.diagnostic;
+        match name {
+            "message" => Ok(ErrorProperty::String(diagnostic.message.clone())),
+            "kind" => Ok(ErrorProperty::String(diagnostic.kind.name().to_string())),
+            "line" => Ok(ErrorProperty::Number(diagnostic.span.line as f64)),
+            _ => Err(Diagnostic::runtime(
+                span,
+                ErrorKind::Property,
+                format!("Undefined property '{name}'."),
+            )),
+        }
+    }
+}
+
+impl Display for LoxError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let diagnostic = &self.diagnostic;
+        write!(f, "{}: {}", diagnostic.kind.name(), diagnostic.message)
+    }
+}
+
+/// The error raised by `throw value` for a value that is not a caught error.
+pub fn uncaught(span: &Span, value: impl Display) -> Diagnostic {
+    Diagnostic::new(span, format!("Uncaught exception: {value}"))
+}
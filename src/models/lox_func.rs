@@ -1,5 +1,6 @@
 use crate::environments::Environment;
 use crate::errors::ValueError;
+use crate::gc::{Heap, Trace, Tracer};
 use crate::interpreter::Interpreter;
 use crate::models::callable::Callable;
 use crate::models::statements::Stmt;
@@ -15,6 +16,7 @@ pub struct LoxFunction {
     params: Vec<Token>,
     body: Vec<Stmt>,
     closure: Rc<RefCell<Environment>>,
+    is_initializer: bool,
 }
 
 impl LoxFunction {
@@ -23,14 +25,40 @@ impl LoxFunction {
         params: Vec<Token>,
         body: Vec<Stmt>,
         closure: Rc<RefCell<Environment>>,
+        is_initializer: bool,
     ) -> Self {
         LoxFunction {
             token,
             params,
             body,
             closure,
+            is_initializer,
         }
     }
+
+    /// Returns a copy of the method whose closure defines `this` as `instance`.
+    pub fn bind(&self, instance: Value, heap: &mut Heap) -> Rc<LoxFunction> {
+        let mut env = Environment::new(Some(Rc::clone(&self.closure)));
+        env.define("this".to_string(), instance);
+        let closure = heap.alloc(RefCell::new(env));
+
+        heap.alloc(LoxFunction::new(
+            self.token.clone(),
+            self.params.clone(),
+            self.body.clone(),
+            closure,
+            self.is_initializer,
+        ))
+    }
+
+    fn this(&self) -> Value {
+        self.closure
+            .borrow()
+            .values
+            .get("this")
+            .cloned()
+            .unwrap_or(Value::Nil)
+    }
 }
 
 impl Display for LoxFunction {
@@ -49,20 +77,47 @@ impl Callable for LoxFunction {
     }
 
     fn call(&self, interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, ValueError> {
+        let call_span = interpreter.call_span.clone();
         let mut env = Environment::new(Some(Rc::clone(&self.closure)));
 
         for (i, param) in self.params.iter().enumerate() {
             env.define(param.name.clone(), args.get(i).unwrap().clone());
         }
 
-        match interpreter.execute_block(&self.body, Rc::new(RefCell::new(env))) {
+        let env = interpreter.heap.alloc(RefCell::new(env));
+        match interpreter.execute_block(&self.body, env) {
             Ok(_) => {}
             Err(v) => match v {
-                ValueError::Error(_) => {}
-                ValueError::Return(v) => return Ok(v),
+                ValueError::Error(error) => {
+                    let frame = (self.token.name.clone(), call_span);
+                    interpreter.call_trace.push(frame);
+                    return Err(ValueError::Error(error));
+                }
+                ValueError::Return(v) => {
+                    if self.is_initializer {
+                        return Ok(self.this());
+                    }
+                    return Ok(v);
+                }
+                ValueError::Break | ValueError::Continue => {
+                    unreachable!("the resolver rejects loop control outside of loops")
+                }
             },
         };
 
+        if self.is_initializer {
+            return Ok(self.this());
+        }
         Ok(Value::Nil)
     }
 }
+
+impl Trace for LoxFunction {
+    fn trace(&self, tracer: &mut Tracer) {
+        tracer.object(&self.closure);
+    }
+
+    // The closure is fixed once the function exists; cycles through it are
+    // broken by clearing the environment on the other side.
+    fn clear(&self) {}
+}
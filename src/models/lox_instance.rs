@@ -0,0 +1,79 @@
+use crate::diagnostics::{Diagnostic, ErrorKind};
+use crate::gc::{Heap, Trace, Tracer};
+use crate::models::lox_class::LoxClass;
+use crate::models::tokens::Token;
+use crate::models::values::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+#[derive(Debug)]
+pub struct LoxInstance {
+    pub class: Rc<LoxClass>,
+    fields: HashMap<String, Value>,
+}
+
+impl LoxInstance {
+    pub fn new(class: Rc<LoxClass>) -> Self {
+        LoxInstance {
+            class,
+            fields: HashMap::new(),
+        }
+    }
+
+    /// Looks up a field first and falls back to a method bound to `instance`.
+    pub fn get(
+        instance: &Rc<RefCell<LoxInstance>>,
+        token: &Token,
+        heap: &mut Heap,
+    ) -> Result<Value, Diagnostic> {
+        if let Some(value) = instance.borrow().fields.get(&token.name) {
+            return Ok(value.clone());
+        }
+
+        let method = instance.borrow().class.find_method(&token.name);
+        match method {
+            Some(method) => {
+                let bound = method.bind(Value::Instance(Rc::clone(instance)), heap);
+                Ok(Value::Callable(bound))
+            }
+            None => Err(Diagnostic::runtime(
+                &token.span,
+                ErrorKind::Property,
+                format!("Undefined property '{}'.", token.name),
+            )),
+        }
+    }
+
+    pub fn set(&mut self, token: &Token, value: Value) {
+        self.fields.insert(token.name.clone(), value);
+    }
+}
+
+impl Display for LoxInstance {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} instance", self.class.name)
+    }
+}
+
+impl Trace for RefCell<LoxInstance> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(instance) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        tracer.object(&instance.class);
+        for value in instance.fields.values() {
+            tracer.value(value);
+        }
+    }
+
+    fn clear(&self) {
+        let fields = match self.try_borrow_mut() {
+            Ok(mut instance) => std::mem::take(&mut instance.fields),
+            Err(_) => return,
+        };
+        drop(fields);
+    }
+}
@@ -0,0 +1,246 @@
+use crate::diagnostics::{Diagnostic, ErrorKind, Failure};
+use crate::errors::ValueError;
+use crate::gc::{Trace, Tracer};
+use crate::interpreter::Interpreter;
+use crate::models::callable::Callable;
+use crate::models::span::Span;
+use crate::models::tokens::Token;
+use crate::models::values::Value;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::ops::Range;
+use std::rc::Rc;
+
+pub type LoxList = RefCell<Vec<Value>>;
+
+pub const INDEX_NOT_INTEGER: Failure = (ErrorKind::Type, "List index must be an integer.");
+pub const INDEX_OUT_OF_RANGE: Failure = (ErrorKind::Index, "List index out of range.");
+const NOT_SORTABLE: Failure = (
+    ErrorKind::Type,
+    "Can only sort lists of numbers or of strings.",
+);
+
+/// Methods every list responds to, with their arity.
+pub const LIST_METHODS: [(&str, usize); 9] = [
+    ("push", 1),
+    ("pop", 0),
+    ("len", 0),
+    ("insert", 2),
+    ("remove", 1),
+    ("map", 1),
+    ("filter", 1),
+    ("reduce", 2),
+    ("sort", 0),
+];
+
+/// Position of element `index` in a list of `len` elements. Negative indexes
+/// count from the end; `None` stands for an index that is not a number.
+pub fn element_index(index: Option<f64>, len: usize) -> Result<usize, Failure> {
+    let index = from_end(integer(index)?, len);
+    if (0..len as i64).contains(&index) {
+        Ok(index as usize)
+    } else {
+        Err(INDEX_OUT_OF_RANGE)
+    }
+}
+
+/// Position `insert` puts a new element at: like `element_index`, but the
+/// end of the list is a valid position too.
+pub fn insert_index(index: Option<f64>, len: usize) -> Result<usize, Failure> {
+    let index = from_end(integer(index)?, len);
+    if (0..=len as i64).contains(&index) {
+        Ok(index as usize)
+    } else {
+        Err(INDEX_OUT_OF_RANGE)
+    }
+}
+
+/// Elements of the slice `start:end` of a list of `len` elements, where an
+/// omitted bound is `None`. Bounds may be negative and are clamped to the
+/// list, so slices never fail for being out of range.
+pub fn slice_range(
+    start: Option<f64>,
+    end: Option<f64>,
+    len: usize,
+) -> Result<Range<usize>, Failure> {
+    let clamp = |bound: Option<f64>, default: usize| match bound {
+        None => Ok(default),
+        Some(bound) => {
+            let bound = from_end(integer(Some(bound))?, len);
+            Ok(bound.clamp(0, len as i64) as usize)
+        }
+    };
+
+    let start = clamp(start, 0)?;
+    let end = clamp(end, len)?;
+    Ok(start..end.max(start))
+}
+
+/// Sorts the elements of a list for `sort`, where `number` and `string`
+/// read an element as either. Numbers go in ascending order, with NaN after
+/// every other number so that the order is total, and strings by their
+/// bytes; lists holding anything else cannot be sorted.
+pub fn sort<V>(
+    items: &mut [V],
+    number: impl Fn(&V) -> Option<f64>,
+    string: impl Fn(&V) -> Option<&str>,
+) -> Result<(), Failure> {
+    if items.iter().all(|item| number(item).is_some()) {
+        items.sort_by(|l, r| {
+            number(l)
+                .zip(number(r))
+                .map_or(Ordering::Equal, |(l, r)| compare_numbers(l, r))
+        });
+    } else if items.iter().all(|item| string(item).is_some()) {
+        items.sort_by(|l, r| string(l).cmp(&string(r)));
+    } else {
+        return Err(NOT_SORTABLE);
+    }
+    Ok(())
+}
+
+fn compare_numbers(l: f64, r: f64) -> Ordering {
+    l.partial_cmp(&r)
+        .unwrap_or_else(|| l.is_nan().cmp(&r.is_nan()))
+}
+
+fn integer(index: Option<f64>) -> Result<i64, Failure> {
+    match index {
+        Some(n) if n.fract() == 0.0 => Ok(n as i64),
+        _ => Err(INDEX_NOT_INTEGER),
+    }
+}
+
+fn from_end(index: i64, len: usize) -> i64 {
+    if index < 0 {
+        index + len as i64
+    } else {
+        index
+    }
+}
+
+/// A list method looked up on a list, such as `xs.push`, bound to that list.
+#[derive(Debug)]
+pub struct ListMethod {
+    list: Rc<LoxList>,
+    name: &'static str,
+    arity: usize,
+}
+
+impl ListMethod {
+    pub fn bind(list: Rc<LoxList>, token: &Token) -> Result<ListMethod, Diagnostic> {
+        match LIST_METHODS.iter().find(|(name, _)| *name == token.name) {
+            Some(&(name, arity)) => Ok(ListMethod { list, name, arity }),
+            None => Err(Diagnostic::runtime(
+                &token.span,
+                ErrorKind::Property,
+                format!("Undefined property '{}'.", token.name),
+            )),
+        }
+    }
+}
+
+impl Callable for ListMethod {
+    fn to_string(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, ValueError> {
+        let call_span = interpreter.call_span.clone();
+        let len = self.list.borrow().len();
+        match self.name {
+            "push" => {
+                self.list.borrow_mut().push(args[0].clone());
+                Ok(Value::Nil)
+            }
+            "pop" => self
+                .list
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| error((ErrorKind::Index, "Can't pop from an empty list."))),
+            "len" => Ok(Value::Number(len as f64)),
+            "insert" => {
+                let index = insert_index(args[0].get_number(), len).map_err(error)?;
+                self.list.borrow_mut().insert(index, args[1].clone());
+                Ok(Value::Nil)
+            }
+            "remove" => {
+                let index = element_index(args[0].get_number(), len).map_err(error)?;
+                Ok(self.list.borrow_mut().remove(index))
+            }
+            "map" => {
+                let items = self.list.borrow().clone();
+                let mut mapped = Vec::with_capacity(items.len());
+                for item in items {
+                    mapped.push(interpreter.call_value(&args[0], &[item], &call_span)?);
+                }
+                Ok(interpreter.alloc_list(mapped))
+            }
+            "filter" => {
+                let items = self.list.borrow().clone();
+                let mut kept = Vec::new();
+                for item in items {
+                    let keep = interpreter.call_value(
+                        &args[0],
+                        std::slice::from_ref(&item),
+                        &call_span,
+                    )?;
+                    if keep.is_truthy() {
+                        kept.push(item);
+                    }
+                }
+                Ok(interpreter.alloc_list(kept))
+            }
+            "reduce" => {
+                let items = self.list.borrow().clone();
+                let mut accumulator = args[1].clone();
+                for item in items {
+                    accumulator =
+                        interpreter.call_value(&args[0], &[accumulator, item], &call_span)?;
+                }
+                Ok(accumulator)
+            }
+            "sort" => {
+                sort(&mut self.list.borrow_mut(), Value::get_number, text).map_err(error)?;
+                Ok(Value::Nil)
+            }
+            name => unreachable!("unknown list method {name}"),
+        }
+    }
+}
+
+fn text(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// A runtime error of a list method, placed at the call by the interpreter.
+fn error(failure: Failure) -> ValueError {
+    ValueError::Error(Diagnostic::failure(&Span::default(), failure))
+}
+
+impl Trace for LoxList {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(items) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        for item in items.iter() {
+            tracer.value(item);
+        }
+    }
+
+    fn clear(&self) {
+        let items = match self.try_borrow_mut() {
+            Ok(mut items) => std::mem::take(&mut *items),
+            Err(_) => return,
+        };
+        drop(items);
+    }
+}
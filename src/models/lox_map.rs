@@ -0,0 +1,222 @@
+use crate::diagnostics::{Diagnostic, ErrorKind, Failure};
+use crate::errors::ValueError;
+use crate::gc::{Trace, Tracer};
+use crate::interpreter::Interpreter;
+use crate::models::callable::Callable;
+use crate::models::span::Span;
+use crate::models::tokens::Token;
+use crate::models::values::Value;
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+pub type LoxMap = RefCell<Entries<Value>>;
+
+pub const NOT_INDEXABLE: Failure = (
+    ErrorKind::Type,
+    "Only lists, maps and strings can be indexed.",
+);
+pub const KEY_NOT_HASHABLE: Failure = (
+    ErrorKind::Type,
+    "Map keys must be strings, numbers, booleans or nil.",
+);
+
+/// Methods every map responds to, with their arity.
+pub const MAP_METHODS: [(&str, usize); 5] = [
+    ("keys", 0),
+    ("values", 0),
+    ("has", 1),
+    ("remove", 1),
+    ("len", 0),
+];
+
+/// The hashable subset of values, usable as map keys by both backends.
+/// Numbers are stored by their bits, with `-0` folded into `0` so that keys
+/// that compare equal hash equally.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum MapKey {
+    Nil,
+    Bool(bool),
+    Number(u64),
+    String(Rc<str>),
+}
+
+impl MapKey {
+    pub fn number(n: f64) -> MapKey {
+        MapKey::Number(if n == 0.0 { 0.0f64 } else { n }.to_bits())
+    }
+}
+
+impl Display for MapKey {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nil => write!(f, "nil"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(bits) => write!(f, "{}", f64::from_bits(*bits)),
+            Self::String(s) => write!(f, "\"{s}\""),
+        }
+    }
+}
+
+/// The runtime error for looking up a missing key.
+pub fn key_not_found(span: &Span, key: &MapKey) -> Diagnostic {
+    Diagnostic::runtime(span, ErrorKind::Key, format!("Undefined key {key}."))
+}
+
+/// Entries of a map in insertion order, indexed by key.
+#[derive(Debug)]
+pub struct Entries<V> {
+    entries: Vec<(MapKey, V)>,
+    index: HashMap<MapKey, usize>,
+}
+
+impl<V> Default for Entries<V> {
+    fn default() -> Self {
+        Entries {
+            entries: Vec::new(),
+            index: HashMap::new(),
+        }
+    }
+}
+
+impl<V> Entries<V> {
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn get(&self, key: &MapKey) -> Option<&V> {
+        self.index.get(key).map(|&i| &self.entries[i].1)
+    }
+
+    pub fn contains_key(&self, key: &MapKey) -> bool {
+        self.index.contains_key(key)
+    }
+
+    /// Sets the value of `key`. A new key goes last; an existing one keeps
+    /// its position.
+    pub fn insert(&mut self, key: MapKey, value: V) {
+        match self.index.get(&key) {
+            Some(&i) => self.entries[i].1 = value,
+            None => {
+                self.index.insert(key.clone(), self.entries.len());
+                self.entries.push((key, value));
+            }
+        }
+    }
+
+    pub fn remove(&mut self, key: &MapKey) -> Option<V> {
+        let i = self.index.remove(key)?;
+        let (_, value) = self.entries.remove(i);
+        for (key, _) in &self.entries[i..] {
+            *self.index.get_mut(key).unwrap() -= 1;
+        }
+        Some(value)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (&MapKey, &V)> {
+        self.entries.iter().map(|(key, value)| (key, value))
+    }
+}
+
+/// The key `value` stands for, if it is hashable.
+pub fn map_key(value: &Value) -> Result<MapKey, Failure> {
+    match value {
+        Value::Nil => Ok(MapKey::Nil),
+        Value::Bool(b) => Ok(MapKey::Bool(*b)),
+        Value::Number(n) => Ok(MapKey::number(*n)),
+        Value::String(s) => Ok(MapKey::String(Rc::from(s.as_str()))),
+        _ => Err(KEY_NOT_HASHABLE),
+    }
+}
+
+impl From<&MapKey> for Value {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Nil => Value::Nil,
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            MapKey::String(s) => Value::String(s.to_string()),
+        }
+    }
+}
+
+/// A map method looked up on a map, such as `m.keys`, bound to that map.
+#[derive(Debug)]
+pub struct MapMethod {
+    map: Rc<LoxMap>,
+    name: &'static str,
+    arity: usize,
+}
+
+impl MapMethod {
+    pub fn bind(map: Rc<LoxMap>, token: &Token) -> Result<MapMethod, Diagnostic> {
+        match MAP_METHODS.iter().find(|(name, _)| *name == token.name) {
+            Some(&(name, arity)) => Ok(MapMethod { map, name, arity }),
+            None => Err(Diagnostic::runtime(
+                &token.span,
+                ErrorKind::Property,
+                format!("Undefined property '{}'.", token.name),
+            )),
+        }
+    }
+}
+
+impl Callable for MapMethod {
+    fn to_string(&self) -> &str {
+        self.name
+    }
+
+    fn arity(&self) -> usize {
+        self.arity
+    }
+
+    fn call(&self, interpreter: &mut Interpreter, args: &[Value]) -> Result<Value, ValueError> {
+        match self.name {
+            "keys" => {
+                let keys = self.map.borrow().iter().map(|(k, _)| k.into()).collect();
+                Ok(interpreter.alloc_list(keys))
+            }
+            "values" => {
+                let values = self.map.borrow().iter().map(|(_, v)| v.clone()).collect();
+                Ok(interpreter.alloc_list(values))
+            }
+            "has" => {
+                let key = map_key(&args[0]).map_err(failure)?;
+                Ok(Value::Bool(self.map.borrow().contains_key(&key)))
+            }
+            "remove" => {
+                let key = map_key(&args[0]).map_err(failure)?;
+                let removed = self.map.borrow_mut().remove(&key);
+                removed.ok_or_else(|| ValueError::Error(key_not_found(&Span::default(), &key)))
+            }
+            "len" => Ok(Value::Number(self.map.borrow().len() as f64)),
+            name => unreachable!("unknown map method {name}"),
+        }
+    }
+}
+
+/// A runtime error of a map method, placed at the call by the interpreter.
+fn failure(failure: Failure) -> ValueError {
+    ValueError::Error(Diagnostic::failure(&Span::default(), failure))
+}
+
+impl Trace for LoxMap {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(entries) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        for (_, value) in entries.iter() {
+            tracer.value(value);
+        }
+    }
+
+    fn clear(&self) {
+        let entries = match self.try_borrow_mut() {
+            Ok(mut entries) => std::mem::take(&mut *entries),
+            Err(_) => return,
+        };
+        drop(entries);
+    }
+}
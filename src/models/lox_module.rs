@@ -0,0 +1,33 @@
+use crate::diagnostics::{Diagnostic, ErrorKind};
+use crate::environments::Environment;
+use crate::models::tokens::Token;
+use crate::models::values::Value;
+use crate::modules::not_exported;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+/// A module that has run, as the name it is imported as sees it: its
+/// exported globals are readable as properties.
+#[derive(Debug)]
+pub struct LoxModule {
+    pub file: String,
+    pub globals: Rc<RefCell<Environment>>,
+    pub exports: Vec<String>,
+}
+
+impl LoxModule {
+    pub fn get(&self, name: &Token) -> Result<Value, Diagnostic> {
+        if !self.exports.contains(&name.name) {
+            let msg = not_exported(&self.file, &name.name);
+            return Err(Diagnostic::runtime(&name.span, ErrorKind::Property, msg));
+        }
+        self.globals.borrow().get(name)
+    }
+}
+
+impl Display for LoxModule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "<module {}>", self.file)
+    }
+}
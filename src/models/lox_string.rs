@@ -0,0 +1,28 @@
+use crate::diagnostics::{ErrorKind, Failure};
+use crate::models::lox_list::{element_index, slice_range};
+
+pub const INDEX_NOT_INTEGER: Failure = (ErrorKind::Type, "String index must be an integer.");
+pub const INDEX_OUT_OF_RANGE: Failure = (ErrorKind::Index, "String index out of range.");
+pub const IMMUTABLE: Failure = (ErrorKind::Type, "Strings cannot be modified.");
+
+/// The character at `index` of `s`, as a string. Indexes count characters,
+/// not bytes, and negative ones count from the end like list indexes.
+pub fn char_at(s: &str, index: Option<f64>) -> Result<String, Failure> {
+    let index = element_index(index, s.chars().count()).map_err(string_failure)?;
+    Ok(s.chars().nth(index).map(String::from).unwrap_or_default())
+}
+
+/// The characters of the slice `start:end` of `s`, with the bounds of a
+/// list slice.
+pub fn char_slice(s: &str, start: Option<f64>, end: Option<f64>) -> Result<String, Failure> {
+    let range = slice_range(start, end, s.chars().count()).map_err(string_failure)?;
+    Ok(s.chars().skip(range.start).take(range.len()).collect())
+}
+
+/// The failure of a list index, reworded for strings.
+fn string_failure((kind, _): Failure) -> Failure {
+    match kind {
+        ErrorKind::Type => INDEX_NOT_INTEGER,
+        _ => INDEX_OUT_OF_RANGE,
+    }
+}
@@ -1,6 +1,14 @@
 pub mod callable;
 pub mod expressions;
+pub mod lox_class;
+pub mod lox_error;
 pub mod lox_func;
+pub mod lox_instance;
+pub mod lox_list;
+pub mod lox_map;
+pub mod lox_module;
+pub mod lox_string;
+pub mod span;
 pub mod statements;
 pub mod token_types;
 pub mod tokens;
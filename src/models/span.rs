@@ -0,0 +1,41 @@
+use std::rc::Rc;
+
+/// Location of a piece of source: the bytes `start..start + len` of `file`,
+/// beginning at `line` and `column`. Lines and columns count from 1, columns
+/// in characters. The default span points nowhere and has line 0.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Span {
+    pub file: Rc<str>,
+    pub start: usize,
+    pub len: usize,
+    pub line: usize,
+    pub column: usize,
+}
+
+impl Span {
+    pub fn new(file: &Rc<str>, start: usize, len: usize, line: usize, column: usize) -> Self {
+        Span {
+            file: Rc::clone(file),
+            start,
+            len,
+            line,
+            column,
+        }
+    }
+
+    /// Returns the span from the start of `self` to the end of `other`.
+    pub fn to(&self, other: &Span) -> Span {
+        if other.line == 0 {
+            return self.clone();
+        }
+        if self.line == 0 {
+            return other.clone();
+        }
+
+        let end = (other.start + other.len).max(self.start + self.len);
+        Span {
+            len: end - self.start,
+            ..self.clone()
+        }
+    }
+}
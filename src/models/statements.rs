@@ -1,31 +1,114 @@
 use crate::models::expressions::Expr;
+use crate::models::span::Span;
 use crate::models::tokens::Token;
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
 pub enum Stmt {
+    Break(Token),
+    Class(Token, Option<Expr>, Vec<Stmt>),
+    Continue(Token),
+    /// `export` and the declaration it makes visible to importers.
+    Export(Token, Box<Stmt>),
     Expression(Expr),
-    Function(Token, Vec<Token>, Box<Vec<Stmt>>),
+    /// `from "path" import a, b;`: the `from` word, the path and the names.
+    FromImport(Token, Token, Vec<Token>),
+    Function(Token, Vec<Token>, Vec<Stmt>),
     If(Expr, Box<Stmt>, Option<Box<Stmt>>),
+    /// `import "path" as name;`: the keyword, the path and the name.
+    Import(Token, Token, Token),
     Print(Expr),
     Return(Token, Option<Expr>),
+    Throw(Token, Expr),
+    /// The `try` keyword, the protected block, the `catch` clause with the
+    /// name its error is bound to, and the `finally` block. At least one of
+    /// the two clauses is present.
+    Try(
+        Token,
+        Vec<Stmt>,
+        Option<(Token, Vec<Stmt>)>,
+        Option<Vec<Stmt>>,
+    ),
     Var(Token, Option<Expr>),
-    While(Expr, Box<Stmt>),
-    Block(Box<Vec<Stmt>>),
+    /// Condition, body and the increment of a desugared `for` loop, which
+    /// runs after the body and after every `continue`.
+    While(Expr, Box<Stmt>, Option<Expr>),
+    Block(Vec<Stmt>),
+}
+
+impl Stmt {
+    /// The source range covered by the tokens and expressions of this
+    /// statement; empty blocks have the default span.
+    pub fn span(&self) -> Span {
+        match self {
+            Self::Break(keyword) | Self::Continue(keyword) => keyword.span.clone(),
+            Self::Class(name, _, methods) | Self::Function(name, _, methods) => {
+                Self::spans_to(name.span.clone(), methods)
+            }
+            Self::Export(keyword, declaration) => keyword.span.to(&declaration.span()),
+            Self::Expression(e) | Self::Print(e) => e.span(),
+            Self::FromImport(keyword, path, names) => names
+                .iter()
+                .fold(keyword.span.to(&path.span), |span, name| {
+                    span.to(&name.span)
+                }),
+            Self::Import(keyword, _, name) => keyword.span.to(&name.span),
+            Self::If(condition, then_branch, else_branch) => {
+                let end = else_branch.as_ref().unwrap_or(then_branch);
+                condition.span().to(&end.span())
+            }
+            Self::Return(keyword, value) | Self::Var(keyword, value) => match value {
+                Some(value) => keyword.span.to(&value.span()),
+                None => keyword.span.clone(),
+            },
+            Self::Throw(keyword, value) => keyword.span.to(&value.span()),
+            Self::Try(keyword, body, catch, finally) => {
+                let span = Self::spans_to(keyword.span.clone(), body);
+                let span = match catch {
+                    Some((name, handler)) => Self::spans_to(span.to(&name.span), handler),
+                    None => span,
+                };
+                Self::spans_to(span, finally.as_deref().unwrap_or_default())
+            }
+            Self::While(condition, body, _) => condition.span().to(&body.span()),
+            Self::Block(stmts) => Self::spans_to(Span::default(), stmts),
+        }
+    }
+
+    fn spans_to(start: Span, stmts: &[Stmt]) -> Span {
+        stmts.iter().fold(start, |span, stmt| span.to(&stmt.span()))
+    }
 }
 
 impl Display for Stmt {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
         match self {
+            Self::Break(_) => write!(f, "Break"),
+            Self::Continue(_) => write!(f, "Continue"),
+            Self::Class(name, superclass, methods) => write!(
+                f,
+                "class {name} < {:?} with methods {:?}",
+                superclass, methods
+            ),
+            Self::Export(_, declaration) => write!(f, "Export {declaration}"),
             Self::Expression(e) => write!(f, "Expression {e}"),
+            Self::FromImport(_, path, names) => {
+                write!(f, "From {} import {:?}", path.name, names)
+            }
+            Self::Import(_, path, name) => write!(f, "Import {} as {name}", path.name),
             Self::Function(name, params, body) => {
                 write!(f, "func {name} {:?} with body {:?}", params, body)
             }
             Self::If(e, tb, eb) => write!(f, "If {} for than {:?} else {:?}", e, tb, eb),
             Self::Print(e) => write!(f, "Print {e}"),
             Self::Return(keyword, value) => write!(f, "{keyword} return {:?}", value),
+            Self::Throw(_, e) => write!(f, "Throw {e}"),
+            Self::Try(_, body, catch, finally) => {
+                write!(f, "Try {:?} catch {:?} finally {:?}", body, catch, finally)
+            }
             Self::Var(t, e) => write!(f, "Variable {t} for {:?}", e),
-            Self::While(e, s) => write!(f, "While {} for {}", e, s),
+            Self::While(e, s, None) => write!(f, "While {} for {}", e, s),
+            Self::While(e, s, Some(i)) => write!(f, "While {} for {} then {}", e, s, i),
             Self::Block(s) => write!(f, "Block for {:?}", s),
         }
     }
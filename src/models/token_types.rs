@@ -7,6 +7,9 @@ pub(crate) enum TokenType {
     RightParen,
     RightBrace,
     LeftBrace,
+    LeftBracket,
+    RightBracket,
+    Colon,
     Comma,
     Dot,
     Minus,
@@ -20,28 +23,39 @@ pub(crate) enum TokenType {
     BangEqual,
     Equal,
     EqualEqual,
+    Arrow,
     Greater,
     GreaterEqual,
     Less,
     LessEqual,
     // Literals
     String,
+    /// The part of a string literal before a `${`.
+    Interpolation,
     Number,
     Identifier,
     // Keywords
     And,
+    Break,
     Class,
+    Continue,
+    Catch,
     Else,
+    Export,
     False,
+    Finally,
     For,
     Fun,
     If,
+    Import,
     Nil,
     Or,
     Return,
     Super,
     This,
+    Throw,
     True,
+    Try,
     Var,
     While,
     Print,
@@ -54,6 +68,9 @@ impl TokenType {
             TokenType::LeftParen => "LEFT_PAREN",
             TokenType::RightBrace => "RIGHT_BRACE",
             TokenType::LeftBrace => "LEFT_BRACE",
+            TokenType::LeftBracket => "LEFT_BRACKET",
+            TokenType::RightBracket => "RIGHT_BRACKET",
+            TokenType::Colon => "COLON",
             TokenType::Comma => "COMMA",
             TokenType::Dot => "DOT",
             TokenType::Minus => "MINUS",
@@ -65,26 +82,36 @@ impl TokenType {
             TokenType::BangEqual => "BANG_EQUAL",
             TokenType::Equal => "EQUAL",
             TokenType::EqualEqual => "EQUAL_EQUAL",
+            TokenType::Arrow => "ARROW",
             TokenType::Greater => "GREATER",
             TokenType::GreaterEqual => "GREATER_EQUAL",
             TokenType::Less => "LESS",
             TokenType::LessEqual => "LESS_EQUAL",
             TokenType::String => "STRING",
+            TokenType::Interpolation => "INTERPOLATION",
             TokenType::Number => "NUMBER",
             TokenType::Identifier => "IDENTIFIER",
             TokenType::And => "AND",
+            TokenType::Break => "BREAK",
+            TokenType::Continue => "CONTINUE",
             TokenType::Class => "CLASS",
+            TokenType::Catch => "CATCH",
             TokenType::Else => "ELSE",
+            TokenType::Export => "EXPORT",
             TokenType::False => "FALSE",
+            TokenType::Finally => "FINALLY",
             TokenType::For => "FOR",
             TokenType::Fun => "FUN",
             TokenType::If => "IF",
+            TokenType::Import => "IMPORT",
             TokenType::Nil => "NIL",
             TokenType::Or => "OR",
             TokenType::Return => "RETURN",
             TokenType::Super => "SUPER",
             TokenType::This => "THIS",
+            TokenType::Throw => "THROW",
             TokenType::True => "TRUE",
+            TokenType::Try => "TRY",
             TokenType::Var => "VAR",
             TokenType::While => "WHILE",
             TokenType::Print => "PRINT",
@@ -95,18 +122,26 @@ impl TokenType {
     pub fn get_keyword_or_identifier(key: &str) -> Self {
         match key {
             "and" => TokenType::And,
+            "break" => TokenType::Break,
             "class" => TokenType::Class,
+            "continue" => TokenType::Continue,
+            "catch" => TokenType::Catch,
             "else" => TokenType::Else,
+            "export" => TokenType::Export,
             "false" => TokenType::False,
+            "finally" => TokenType::Finally,
             "for" => TokenType::For,
             "fun" => TokenType::Fun,
             "if" => TokenType::If,
+            "import" => TokenType::Import,
             "nil" => TokenType::Nil,
             "or" => TokenType::Or,
             "return" => TokenType::Return,
             "super" => TokenType::Super,
             "this" => TokenType::This,
+            "throw" => TokenType::Throw,
             "true" => TokenType::True,
+            "try" => TokenType::Try,
             "var" => TokenType::Var,
             "while" => TokenType::While,
             "print" => TokenType::Print,
@@ -1,5 +1,6 @@
+use crate::models::span::Span;
 use crate::models::token_types::TokenType;
-use crate::models::values::Value;
+use crate::models::values::{format_number_literal, Value};
 use std::fmt::{Display, Formatter};
 
 #[derive(Debug, Clone)]
@@ -7,22 +8,34 @@ pub struct Token {
     pub token_type: TokenType,
     pub name: String,
     pub value: Value,
-    pub line_number: usize,
+    pub span: Span,
 }
 
 impl Token {
-    pub fn new(token_type: TokenType, name: String, value: Value, line_number: usize) -> Self {
+    pub fn new(token_type: TokenType, name: String, value: Value, span: Span) -> Self {
         Token {
             token_type,
             name,
             value,
-            line_number,
+            span,
         }
     }
 }
 
 impl Display for Token {
     fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{} {} {}", self.token_type, self.name, self.value)
+        match (&self.token_type, &self.value) {
+            (TokenType::String | TokenType::Interpolation, Value::String(s)) => {
+                write!(f, "{} {} {s}", self.token_type, self.name)
+            }
+            (TokenType::Number, Value::Number(n)) => write!(
+                f,
+                "{} {} {}",
+                self.token_type,
+                self.name,
+                format_number_literal(*n)
+            ),
+            _ => write!(f, "{} {} null", self.token_type, self.name),
+        }
     }
 }
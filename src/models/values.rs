@@ -1,6 +1,14 @@
 use crate::models::callable::Callable;
+use crate::models::lox_class::LoxClass;
+use crate::models::lox_error::LoxError;
+use crate::models::lox_instance::LoxInstance;
+use crate::models::lox_list::LoxList;
+use crate::models::lox_map::LoxMap;
+use crate::models::lox_module::LoxModule;
+use std::cell::RefCell;
+use std::collections::HashSet;
 use std::fmt::{Display, Formatter};
-use std::sync::Arc;
+use std::rc::Rc;
 
 #[derive(Debug, Clone)]
 pub enum Value {
@@ -8,7 +16,13 @@ pub enum Value {
     Bool(bool),
     String(String),
     Number(f64),
-    Callable(Arc<dyn Callable>),
+    Callable(Rc<dyn Callable>),
+    Class(Rc<LoxClass>),
+    Instance(Rc<RefCell<LoxInstance>>),
+    List(Rc<LoxList>),
+    Map(Rc<LoxMap>),
+    Error(Rc<LoxError>),
+    Module(Rc<LoxModule>),
 }
 
 impl Display for Value {
@@ -19,10 +33,75 @@ impl Display for Value {
             Self::String(s) => write!(f, "{s}"),
             Self::Number(n) => write!(f, "{n}"),
             Self::Callable(c) => write!(f, "<fn {}>", c.to_string()),
+            Self::Class(c) => write!(f, "{c}"),
+            Self::Instance(i) => write!(f, "{}", i.borrow()),
+            Self::List(list) => write_list(f, list),
+            Self::Map(map) => write_map(f, map),
+            Self::Error(error) => write!(f, "{error}"),
+            Self::Module(module) => write!(f, "{module}"),
         }
     }
 }
 
+thread_local! {
+    /// Lists and maps being printed, so that one containing itself prints
+    /// `[...]` or `{...}` instead of recursing forever.
+    static PRINTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Writes the elements of a collection with `write_items`, or `elided` when
+/// the collection at `address` is already being printed.
+fn write_once(
+    f: &mut Formatter<'_>,
+    address: *const (),
+    elided: &str,
+    write_items: impl FnOnce(&mut Formatter<'_>) -> std::fmt::Result,
+) -> std::fmt::Result {
+    if PRINTING.with_borrow(|printing| printing.contains(&address)) {
+        return write!(f, "{elided}");
+    }
+
+    PRINTING.with_borrow_mut(|printing| printing.push(address));
+    let result = write_items(f);
+    PRINTING.with_borrow_mut(|printing| printing.pop());
+    result
+}
+
+fn write_list(f: &mut Formatter<'_>, list: &Rc<LoxList>) -> std::fmt::Result {
+    write_once(f, Rc::as_ptr(list) as *const (), "[...]", |f| {
+        write!(f, "[")?;
+        for (i, item) in list.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_element(f, item)?;
+        }
+        write!(f, "]")
+    })
+}
+
+fn write_map(f: &mut Formatter<'_>, map: &Rc<LoxMap>) -> std::fmt::Result {
+    write_once(f, Rc::as_ptr(map) as *const (), "{...}", |f| {
+        write!(f, "{{")?;
+        for (i, (key, value)) in map.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}: ")?;
+            write_element(f, value)?;
+        }
+        write!(f, "}}")
+    })
+}
+
+/// Writes a value inside a collection, where strings are quoted.
+fn write_element(f: &mut Formatter<'_>, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "\"{s}\""),
+        value => write!(f, "{value}"),
+    }
+}
+
 impl Value {
     pub fn get_string(&self) -> Option<String> {
         match self {
@@ -41,27 +120,90 @@ impl Value {
     pub fn is_truthy(&self) -> bool {
         match self {
             Self::Nil => false,
-            Self::Bool(b) => *b == true,
+            Self::Bool(b) => *b,
             Self::String(s) => !s.is_empty(),
             Self::Number(n) => *n != 0.0,
-            _ => false,
+            _ => true,
         }
     }
 
     pub fn is_equal(&self, other: Self) -> bool {
+        // The elements of lists and maps are compared from a worklist rather
+        // than by recursion, so that deeply nested ones cannot overflow the
+        // stack. `comparing` holds the pairs of lists and maps compared so
+        // far: one that comes around again through a cycle is equal unless
+        // something else tells the two sides apart.
+        let mut comparing: HashSet<(*const (), *const ())> = HashSet::new();
+        let mut pending = vec![(self.clone(), other.clone())];
+        while let Some((l, r)) = pending.pop() {
+            if !l.equals(&r, &mut comparing, &mut pending) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Compares `self` and `other` like `is_equal`, except that the elements
+    /// of two lists or maps are left in `pending` to compare.
+    fn equals(
+        &self,
+        other: &Value,
+        comparing: &mut HashSet<(*const (), *const ())>,
+        pending: &mut Vec<(Value, Value)>,
+    ) -> bool {
         match (self, other) {
             (Self::Nil, Value::Nil) => true,
-            (Self::Bool(l), Value::Bool(r)) => *l == r,
-            (Self::Number(l), Value::Number(r)) => *l == r,
-            (Self::String(l), Value::String(r)) => *l == r,
+            (Self::Bool(l), Value::Bool(r)) => l == r,
+            (Self::Number(l), Value::Number(r)) => l == r,
+            (Self::String(l), Value::String(r)) => l == r,
+            (Self::Callable(l), Value::Callable(r)) => Rc::ptr_eq(l, r),
+            (Self::Class(l), Value::Class(r)) => Rc::ptr_eq(l, r),
+            (Self::Instance(l), Value::Instance(r)) => Rc::ptr_eq(l, r),
+            (Self::Error(l), Value::Error(r)) => Rc::ptr_eq(l, r),
+            (Self::Module(l), Value::Module(r)) => Rc::ptr_eq(l, r),
+            (Self::List(l), Value::List(r)) => {
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if Rc::ptr_eq(l, r) || !comparing.insert(pair) {
+                    return true;
+                }
+                let (l, r) = (l.borrow(), r.borrow());
+                pending.extend(l.iter().cloned().zip(r.iter().cloned()));
+                l.len() == r.len()
+            }
+            (Self::Map(l), Value::Map(r)) => {
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if Rc::ptr_eq(l, r) || !comparing.insert(pair) {
+                    return true;
+                }
+                let (l, r) = (l.borrow(), r.borrow());
+                l.len() == r.len()
+                    && l.iter().all(|(key, l)| match r.get(key) {
+                        Some(r) => {
+                            pending.push((l.clone(), r.clone()));
+                            true
+                        }
+                        None => false,
+                    })
+            }
             _ => false,
         }
     }
 
-    pub fn is_callable(&self) -> Option<&Arc<dyn Callable>> {
+    pub fn is_callable(&self) -> Option<&dyn Callable> {
         match self {
-            Self::Callable(f) => Some(f),
+            Self::Callable(f) => Some(f.as_ref()),
+            Self::Class(c) => Some(c),
             _ => None,
         }
     }
 }
+
+/// Formats a number the way literals are shown by `tokenize` and `parse`,
+/// always with a fractional part: `42` becomes `42.0`.
+pub fn format_number_literal(n: f64) -> String {
+    if n.fract() == 0.0 && n.is_finite() {
+        format!("{n:.1}")
+    } else {
+        format!("{n}")
+    }
+}
@@ -0,0 +1,228 @@
+use crate::diagnostics::Diagnostic;
+use crate::models::span::Span;
+use crate::models::statements::Stmt;
+use crate::models::tokens::Token;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::Scanner;
+use std::collections::{HashMap, HashSet};
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::rc::Rc;
+
+/// Environment variable listing the directories searched for modules that
+/// are not found next to the importing file.
+const LOX_PATH: &str = "LOX_PATH";
+
+/// A module file, scanned, parsed and resolved.
+#[derive(Debug)]
+pub struct Module {
+    /// The path the module is shown as in diagnostics.
+    pub file: String,
+    pub stmts: Vec<Stmt>,
+    pub locals: HashMap<usize, usize>,
+    /// Names declared with `export` at the top level of the module.
+    pub exports: Vec<String>,
+    /// Whether the module is a native module, whose globals the host
+    /// defines instead of statements.
+    pub native: bool,
+}
+
+/// Loaded modules by canonical path; native modules by their name.
+pub type Modules = HashMap<PathBuf, Rc<Module>>;
+
+/// The names of the functions of every native module, by module name.
+pub type NativeExports = HashMap<String, Vec<String>>;
+
+/// Loads the modules a program imports, each once, while the resolver walks
+/// the program. The backends then run a module the first time one of its
+/// imports executes.
+#[derive(Debug, Default)]
+pub struct ModuleLoader {
+    pub modules: Modules,
+    /// Native modules, which imports find before any file.
+    natives: Rc<NativeExports>,
+    /// File name and text of every module read, for showing diagnostics.
+    pub sources: Vec<(String, String)>,
+    /// Canonical path and file name of the modules being loaded, outermost
+    /// first, to detect import cycles.
+    loading: Vec<(PathBuf, String)>,
+    /// Modules that failed to load; their errors were reported already.
+    failed: HashSet<PathBuf>,
+    /// Whether imports may only name native modules, for programs without
+    /// filesystem access.
+    pub no_files: bool,
+}
+
+impl ModuleLoader {
+    /// A loader that resolves the names of `natives` to native modules.
+    pub fn with_natives(natives: Rc<NativeExports>) -> Self {
+        ModuleLoader {
+            natives,
+            ..ModuleLoader::default()
+        }
+    }
+
+    /// Loads the module `path` names, and the modules it imports in turn.
+    pub fn load(&mut self, path: &Token) -> Result<Rc<Module>, Vec<Diagnostic>> {
+        let name = module_path(path);
+        if let Some(exports) = self.natives.get(&name) {
+            let module = self.modules.entry(PathBuf::from(&name)).or_insert_with(|| {
+                Rc::new(Module {
+                    file: name.clone(),
+                    stmts: Vec::new(),
+                    locals: HashMap::new(),
+                    exports: exports.clone(),
+                    native: true,
+                })
+            });
+            return Ok(Rc::clone(module));
+        }
+        if self.no_files {
+            let msg = format!("Cannot import \"{name}\" without filesystem access.");
+            return Err(vec![Diagnostic::new(&path.span, msg)]);
+        }
+        let Some((canonical, file)) = find_module(&path.span.file, &name) else {
+            let msg = format!("Cannot find module \"{name}\".");
+            return Err(vec![Diagnostic::new(&path.span, msg)]);
+        };
+
+        if let Some(module) = self.modules.get(&canonical) {
+            return Ok(Rc::clone(module));
+        }
+        if self.failed.contains(&canonical) {
+            return Err(Vec::new());
+        }
+
+        // The importing file is the root of the chain when it is the program.
+        let root = if self.loading.is_empty() {
+            Path::new(&*path.span.file).canonicalize().ok()
+        } else {
+            None
+        };
+        if let Some(root) = &root {
+            self.loading
+                .push((root.clone(), path.span.file.to_string()));
+        }
+        let result = self.load_file(&path.span, canonical.clone(), file);
+        if root.is_some() {
+            self.loading.pop();
+        }
+
+        match result {
+            Ok(module) => {
+                self.modules.insert(canonical, Rc::clone(&module));
+                Ok(module)
+            }
+            Err(errors) => {
+                self.failed.insert(canonical);
+                Err(errors)
+            }
+        }
+    }
+
+    fn load_file(
+        &mut self,
+        import: &Span,
+        canonical: PathBuf,
+        file: String,
+    ) -> Result<Rc<Module>, Vec<Diagnostic>> {
+        if let Some(start) = self.loading.iter().position(|(path, _)| *path == canonical) {
+            let mut chain: Vec<&str> = self.loading[start..]
+                .iter()
+                .map(|(_, file)| file.as_str())
+                .collect();
+            chain.push(&file);
+            let msg = format!("Import cycle: {}.", chain.join(" -> "));
+            return Err(vec![Diagnostic::new(import, msg)]);
+        }
+
+        let Ok(source) = fs::read_to_string(&canonical) else {
+            let msg = format!("Cannot read module \"{file}\".");
+            return Err(vec![Diagnostic::new(import, msg)]);
+        };
+        self.sources.push((file.clone(), source.clone()));
+
+        let (tokens, errors) = Scanner::new(&file, &source).scan_tokens();
+        if !errors.is_empty() {
+            return Err(errors);
+        }
+        let mut parser = Parser::new(&tokens);
+        parser.parse();
+        if !parser.errors.is_empty() {
+            return Err(parser.errors);
+        }
+
+        self.loading.push((canonical, file.clone()));
+        let mut resolver = Resolver::with_loader(std::mem::take(self));
+        resolver.resolve(&parser.stmts);
+        *self = std::mem::take(&mut resolver.loader);
+        self.loading.pop();
+        if !resolver.errors.is_empty() {
+            return Err(resolver.errors);
+        }
+
+        let exports = parser
+            .stmts
+            .iter()
+            .filter_map(|stmt| match stmt {
+                Stmt::Export(_, declaration) => declared_name(declaration),
+                _ => None,
+            })
+            .collect();
+        Ok(Rc::new(Module {
+            file,
+            stmts: parser.stmts,
+            locals: resolver.locals,
+            exports,
+            native: false,
+        }))
+    }
+}
+
+/// The loaded module an import statement refers to, with its canonical path.
+pub fn imported(modules: &Modules, path: &Token) -> Option<(PathBuf, Rc<Module>)> {
+    let name = PathBuf::from(module_path(path));
+    if let Some(module) = modules.get(&name).filter(|module| module.native) {
+        return Some((name, Rc::clone(module)));
+    }
+    let (canonical, _) = find_module(&path.span.file, &module_path(path))?;
+    let module = modules.get(&canonical)?;
+    Some((canonical, Rc::clone(module)))
+}
+
+/// The message for reading a name a module does not export.
+pub fn not_exported(module: &str, name: &str) -> String {
+    format!("Module \"{module}\" does not export '{name}'.")
+}
+
+fn module_path(path: &Token) -> String {
+    path.value.get_string().unwrap_or_default()
+}
+
+/// Finds the file `path` imported from `importer` refers to: next to the
+/// importing file first, then in every `LOX_PATH` directory. Returns its
+/// canonical path and the path it is shown as.
+fn find_module(importer: &str, path: &str) -> Option<(PathBuf, String)> {
+    let dir = Path::new(importer).parent().unwrap_or(Path::new(""));
+    let mut candidates = vec![dir.join(path)];
+    if let Some(dirs) = env::var_os(LOX_PATH) {
+        candidates.extend(env::split_paths(&dirs).map(|dir| dir.join(path)));
+    }
+
+    candidates.into_iter().find_map(|candidate| {
+        let canonical = candidate.canonicalize().ok()?;
+        let file = candidate.display().to_string();
+        canonical.is_file().then_some((canonical, file))
+    })
+}
+
+fn declared_name(declaration: &Stmt) -> Option<String> {
+    match declaration {
+        Stmt::Class(name, ..) | Stmt::Function(name, ..) | Stmt::Var(name, _) => {
+            Some(name.name.clone())
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,25 @@
+use crate::errors::ValueError;
+use crate::interpreter::Interpreter;
+use crate::models::callable::Callable;
+use crate::models::values::Value;
+
+#[derive(Debug)]
+pub struct GcStatsFunction;
+
+impl Callable for GcStatsFunction {
+    fn to_string(&self) -> &str {
+        "gc_stats"
+    }
+
+    fn arity(&self) -> usize {
+        0
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        _arguments: &[Value],
+    ) -> Result<Value, ValueError> {
+        Ok(Value::String(interpreter.heap.stats().to_string()))
+    }
+}
@@ -0,0 +1,38 @@
+use crate::diagnostics::{Diagnostic, ErrorKind};
+use crate::errors::ValueError;
+use crate::host::HostFunction;
+use crate::interpreter::Interpreter;
+use crate::models::callable::Callable;
+use crate::models::span::Span;
+use crate::models::values::Value;
+use crate::value::Value as HostValue;
+
+impl Callable for HostFunction {
+    fn to_string(&self) -> &str {
+        &self.name
+    }
+
+    fn arity(&self) -> usize {
+        self.call_arity()
+    }
+
+    fn variadic(&self) -> bool {
+        self.variadic
+    }
+
+    fn call(
+        &self,
+        interpreter: &mut Interpreter,
+        arguments: &[Value],
+    ) -> Result<Value, ValueError> {
+        let arguments = arguments.iter().map(HostValue::from_tree).collect();
+        let result = self.invoke(arguments).and_then(|value| {
+            value
+                .to_tree(&mut interpreter.heap)
+                .map_err(|error| (ErrorKind::Type, error.to_string()))
+        });
+        result.map_err(|(kind, message)| {
+            ValueError::Error(Diagnostic::runtime(&Span::default(), kind, message))
+        })
+    }
+}
@@ -0,0 +1,103 @@
+//! Reading stdin, and reading and writing files.
+
+use crate::host::{HostFunction, IntoHostFunction, Rest};
+use crate::value::Value;
+use std::fs;
+use std::io::{self, Write};
+use std::path::Path;
+
+/// The natives that touch the filesystem, which `--no-fs` leaves undefined.
+pub const FS_FUNCTIONS: [&str; 6] = [
+    "read_file",
+    "read_lines",
+    "write_file",
+    "append_file",
+    "list_dir",
+    "exists",
+];
+
+/// `input` and the filesystem natives.
+pub fn functions() -> Vec<HostFunction> {
+    let mut functions = vec![input.into_host_function("input")];
+    functions.extend(fs_functions());
+    functions
+}
+
+/// The natives named in `FS_FUNCTIONS`.
+pub fn fs_functions() -> Vec<HostFunction> {
+    vec![
+        read_file.into_host_function("read_file"),
+        read_lines.into_host_function("read_lines"),
+        write_file.into_host_function("write_file"),
+        append_file.into_host_function("append_file"),
+        list_dir.into_host_function("list_dir"),
+        exists.into_host_function("exists"),
+    ]
+}
+
+/// Prints the arguments as a prompt and reads a line from stdin, without
+/// its line ending, or nil at the end of the input.
+fn input(prompt: Rest<Value>) -> Result<Option<String>, String> {
+    if !prompt.0.is_empty() {
+        let mut stdout = io::stdout();
+        for part in &prompt.0 {
+            print!("{part}");
+        }
+        stdout
+            .flush()
+            .map_err(|error| format!("Cannot write to stdout: {error}."))?;
+    }
+
+    let mut line = String::new();
+    match io::stdin().read_line(&mut line) {
+        Ok(0) => Ok(None),
+        Ok(_) => {
+            let end = line.trim_end_matches(['\n', '\r']).len();
+            line.truncate(end);
+            Ok(Some(line))
+        }
+        Err(error) => Err(format!("Cannot read from stdin: {error}.")),
+    }
+}
+
+/// The message for `error`, which happened when doing `action` to `path`.
+fn failure(action: &str, path: &str, error: io::Error) -> String {
+    format!("Cannot {action} \"{path}\": {error}.")
+}
+
+fn read_file(path: String) -> Result<String, String> {
+    fs::read_to_string(&path).map_err(|error| failure("read", &path, error))
+}
+
+fn read_lines(path: String) -> Result<Vec<String>, String> {
+    let text = read_file(path)?;
+    Ok(text.lines().map(str::to_string).collect())
+}
+
+fn write_file(path: String, text: String) -> Result<(), String> {
+    fs::write(&path, text).map_err(|error| failure("write", &path, error))
+}
+
+fn append_file(path: String, text: String) -> Result<(), String> {
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| file.write_all(text.as_bytes()))
+        .map_err(|error| failure("append to", &path, error))
+}
+
+/// The names of the entries of the directory `path`, sorted.
+fn list_dir(path: String) -> Result<Vec<String>, String> {
+    let entries = fs::read_dir(&path).map_err(|error| failure("list", &path, error))?;
+    let mut names = entries
+        .map(|entry| entry.map(|entry| entry.file_name().to_string_lossy().into_owned()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|error| failure("list", &path, error))?;
+    names.sort();
+    Ok(names)
+}
+
+fn exists(path: String) -> bool {
+    Path::new(&path).try_exists().unwrap_or(false)
+}
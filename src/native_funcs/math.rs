@@ -0,0 +1,34 @@
+use crate::host::{NativeModule, Rest};
+use std::f64::consts::{E, PI};
+
+/// The `math` module: numeric functions and constants, following IEEE 754
+/// where the result is undefined, so `sqrt(-1)` is NaN.
+pub fn module() -> NativeModule {
+    let mut math = NativeModule::new("math");
+    math.constant("PI", PI)
+        .constant("E", E)
+        .register("sqrt", f64::sqrt)
+        .register("pow", f64::powf)
+        .register("abs", f64::abs)
+        .register("floor", f64::floor)
+        .register("ceil", f64::ceil)
+        .register("round", f64::round)
+        .register("min", |first: f64, rest: Rest<f64>| {
+            rest.0.into_iter().fold(first, f64::min)
+        })
+        .register("max", |first: f64, rest: Rest<f64>| {
+            rest.0.into_iter().fold(first, f64::max)
+        })
+        .register("sin", f64::sin)
+        .register("cos", f64::cos)
+        .register("tan", f64::tan)
+        .register("asin", f64::asin)
+        .register("acos", f64::acos)
+        .register("atan", f64::atan)
+        .register("atan2", f64::atan2)
+        .register("log", f64::ln)
+        .register("exp", f64::exp)
+        .register("is_nan", f64::is_nan)
+        .register("is_finite", f64::is_finite);
+    math
+}
@@ -1 +1,39 @@
-pub mod clock_func;
+use crate::host::{HostFunction, IntoHostFunction, NativeModule};
+use crate::modules::NativeExports;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+pub mod gc_stats_func;
+pub mod host_func;
+pub mod io;
+pub mod math;
+pub mod process;
+pub mod string;
+
+/// The native functions both backends define in every global scope.
+pub fn builtins() -> Vec<HostFunction> {
+    let mut functions = vec![clock.into_host_function("clock")];
+    functions.extend(io::functions());
+    functions.extend(process::functions());
+    functions
+}
+
+/// The native modules every program can import.
+pub fn builtin_modules() -> Vec<NativeModule> {
+    vec![math::module(), string::module()]
+}
+
+/// What the built-in native modules export, for resolving imports of them.
+pub fn builtin_exports() -> NativeExports {
+    builtin_modules()
+        .iter()
+        .map(|module| (module.name.clone(), module.exports()))
+        .collect()
+}
+
+/// Seconds since the epoch.
+fn clock() -> Result<f64, &'static str> {
+    match SystemTime::now().duration_since(UNIX_EPOCH) {
+        Ok(duration) => Ok(duration.as_secs_f64()),
+        Err(_) => Err("System time before UNIX epoch!"),
+    }
+}
@@ -0,0 +1,32 @@
+//! The environment of the process running the program, and ending it.
+
+use crate::diagnostics::ErrorKind;
+use crate::host::{argument, HostFunction, IntoHostFunction};
+use std::env;
+
+pub fn functions() -> Vec<HostFunction> {
+    vec![getenv.into_host_function("getenv"), exit()]
+}
+
+/// The environment variable `name`, or nil when it is unset or not
+/// Unicode.
+fn getenv(name: String) -> Option<String> {
+    env::var(name).ok()
+}
+
+/// `exit(status)` ends the program with the process status, which must be
+/// from 0 to 255. It unwinds like an error no `try` catches, which the
+/// engine returns as `Error::Exit`.
+fn exit() -> HostFunction {
+    HostFunction::new("exit", 1, false, |args, first| {
+        let value = args.into_iter().next().unwrap();
+        let status: i64 = argument("exit", first, value)?;
+        match i32::try_from(status) {
+            Ok(status @ 0..=255) => Err((ErrorKind::Exit(status), "Exit.".to_string())),
+            _ => {
+                let msg = "Exit status must be between 0 and 255.".to_string();
+                Err((ErrorKind::Error, msg))
+            }
+        }
+    })
+}
@@ -0,0 +1,85 @@
+use crate::host::{HostFunction, NativeModule};
+use crate::models::lox_string::char_slice;
+use crate::value::Value;
+
+/// The longest string, in bytes, `repeat` builds. Longer ones are much more
+/// likely a runaway count than intended, and would abort the process when
+/// the allocation fails.
+const MAX_REPEAT_LEN: usize = 1 << 30;
+
+thread_local! {
+    /// The functions string methods are bound from.
+    static METHODS: NativeModule = module();
+}
+
+/// The `string` module. Every function takes the string it works on first,
+/// so `string.upper(s)` is `s.upper()`. Lengths and positions count
+/// characters, not bytes.
+pub fn module() -> NativeModule {
+    let mut string = NativeModule::new("string");
+    string
+        .register("len", |s: String| s.chars().count() as f64)
+        .register("upper", |s: String| s.to_uppercase())
+        .register("lower", |s: String| s.to_lowercase())
+        .register("trim", |s: String| s.trim().to_string())
+        .register("split", split)
+        .register("join", join)
+        .register("replace", |s: String, from: String, to: String| {
+            s.replace(&from, &to)
+        })
+        .register("contains", |s: String, part: String| s.contains(&part))
+        .register("starts_with", |s: String, prefix: String| {
+            s.starts_with(&prefix)
+        })
+        .register("ends_with", |s: String, suffix: String| {
+            s.ends_with(&suffix)
+        })
+        .register("find", find)
+        .register("substring", substring)
+        .register("repeat", repeat);
+    string
+}
+
+/// The method `name` of the string `s`, if strings have one.
+pub fn method(s: &str, name: &str) -> Option<HostFunction> {
+    METHODS.with(|methods| {
+        let function = methods.functions.iter().find(|f| f.name == name)?;
+        Some(function.bind(Value::String(s.to_string())))
+    })
+}
+
+/// The parts of `s` between occurrences of `separator`; an empty separator
+/// splits `s` into its characters.
+fn split(s: String, separator: String) -> Vec<String> {
+    if separator.is_empty() {
+        return s.chars().map(String::from).collect();
+    }
+    s.split(&separator).map(String::from).collect()
+}
+
+/// The items of `parts`, printed as `print` does, with `separator` between
+/// them.
+fn join(separator: String, parts: Vec<Value>) -> String {
+    let parts: Vec<String> = parts.iter().map(Value::to_string).collect();
+    parts.join(&separator)
+}
+
+/// The position of the first occurrence of `part` in `s`, or `nil`.
+fn find(s: String, part: String) -> Option<f64> {
+    let byte = s.find(&part)?;
+    Some(s[..byte].chars().count() as f64)
+}
+
+/// The characters from `start` up to `end`, or to the end of `s` when `end`
+/// is `nil`, with the bounds of a slice `s[start:end]`.
+fn substring(s: String, start: i64, end: Option<i64>) -> String {
+    char_slice(&s, Some(start as f64), end.map(|end| end as f64)).unwrap_or_default()
+}
+
+fn repeat(s: String, count: i64) -> Result<String, &'static str> {
+    let count = usize::try_from(count).map_err(|_| "Repeat count must not be negative.")?;
+    match s.len().checked_mul(count) {
+        Some(len) if len <= MAX_REPEAT_LEN => Ok(s.repeat(count)),
+        _ => Err("Repeated string is too long."),
+    }
+}
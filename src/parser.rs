@@ -1,4 +1,5 @@
-use crate::models::expressions::Expr;
+use crate::diagnostics::Diagnostic;
+use crate::models::expressions::{next_expr_id, Expr};
 use crate::models::statements::Stmt;
 use crate::models::token_types::TokenType;
 use crate::models::tokens::Token;
@@ -7,7 +8,7 @@ use crate::models::values::Value;
 pub struct Parser<'a> {
     pub tokens: &'a [Token],
     pub stmts: Vec<Stmt>,
-    pub errors: Vec<String>,
+    pub errors: Vec<Diagnostic>,
     current: usize,
 }
 
@@ -30,6 +31,21 @@ impl<'a> Parser<'a> {
         }
     }
 
+    /// Parses the tokens as one standalone expression, as the `parse` and
+    /// `evaluate` commands expect.
+    pub fn parse_expression(&mut self) -> Result<Expr, Diagnostic> {
+        let expr = self.expression()?;
+        // Errors in the body of a function are collected, not returned.
+        if !self.errors.is_empty() {
+            return Err(self.errors.remove(0));
+        }
+        if !self.is_at_end() {
+            return Err(self.error_at_peek("Expect end of expression."));
+        }
+
+        Ok(expr)
+    }
+
     fn is_at_end(&self) -> bool {
         self.peek().token_type == TokenType::Eof
     }
@@ -54,6 +70,18 @@ impl<'a> Parser<'a> {
         &self.peek().token_type == token_type
     }
 
+    /// Whether the current token is the identifier `word`, for words that
+    /// are keywords only in some places.
+    fn check_word(&self, word: &str) -> bool {
+        self.check(&TokenType::Identifier) && self.peek().name == word
+    }
+
+    fn check_next(&self, token_type: &TokenType) -> bool {
+        self.tokens
+            .get(self.current + 1)
+            .is_some_and(|token| &token.token_type == token_type)
+    }
+
     fn matches(&mut self, types: &[TokenType]) -> bool {
         for token_type in types {
             if self.check(token_type) {
@@ -65,21 +93,31 @@ impl<'a> Parser<'a> {
         false
     }
 
-    fn consume(&mut self, token_type: TokenType, msg: &str) -> Result<&Token, String> {
+    fn consume(&mut self, token_type: TokenType, msg: &str) -> Result<&Token, Diagnostic> {
         if self.check(&token_type) {
             return Ok(self.advance());
         }
 
-        Err(format!("[line {}] {}", self.previous().line_number, msg))
+        Err(Diagnostic::new(&self.previous().span, msg))
+    }
+
+    fn error_at_peek(&self, msg: &str) -> Diagnostic {
+        Diagnostic::new(&self.peek().span, msg)
     }
 
-    fn declaration(&mut self) -> Result<Stmt, String> {
+    fn declaration(&mut self) -> Result<Stmt, Diagnostic> {
+        let start = self.current;
         let res;
 
-        if self.matches(&[TokenType::Fun]) {
+        if self.matches(&[TokenType::Class]) {
+            res = self.class_declaration();
+        } else if self.check(&TokenType::Fun) && !self.check_next(&TokenType::LeftParen) {
+            self.advance();
             res = self.function("function");
         } else if self.matches(&[TokenType::Var]) {
             res = self.var_declaration();
+        } else if self.matches(&[TokenType::Export]) {
+            res = self.export_declaration();
         } else {
             res = self.statement();
         }
@@ -87,13 +125,34 @@ impl<'a> Parser<'a> {
         match res {
             Ok(s) => Ok(s),
             Err(e) => {
-                self.synchronize();
+                self.synchronize(start);
                 Err(e)
             }
         }
     }
 
-    fn function(&mut self, kind: &str) -> Result<Stmt, String> {
+    fn class_declaration(&mut self) -> Result<Stmt, Diagnostic> {
+        self.consume(TokenType::Identifier, "Expect class name.")?;
+        let name = self.previous().clone();
+
+        let mut superclass = None;
+        if self.matches(&[TokenType::Less]) {
+            self.consume(TokenType::Identifier, "Expect superclass name.")?;
+            superclass = Some(Expr::Variable(next_expr_id(), self.previous().clone()));
+        }
+
+        self.consume(TokenType::LeftBrace, "Expect '{' before class body.")?;
+
+        let mut methods = vec![];
+        while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
+            methods.push(self.function("method")?);
+        }
+
+        self.consume(TokenType::RightBrace, "Expect '}' after class body.")?;
+        Ok(Stmt::Class(name, superclass, methods))
+    }
+
+    fn function(&mut self, kind: &str) -> Result<Stmt, Diagnostic> {
         self.consume(
             TokenType::Identifier,
             format!("Expect {} name.", kind).as_str(),
@@ -104,6 +163,34 @@ impl<'a> Parser<'a> {
             format!("Expect '(' after {} name.", kind).as_str(),
         )?;
 
+        let parameters = self.parameters()?;
+        self.consume(
+            TokenType::LeftBrace,
+            format!("Expect '{{' before {} body.", kind).as_str(),
+        )?;
+
+        let body = self.block()?;
+        let func = Stmt::Function(name.clone(), parameters, body);
+        Ok(func)
+    }
+
+    fn export_declaration(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().clone();
+        let declaration = if self.matches(&[TokenType::Class]) {
+            self.class_declaration()?
+        } else if self.matches(&[TokenType::Fun]) {
+            self.function("function")?
+        } else if self.matches(&[TokenType::Var]) {
+            self.var_declaration()?
+        } else {
+            return Err(self.error_at_peek("Expect declaration after 'export'."));
+        };
+        Ok(Stmt::Export(keyword, Box::new(declaration)))
+    }
+
+    /// Parses a parameter list after its opening parenthesis, up to and
+    /// including the closing one.
+    fn parameters(&mut self) -> Result<Vec<Token>, Diagnostic> {
         let mut parameters = vec![];
         if !self.check(&TokenType::RightParen) {
             loop {
@@ -117,17 +204,10 @@ impl<'a> Parser<'a> {
         }
 
         self.consume(TokenType::RightParen, "Expect ')' after parameters.")?;
-        self.consume(
-            TokenType::LeftBrace,
-            format!("Expect '{{' before {} body.", kind).as_str(),
-        )?;
-
-        let body = self.block()?;
-        let func = Stmt::Function(name.clone(), parameters, Box::new(body));
-        Ok(func)
+        Ok(parameters)
     }
 
-    fn var_declaration(&mut self) -> Result<Stmt, String> {
+    fn var_declaration(&mut self) -> Result<Stmt, Diagnostic> {
         self.consume(TokenType::Identifier, "Expect variable name.")?;
         let token = self.previous().clone();
         let mut initializer = None;
@@ -144,7 +224,7 @@ impl<'a> Parser<'a> {
         Ok(Stmt::Var(token.clone(), initializer))
     }
 
-    fn statement(&mut self) -> Result<Stmt, String> {
+    fn statement(&mut self) -> Result<Stmt, Diagnostic> {
         if self.matches(&[TokenType::For]) {
             return self.for_statement();
         }
@@ -161,19 +241,41 @@ impl<'a> Parser<'a> {
             return self.return_statement();
         }
 
+        if self.matches(&[TokenType::Break, TokenType::Continue]) {
+            return self.loop_control_statement();
+        }
+
+        if self.matches(&[TokenType::Throw]) {
+            return self.throw_statement();
+        }
+
+        if self.matches(&[TokenType::Import]) {
+            return self.import_statement();
+        }
+
+        // `from` is only a keyword at the start of an import.
+        if self.check_word("from") && self.check_next(&TokenType::String) {
+            return self.selective_import();
+        }
+
+        if self.matches(&[TokenType::Try]) {
+            return self.try_statement();
+        }
+
         if self.matches(&[TokenType::While]) {
             return self.while_statement();
         }
 
-        if self.matches(&[TokenType::LeftBrace]) {
+        if self.check(&TokenType::LeftBrace) && !self.starts_map() {
+            self.advance();
             let stmts = self.block()?;
-            return Ok(Stmt::Block(Box::new(stmts)));
+            return Ok(Stmt::Block(stmts));
         }
 
         self.expression_statement()
     }
 
-    fn for_statement(&mut self) -> Result<Stmt, String> {
+    fn for_statement(&mut self) -> Result<Stmt, Diagnostic> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'for'.")?;
 
         let initializer = if self.matches(&[TokenType::Semicolon]) {
@@ -185,7 +287,7 @@ impl<'a> Parser<'a> {
         };
 
         let condition = if self.check(&TokenType::Semicolon) {
-            Expr::Literal(Value::Bool(true))
+            Expr::Literal(Value::Bool(true), self.peek().span.clone())
         } else {
             self.expression()?
         };
@@ -198,41 +300,30 @@ impl<'a> Parser<'a> {
         };
         self.consume(TokenType::RightParen, "Expect ')' after for clauses.")?;
 
-        let mut body = self.statement()?;
-
-        if let Some(i) = increment {
-            body = Stmt::Block(Box::new(vec![body, Stmt::Expression(i)]));
-        }
-
-        body = Stmt::While(condition, Box::new(body));
+        let body = self.statement()?;
+        let mut body = Stmt::While(condition, Box::new(body), increment);
 
         if let Some(i) = initializer {
-            body = Stmt::Block(Box::new(vec![i, body]));
+            body = Stmt::Block(vec![i, body]);
         }
 
         Ok(body)
     }
 
-    fn if_statement(&mut self) -> Result<Stmt, String> {
+    fn if_statement(&mut self) -> Result<Stmt, Diagnostic> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'if'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after 'if' condition.")?;
 
         if self.peek().token_type != TokenType::LeftBrace {
-            return Err(format!(
-                "[line {}] Expect {{ before if body",
-                self.peek().line_number
-            ));
+            return Err(self.error_at_peek("Expect { before if body"));
         }
         let then_branch = self.statement()?;
 
         let mut else_branch = None;
         if self.matches(&[TokenType::Else]) {
             if self.peek().token_type != TokenType::LeftBrace {
-                return Err(format!(
-                    "[line {}] Expect {{ before else body",
-                    self.peek().line_number
-                ));
+                return Err(self.error_at_peek("Expect { before else body"));
             }
             let e = self.statement()?;
             else_branch = Some(Box::new(e));
@@ -241,84 +332,172 @@ impl<'a> Parser<'a> {
         Ok(Stmt::If(condition, Box::new(then_branch), else_branch))
     }
 
-    fn print_statement(&mut self) -> Result<Stmt, String> {
+    fn print_statement(&mut self) -> Result<Stmt, Diagnostic> {
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
         Ok(Stmt::Print(expr))
     }
 
-    fn return_statement(&mut self) -> Result<Stmt, String> {
+    fn return_statement(&mut self) -> Result<Stmt, Diagnostic> {
         let token = self.previous().clone();
-        let value;
-        if !self.check(&TokenType::Semicolon) {
-            value = Some(self.expression()?);
+        let value = if !self.check(&TokenType::Semicolon) {
+            Some(self.expression()?)
         } else {
-            value = None;
-        }
+            None
+        };
 
-        self.consume(
-            TokenType::Semicolon,
-            format!(
-                "[line {}] Expect ';' after return value.",
-                token.line_number
-            )
-            .as_str(),
-        )?;
+        self.consume(TokenType::Semicolon, "Expect ';' after return value.")?;
 
         let return_stmt = Stmt::Return(token, value);
         Ok(return_stmt)
     }
 
-    fn while_statement(&mut self) -> Result<Stmt, String> {
+    /// Parses `break;` or `continue;` after its keyword.
+    fn loop_control_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().clone();
+        let msg = format!("Expect ';' after '{}'.", keyword.name);
+        self.consume(TokenType::Semicolon, &msg)?;
+
+        if keyword.token_type == TokenType::Break {
+            Ok(Stmt::Break(keyword))
+        } else {
+            Ok(Stmt::Continue(keyword))
+        }
+    }
+
+    /// Parses `import "path" as name;` after its keyword.
+    fn import_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().clone();
+        let path = self
+            .consume(TokenType::String, "Expect module path after 'import'.")?
+            .clone();
+        if !self.check_word("as") {
+            return Err(self.error_at_peek("Expect 'as' after module path."));
+        }
+        self.advance();
+        let name = self
+            .consume(TokenType::Identifier, "Expect module name after 'as'.")?
+            .clone();
+        self.consume(TokenType::Semicolon, "Expect ';' after import.")?;
+        Ok(Stmt::Import(keyword, path, name))
+    }
+
+    /// Parses `from "path" import a, b;`.
+    fn selective_import(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.advance().clone();
+        let path = self.advance().clone();
+        self.consume(TokenType::Import, "Expect 'import' after module path.")?;
+        let mut names = vec![];
+        loop {
+            let name = self.consume(TokenType::Identifier, "Expect name to import.")?;
+            names.push(name.clone());
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+        self.consume(TokenType::Semicolon, "Expect ';' after import.")?;
+        Ok(Stmt::FromImport(keyword, path, names))
+    }
+
+    fn throw_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().clone();
+        let value = self.expression()?;
+        self.consume(TokenType::Semicolon, "Expect ';' after thrown value.")?;
+        Ok(Stmt::Throw(keyword, value))
+    }
+
+    /// Parses `try { } catch (name) { } finally { }` after its keyword;
+    /// either clause may be left out, but not both.
+    fn try_statement(&mut self) -> Result<Stmt, Diagnostic> {
+        let keyword = self.previous().clone();
+        self.consume(TokenType::LeftBrace, "Expect '{' after 'try'.")?;
+        let body = self.block()?;
+
+        let catch = if self.matches(&[TokenType::Catch]) {
+            self.consume(TokenType::LeftParen, "Expect '(' after 'catch'.")?;
+            let name = self
+                .consume(TokenType::Identifier, "Expect error variable name.")?
+                .clone();
+            self.consume(TokenType::RightParen, "Expect ')' after error variable.")?;
+            self.consume(TokenType::LeftBrace, "Expect '{' before catch body.")?;
+            Some((name, self.block()?))
+        } else {
+            None
+        };
+
+        let finally = if self.matches(&[TokenType::Finally]) {
+            self.consume(TokenType::LeftBrace, "Expect '{' after 'finally'.")?;
+            Some(self.block()?)
+        } else {
+            None
+        };
+
+        if catch.is_none() && finally.is_none() {
+            return Err(self.error_at_peek("Expect 'catch' or 'finally' after try block."));
+        }
+        Ok(Stmt::Try(keyword, body, catch, finally))
+    }
+
+    fn while_statement(&mut self) -> Result<Stmt, Diagnostic> {
         self.consume(TokenType::LeftParen, "Expect '(' after 'while'.")?;
         let condition = self.expression()?;
         self.consume(TokenType::RightParen, "Expect ')' after condition.")?;
 
         if self.peek().token_type != TokenType::LeftBrace {
-            return Err(format!(
-                "[line {}] Expect {{ before while body",
-                self.peek().line_number
-            ));
+            return Err(self.error_at_peek("Expect { before while body"));
         }
 
         let body = self.statement()?;
-        let while_stmt = Stmt::While(condition, Box::new(body));
+        let while_stmt = Stmt::While(condition, Box::new(body), None);
         Ok(while_stmt)
     }
 
-    fn expression_statement(&mut self) -> Result<Stmt, String> {
+    fn expression_statement(&mut self) -> Result<Stmt, Diagnostic> {
         let expr = self.expression()?;
         self.consume(TokenType::Semicolon, "Expect ';' after value.")?;
 
         Ok(Stmt::Expression(expr))
     }
 
-    fn block(&mut self) -> Result<Vec<Stmt>, String> {
+    fn block(&mut self) -> Result<Vec<Stmt>, Diagnostic> {
         let mut statements: Vec<Stmt> = Vec::new();
 
         while !self.check(&TokenType::RightBrace) && !self.is_at_end() {
-            statements.push(self.declaration()?)
+            // A statement with an error is reported and skipped, so that the
+            // rest of the block is still parsed instead of read as the
+            // statements after it.
+            match self.declaration() {
+                Ok(stmt) => statements.push(stmt),
+                Err(error) => self.errors.push(error),
+            }
         }
 
         self.consume(TokenType::RightBrace, "Expect '}' after block.")?;
         Ok(statements)
     }
 
-    fn expression(&mut self) -> Result<Expr, String> {
+    fn expression(&mut self) -> Result<Expr, Diagnostic> {
         self.assignment()
     }
 
-    fn assignment(&mut self) -> Result<Expr, String> {
+    fn assignment(&mut self) -> Result<Expr, Diagnostic> {
         let expr = self.logic_or()?;
 
         if self.matches(&[TokenType::Equal]) {
             let token_equal = self.previous().clone();
             let assignment = self.assignment()?;
             return match expr {
-                Expr::Variable(t) => Ok(Expr::Assign(t, Box::new(assignment))),
-                _ => Err(format!(
-                    "[line {}] Invalid assignment target.",
-                    token_equal.line_number
+                Expr::Variable(_, t) => Ok(Expr::Assign(next_expr_id(), t, Box::new(assignment))),
+                Expr::Get(object, name) => Ok(Expr::Set(object, name, Box::new(assignment))),
+                Expr::Subscript(object, bracket, index) => Ok(Expr::SetSubscript(
+                    object,
+                    bracket,
+                    index,
+                    Box::new(assignment),
+                )),
+                _ => Err(Diagnostic::new(
+                    &token_equal.span,
+                    "Invalid assignment target.",
                 )),
             };
         }
@@ -326,31 +505,31 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn logic_or(&mut self) -> Result<Expr, String> {
-        let left = self.logic_and()?;
+    fn logic_or(&mut self) -> Result<Expr, Diagnostic> {
+        let mut expr = self.logic_and()?;
 
         while self.matches(&[TokenType::Or]) {
             let operator = self.previous().clone();
             let right = self.logic_and()?;
-            return Ok(Expr::Logical(Box::new(left), operator, Box::new(right)));
+            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
         }
 
-        Ok(left)
+        Ok(expr)
     }
 
-    fn logic_and(&mut self) -> Result<Expr, String> {
-        let left = self.equality()?;
+    fn logic_and(&mut self) -> Result<Expr, Diagnostic> {
+        let mut expr = self.equality()?;
 
         while self.matches(&[TokenType::And]) {
             let operator = self.previous().clone();
             let right = self.equality()?;
-            return Ok(Expr::Logical(Box::new(left), operator, Box::new(right)));
+            expr = Expr::Logical(Box::new(expr), operator, Box::new(right));
         }
 
-        Ok(left)
+        Ok(expr)
     }
 
-    fn equality(&mut self) -> Result<Expr, String> {
+    fn equality(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.comparison()?;
 
         while self.matches(&[TokenType::BangEqual, TokenType::EqualEqual]) {
@@ -362,7 +541,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn comparison(&mut self) -> Result<Expr, String> {
+    fn comparison(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.term()?;
 
         let comparison_tokens = &[
@@ -380,7 +559,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn term(&mut self) -> Result<Expr, String> {
+    fn term(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.factor()?;
 
         while self.matches(&[TokenType::Minus, TokenType::Plus]) {
@@ -392,7 +571,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn factor(&mut self) -> Result<Expr, String> {
+    fn factor(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.unary()?;
 
         while self.matches(&[TokenType::Slash, TokenType::Star]) {
@@ -404,7 +583,7 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn unary(&mut self) -> Result<Expr, String> {
+    fn unary(&mut self) -> Result<Expr, Diagnostic> {
         if self.matches(&[TokenType::Bang, TokenType::Minus]) {
             let operator = self.previous().clone();
             let right = self.unary()?;
@@ -414,12 +593,18 @@ impl<'a> Parser<'a> {
         self.call()
     }
 
-    fn call(&mut self) -> Result<Expr, String> {
+    fn call(&mut self) -> Result<Expr, Diagnostic> {
         let mut expr = self.primary()?;
 
         loop {
             if self.matches(&[TokenType::LeftParen]) {
                 expr = self.finish_call(expr)?;
+            } else if self.matches(&[TokenType::Dot]) {
+                self.consume(TokenType::Identifier, "Expect property name after '.'.")?;
+                let name = self.previous().clone();
+                expr = Expr::Get(Box::new(expr), name);
+            } else if self.matches(&[TokenType::LeftBracket]) {
+                expr = self.finish_subscript(expr)?;
             } else {
                 break;
             }
@@ -428,17 +613,37 @@ impl<'a> Parser<'a> {
         Ok(expr)
     }
 
-    fn finish_call(&mut self, callee: Expr) -> Result<Expr, String> {
+    fn finish_subscript(&mut self, object: Expr) -> Result<Expr, Diagnostic> {
+        let start = if self.check(&TokenType::Colon) {
+            None
+        } else {
+            let index = self.expression()?;
+            if !self.check(&TokenType::Colon) {
+                let bracket = self.consume(TokenType::RightBracket, "Expect ']' after index.")?;
+                let bracket = bracket.clone();
+                return Ok(Expr::Subscript(Box::new(object), bracket, Box::new(index)));
+            }
+            Some(Box::new(index))
+        };
+        self.consume(TokenType::Colon, "Expect ':' in slice.")?;
+
+        let end = if self.check(&TokenType::RightBracket) {
+            None
+        } else {
+            Some(Box::new(self.expression()?))
+        };
+        let bracket = self.consume(TokenType::RightBracket, "Expect ']' after slice.")?;
+        Ok(Expr::Slice(Box::new(object), bracket.clone(), start, end))
+    }
+
+    fn finish_call(&mut self, callee: Expr) -> Result<Expr, Diagnostic> {
         let mut arguments = vec![];
 
         if !self.check(&TokenType::RightParen) {
             arguments.push(self.expression()?);
             while self.matches(&[TokenType::Comma]) {
                 if arguments.len() >= 255 {
-                    return Err(format!(
-                        "[line {}] Can't have more than 255 arguments.",
-                        self.peek().line_number
-                    ));
+                    return Err(self.error_at_peek("Can't have more than 255 arguments."));
                 }
 
                 arguments.push(self.expression()?)
@@ -446,11 +651,11 @@ impl<'a> Parser<'a> {
         }
 
         let paren = self.consume(TokenType::RightParen, "Expect ')' after arguments.")?;
-        let func = Expr::Call(Box::new(callee), paren.clone(), Box::new(arguments));
+        let func = Expr::Call(Box::new(callee), paren.clone(), arguments);
         Ok(func)
     }
 
-    fn primary(&mut self) -> Result<Expr, String> {
+    fn primary(&mut self) -> Result<Expr, Diagnostic> {
         if self.matches(&[
             TokenType::Nil,
             TokenType::True,
@@ -459,18 +664,38 @@ impl<'a> Parser<'a> {
             TokenType::Number,
         ]) {
             let token = self.previous().clone();
-            return match token.token_type {
-                TokenType::Nil => Ok(Expr::Literal(Value::Nil)),
-                TokenType::True => Ok(Expr::Literal(Value::Bool(true))),
-                TokenType::False => Ok(Expr::Literal(Value::Bool(false))),
-                TokenType::String => Ok(Expr::Literal(Value::String(
-                    token.value.get_string().unwrap(),
-                ))),
-                TokenType::Number => Ok(Expr::Literal(Value::Number(
-                    token.value.get_number().unwrap(),
-                ))),
-                _ => Err("Invalid value for literal type".to_string()),
+            let value = match token.token_type {
+                TokenType::Nil => Value::Nil,
+                TokenType::True => Value::Bool(true),
+                TokenType::False => Value::Bool(false),
+                TokenType::String => Value::String(token.value.get_string().unwrap()),
+                TokenType::Number => Value::Number(token.value.get_number().unwrap()),
+                _ => {
+                    let msg = "Invalid value for literal type";
+                    return Err(Diagnostic::new(&token.span, msg));
+                }
             };
+            return Ok(Expr::Literal(value, token.span));
+        }
+
+        if self.matches(&[TokenType::Interpolation]) {
+            return self.interpolation();
+        }
+
+        if self.matches(&[TokenType::LeftBracket]) {
+            return self.list();
+        }
+
+        if self.matches(&[TokenType::LeftBrace]) {
+            return self.map();
+        }
+
+        if self.matches(&[TokenType::Fun]) {
+            return self.lambda();
+        }
+
+        if self.check(&TokenType::LeftParen) && self.starts_arrow_function() {
+            return self.arrow_function();
         }
 
         if self.matches(&[TokenType::LeftParen]) {
@@ -480,27 +705,163 @@ impl<'a> Parser<'a> {
         }
 
         if self.matches(&[TokenType::RightParen]) {
-            return Err(format!(
-                "[line {}] found not expected ')'",
-                self.previous().line_number
+            return Err(Diagnostic::new(
+                &self.previous().span,
+                "found not expected ')'",
             ));
         }
 
+        if self.matches(&[TokenType::Super]) {
+            let keyword = self.previous().clone();
+            self.consume(TokenType::Dot, "Expect '.' after 'super'.")?;
+            self.consume(TokenType::Identifier, "Expect superclass method name.")?;
+            let method = self.previous().clone();
+            return Ok(Expr::Super(next_expr_id(), keyword, method));
+        }
+
+        if self.matches(&[TokenType::This]) {
+            return Ok(Expr::This(next_expr_id(), self.previous().clone()));
+        }
+
         if self.matches(&[TokenType::Identifier]) {
-            return Ok(Expr::Variable(self.previous().clone()));
+            return Ok(Expr::Variable(next_expr_id(), self.previous().clone()));
+        }
+
+        Err(self.error_at_peek("Expect expression."))
+    }
+
+    /// Parses an interpolated string after its first part: the scanner
+    /// emits an `Interpolation` token for every part followed by an
+    /// expression, and a `String` token for the last part.
+    fn interpolation(&mut self) -> Result<Expr, Diagnostic> {
+        let start = self.previous().span.clone();
+        let mut parts = vec![];
+        loop {
+            let token = self.previous().clone();
+            let text = token.value.get_string().unwrap_or_default();
+            if !text.is_empty() {
+                parts.push(Expr::Literal(Value::String(text), token.span.clone()));
+            }
+            if token.token_type == TokenType::String {
+                return Ok(Expr::Interpolation(parts, start.to(&token.span)));
+            }
+
+            // The rest of the string right after `${`, as in "${}".
+            if self.peek().name.starts_with('}') {
+                return Err(self.error_at_peek("Expect expression."));
+            }
+            parts.push(self.expression()?);
+            if !self.matches(&[TokenType::Interpolation, TokenType::String]) {
+                return Err(self.error_at_peek("Expect '}' after interpolated expression."));
+            }
+        }
+    }
+
+    /// Parses the elements of a list literal after its opening bracket. A
+    /// trailing comma is allowed.
+    fn list(&mut self) -> Result<Expr, Diagnostic> {
+        let open = self.previous().span.clone();
+        let mut elements = vec![];
+        while !self.check(&TokenType::RightBracket) {
+            elements.push(self.expression()?);
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
         }
 
-        Err(format!(
-            "[line {}] Expect expression.",
-            self.peek().line_number
+        let close = self.consume(TokenType::RightBracket, "Expect ']' after list elements.")?;
+        Ok(Expr::List(elements, open.to(&close.span)))
+    }
+
+    /// Parses the entries of a map literal after its opening brace. A
+    /// trailing comma is allowed.
+    fn map(&mut self) -> Result<Expr, Diagnostic> {
+        let open = self.previous().span.clone();
+        let mut entries = vec![];
+        while !self.check(&TokenType::RightBrace) {
+            let key = self.expression()?;
+            self.consume(TokenType::Colon, "Expect ':' after map key.")?;
+            entries.push((key, self.expression()?));
+            if !self.matches(&[TokenType::Comma]) {
+                break;
+            }
+        }
+
+        let close = self.consume(TokenType::RightBrace, "Expect '}' after map entries.")?;
+        Ok(Expr::Map(entries, open.to(&close.span)))
+    }
+
+    /// Parses `fun (params) { body }` after its keyword.
+    fn lambda(&mut self) -> Result<Expr, Diagnostic> {
+        let name = anonymous(self.previous());
+        self.consume(TokenType::LeftParen, "Expect '(' after 'fun'.")?;
+        let params = self.parameters()?;
+        self.consume(TokenType::LeftBrace, "Expect '{' before function body.")?;
+        let body = self.block()?;
+        Ok(Expr::Lambda(name, params, body))
+    }
+
+    /// Parses `(params) => value`, whose body returns `value`.
+    fn arrow_function(&mut self) -> Result<Expr, Diagnostic> {
+        let name = anonymous(self.advance());
+        let params = self.parameters()?;
+        let arrow = self
+            .consume(TokenType::Arrow, "Expect '=>' after parameters.")?
+            .clone();
+        // `{` would otherwise be read as a map literal and fail at its first
+        // statement with a confusing message about map keys.
+        if self.check(&TokenType::LeftBrace) && !self.starts_map() {
+            let msg = "Arrow function bodies are expressions; use fun (...) { ... } for a block.";
+            return Err(self.error_at_peek(msg));
+        }
+        let value = self.expression()?;
+        Ok(Expr::Lambda(
+            name,
+            params,
+            vec![Stmt::Return(arrow, Some(value))],
         ))
     }
 
-    fn synchronize(&mut self) {
-        self.advance();
+    /// Whether the `(` at the current token opens the parameter list of an
+    /// arrow function: identifiers separated by commas, then `)` and `=>`.
+    fn starts_arrow_function(&self) -> bool {
+        let mut i = self.current + 1;
+        let token_type = |i: usize| self.tokens.get(i).map(|token| &token.token_type);
+        if token_type(i) != Some(&TokenType::RightParen) {
+            loop {
+                if token_type(i) != Some(&TokenType::Identifier) {
+                    return false;
+                }
+                i += 1;
+                if token_type(i) != Some(&TokenType::Comma) {
+                    break;
+                }
+                i += 1;
+            }
+        }
+        token_type(i) == Some(&TokenType::RightParen)
+            && token_type(i + 1) == Some(&TokenType::Arrow)
+    }
+
+    /// Whether the `{` starting a statement opens a map literal rather than
+    /// a block: only a map has a `:` after its first token, as in
+    /// `{"a": 1}.len();`. `{}` is an empty block.
+    fn starts_map(&self) -> bool {
+        self.tokens
+            .get(self.current + 2)
+            .is_some_and(|token| token.token_type == TokenType::Colon)
+    }
+
+    /// Skips the rest of the statement that started at `start` and failed,
+    /// up to a `;` or the keyword of the next statement. The keyword the
+    /// error was found at is kept, unless the statement starts with it.
+    fn synchronize(&mut self, start: usize) {
+        if self.current == start {
+            self.advance();
+        }
 
         while !self.is_at_end() {
-            if self.previous().token_type == TokenType::Semicolon {
+            if self.current > start && self.previous().token_type == TokenType::Semicolon {
                 return;
             }
             let switched = [
@@ -512,12 +873,27 @@ impl<'a> Parser<'a> {
                 TokenType::While,
                 TokenType::Print,
                 TokenType::Return,
+                TokenType::Try,
+                TokenType::Throw,
+                TokenType::Break,
+                TokenType::Continue,
+                TokenType::Import,
+                TokenType::Export,
             ];
             if switched.contains(&self.peek().token_type) {
                 return;
             }
+            if self.check_word("from") && self.check_next(&TokenType::String) {
+                return;
+            }
 
             self.advance();
         }
     }
 }
+
+/// The name token of an anonymous function starting at `start`.
+fn anonymous(start: &Token) -> Token {
+    let name = "anonymous".to_string();
+    Token::new(TokenType::Identifier, name, Value::Nil, start.span.clone())
+}
@@ -0,0 +1,206 @@
+use crate::diagnostics::{Diagnostic, ErrorKind, Reporter};
+use crate::errors::ValueError;
+use crate::interpreter::Interpreter;
+use crate::models::statements::Stmt;
+use crate::models::token_types::TokenType;
+use crate::models::values::Value;
+use crate::native_funcs::io::FS_FUNCTIONS;
+use crate::parser::Parser;
+use crate::resolver::Resolver;
+use crate::scanner::{is_unterminated, Scanner};
+use std::io::{self, Write};
+use std::process;
+
+const PROMPT: &str = "> ";
+const CONTINUATION_PROMPT: &str = "... ";
+const HELP: &str = "\
+:help     show this message
+:env      print the variables of the current environment
+:history  list the inputs entered in this session
+:reset    drop all definitions and start from a fresh interpreter
+:quit     leave the repl";
+
+/// Interactive session that keeps one interpreter, and therefore one set of
+/// globals, alive across every line the user types.
+pub struct Repl {
+    interpreter: Interpreter,
+    /// Holds the text of every input, so errors in functions defined by an
+    /// earlier one still show their source.
+    reporter: Reporter,
+    history: Vec<String>,
+    /// Whether scripts have the natives that read and write files.
+    fs_access: bool,
+}
+
+impl Default for Repl {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Repl {
+    pub fn new() -> Self {
+        Repl {
+            interpreter: Interpreter::new(),
+            reporter: Reporter::default(),
+            history: Vec::new(),
+            fs_access: true,
+        }
+    }
+
+    /// Defines or removes the natives that read and write files, also in
+    /// the interpreters `:reset` starts, and allows or forbids importing
+    /// module files.
+    pub fn set_fs_access(&mut self, allowed: bool) {
+        self.fs_access = allowed;
+        self.interpreter = self.new_interpreter();
+    }
+
+    fn new_interpreter(&self) -> Interpreter {
+        let mut interpreter = Interpreter::new();
+        if !self.fs_access {
+            for name in FS_FUNCTIONS {
+                interpreter.undefine_native(name);
+            }
+        }
+        interpreter
+    }
+
+    pub fn run(&mut self) {
+        let mut buffer = String::new();
+
+        loop {
+            let prompt = if buffer.is_empty() {
+                PROMPT
+            } else {
+                CONTINUATION_PROMPT
+            };
+            print!("{prompt}");
+            io::stdout().flush().unwrap_or_default();
+
+            // Reads through `Stdin` line by line rather than holding its
+            // lock, which `input` takes to read from the same stream.
+            let mut line = String::new();
+            match io::stdin().read_line(&mut line) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let end = line.trim_end_matches(['\n', '\r']).len();
+                    line.truncate(end);
+                }
+            }
+
+            if buffer.is_empty() {
+                match line.trim() {
+                    "" => continue,
+                    ":quit" | ":q" => break,
+                    ":help" => {
+                        println!("{HELP}");
+                        continue;
+                    }
+                    ":env" => {
+                        print!("{}", self.interpreter.environment.borrow());
+                        continue;
+                    }
+                    ":history" => {
+                        for (i, entry) in self.history.iter().enumerate() {
+                            println!("{:>4}  {}", i + 1, entry.replace('\n', "\n      "));
+                        }
+                        continue;
+                    }
+                    ":reset" => {
+                        self.interpreter = self.new_interpreter();
+                        continue;
+                    }
+                    command if command.starts_with(':') => {
+                        eprintln!("Unknown command {command}, try :help");
+                        continue;
+                    }
+                    _ => {}
+                }
+            }
+
+            buffer.push_str(&line);
+            buffer.push('\n');
+            if !is_complete(&buffer) {
+                continue;
+            }
+
+            let source = std::mem::take(&mut buffer);
+            self.history.push(source.trim_end().to_string());
+            let file = format!("<repl-{}>", self.history.len());
+            self.eval(&file, &source);
+        }
+    }
+
+    /// Runs the input `source`, whose diagnostics name it `file`.
+    fn eval(&mut self, file: &str, source: &str) {
+        let reporter = &self.reporter;
+        reporter.add_source(file, source);
+
+        let (tokens, errors) = Scanner::new(file, source).scan_tokens();
+        if !errors.is_empty() {
+            reporter.report(&errors);
+            return;
+        }
+
+        let mut parser = Parser::new(&tokens);
+        parser.parse();
+        if !parser.errors.is_empty() {
+            reporter.report(&parser.errors);
+            return;
+        }
+
+        let mut resolver = Resolver::new();
+        resolver.loader.no_files = !self.fs_access;
+        resolver.resolve(&parser.stmts);
+        for (module, text) in &resolver.loader.sources {
+            reporter.add_source(module, text);
+        }
+        if !resolver.errors.is_empty() {
+            reporter.report(&resolver.errors);
+            return;
+        }
+        self.interpreter.add_locals(resolver.locals);
+        self.interpreter.add_modules(resolver.loader.modules);
+
+        // A lone expression statement echoes its value, like most repls do.
+        let mut errors: Vec<Diagnostic> = Vec::new();
+        if let [Stmt::Expression(expr)] = parser.stmts.as_slice() {
+            match self.interpreter.evaluate(expr) {
+                Ok(Value::Nil) => {}
+                Ok(value) => println!("{value}"),
+                Err(ValueError::Error(error)) => errors.push(self.interpreter.stack_trace(error)),
+                Err(ValueError::Return(_) | ValueError::Break | ValueError::Continue) => {}
+            }
+        } else {
+            self.interpreter.interpret(&parser.stmts);
+        }
+
+        errors.append(&mut self.interpreter.errors);
+        if let Some(ErrorKind::Exit(status)) = errors.first().map(|error| error.kind) {
+            process::exit(status);
+        }
+        self.reporter.report(&errors);
+    }
+}
+
+/// Reports whether every `{`, `(` and `[` in `source` has been closed and no
+/// string, interpolation or block comment is left open, so the repl knows
+/// when to stop reading. It scans `source` like running it does, so the two
+/// cannot disagree on where strings and comments end.
+fn is_complete(source: &str) -> bool {
+    let (tokens, errors) = Scanner::new("", source).scan_tokens();
+    if errors.iter().any(is_unterminated) {
+        return false;
+    }
+
+    let depth: i32 = tokens
+        .iter()
+        .map(|token| match token.token_type {
+            TokenType::LeftBrace | TokenType::LeftParen | TokenType::LeftBracket => 1,
+            TokenType::RightBrace | TokenType::RightParen | TokenType::RightBracket => -1,
+            _ => 0,
+        })
+        .sum();
+    depth <= 0
+}
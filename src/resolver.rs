@@ -0,0 +1,362 @@
+use crate::diagnostics::Diagnostic;
+use crate::models::expressions::Expr;
+use crate::models::statements::Stmt;
+use crate::models::tokens::Token;
+use crate::modules::{not_exported, ModuleLoader};
+use crate::native_funcs::builtin_exports;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionType {
+    None,
+    Function,
+    Initializer,
+    Method,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ClassType {
+    None,
+    Class,
+    Subclass,
+}
+
+/// Static pass between parsing and interpreting. It computes how many
+/// environments away every local variable lives and reports the errors that
+/// can be found without running the program.
+pub struct Resolver {
+    /// Scope depth of every resolved expression id; unlisted ids are globals.
+    pub locals: HashMap<usize, usize>,
+    scopes: Vec<HashMap<String, bool>>,
+    current_function: FunctionType,
+    current_class: ClassType,
+    /// Number of loops around the statement being resolved, within the
+    /// current function.
+    loop_depth: usize,
+    /// Loads the modules the program imports while it is resolved.
+    pub loader: ModuleLoader,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Resolver {
+    /// A resolver that can import the built-in native modules.
+    pub fn new() -> Self {
+        Self::with_loader(ModuleLoader::with_natives(Rc::new(builtin_exports())))
+    }
+
+    /// A resolver that loads imported modules with `loader`, which already
+    /// holds the modules loaded so far.
+    pub fn with_loader(loader: ModuleLoader) -> Self {
+        Resolver {
+            locals: HashMap::new(),
+            scopes: Vec::new(),
+            current_function: FunctionType::None,
+            current_class: ClassType::None,
+            loop_depth: 0,
+            loader,
+            errors: Vec::new(),
+        }
+    }
+
+    pub fn resolve(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.resolve_stmt(stmt);
+        }
+    }
+
+    fn resolve_stmt(&mut self, stmt: &Stmt) {
+        match stmt {
+            Stmt::Block(stmts) => {
+                self.begin_scope();
+                self.resolve(stmts);
+                self.end_scope();
+            }
+            Stmt::Class(name, superclass, methods) => self.resolve_class(name, superclass, methods),
+            Stmt::Expression(e) | Stmt::Print(e) => self.resolve_expr(e),
+            Stmt::Function(name, params, body) => {
+                self.declare(name);
+                self.define(name);
+                self.resolve_function(params, body, FunctionType::Function);
+            }
+            Stmt::If(condition, then_branch, else_branch) => {
+                self.resolve_expr(condition);
+                self.resolve_stmt(then_branch);
+                if let Some(else_branch) = else_branch {
+                    self.resolve_stmt(else_branch);
+                }
+            }
+            Stmt::Return(keyword, value) => {
+                if self.current_function == FunctionType::None {
+                    self.error(keyword, "Can't return from top-level code.");
+                }
+
+                if let Some(value) = value {
+                    if self.current_function == FunctionType::Initializer {
+                        self.error(keyword, "Can't return a value from an initializer.");
+                    }
+                    self.resolve_expr(value);
+                }
+            }
+            Stmt::Var(name, initializer) => {
+                self.declare(name);
+                if let Some(initializer) = initializer {
+                    self.resolve_expr(initializer);
+                }
+                self.define(name);
+            }
+            Stmt::While(condition, body, increment) => {
+                self.resolve_expr(condition);
+                self.loop_depth += 1;
+                self.resolve_stmt(body);
+                self.loop_depth -= 1;
+                if let Some(increment) = increment {
+                    self.resolve_expr(increment);
+                }
+            }
+            Stmt::Break(keyword) | Stmt::Continue(keyword) => {
+                if self.loop_depth == 0 {
+                    let msg = format!("Can't use '{}' outside of a loop.", keyword.name);
+                    self.error(keyword, &msg);
+                }
+            }
+            Stmt::Export(keyword, declaration) => {
+                if !self.scopes.is_empty() {
+                    self.error(keyword, "Can only export top-level declarations.");
+                }
+                self.resolve_stmt(declaration);
+            }
+            Stmt::Import(_, path, name) => {
+                if let Err(errors) = self.loader.load(path) {
+                    self.errors.extend(errors);
+                }
+                self.declare(name);
+                self.define(name);
+            }
+            Stmt::FromImport(_, path, names) => {
+                match self.loader.load(path) {
+                    Ok(module) => {
+                        for name in names {
+                            if !module.exports.contains(&name.name) {
+                                self.error(name, &not_exported(&module.file, &name.name));
+                            }
+                        }
+                    }
+                    Err(errors) => self.errors.extend(errors),
+                }
+                for name in names {
+                    self.declare(name);
+                    self.define(name);
+                }
+            }
+            Stmt::Throw(_, value) => self.resolve_expr(value),
+            Stmt::Try(_, body, catch, finally) => {
+                self.begin_scope();
+                self.resolve(body);
+                self.end_scope();
+                if let Some((name, handler)) = catch {
+                    self.begin_scope();
+                    self.declare(name);
+                    self.define(name);
+                    self.resolve(handler);
+                    self.end_scope();
+                }
+                if let Some(finally) = finally {
+                    self.begin_scope();
+                    self.resolve(finally);
+                    self.end_scope();
+                }
+            }
+        }
+    }
+
+    fn resolve_class(&mut self, name: &Token, superclass: &Option<Expr>, methods: &[Stmt]) {
+        let enclosing_class = self.current_class;
+        self.current_class = ClassType::Class;
+
+        self.declare(name);
+        self.define(name);
+
+        if let Some(superclass) = superclass {
+            if let Expr::Variable(_, superclass_name) = superclass {
+                if superclass_name.name == name.name {
+                    self.error(superclass_name, "A class can't inherit from itself.");
+                }
+            }
+
+            self.current_class = ClassType::Subclass;
+            self.resolve_expr(superclass);
+
+            self.begin_scope();
+            self.define_name("super");
+        }
+
+        self.begin_scope();
+        self.define_name("this");
+
+        for method in methods {
+            if let Stmt::Function(method_name, params, body) = method {
+                let declaration = if method_name.name == "init" {
+                    FunctionType::Initializer
+                } else {
+                    FunctionType::Method
+                };
+                self.resolve_function(params, body, declaration);
+            }
+        }
+
+        self.end_scope();
+        if superclass.is_some() {
+            self.end_scope();
+        }
+
+        self.current_class = enclosing_class;
+    }
+
+    fn resolve_function(&mut self, params: &[Token], body: &[Stmt], function_type: FunctionType) {
+        let enclosing_function = self.current_function;
+        self.current_function = function_type;
+        let enclosing_loop_depth = std::mem::take(&mut self.loop_depth);
+
+        self.begin_scope();
+        for param in params {
+            self.declare(param);
+            self.define(param);
+        }
+        self.resolve(body);
+        self.end_scope();
+
+        self.current_function = enclosing_function;
+        self.loop_depth = enclosing_loop_depth;
+    }
+
+    fn resolve_expr(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(..) => {}
+            Expr::Logical(left, _, right) | Expr::Binary(left, _, right) => {
+                self.resolve_expr(left);
+                self.resolve_expr(right);
+            }
+            Expr::Unary(_, e) | Expr::Grouping(e) => self.resolve_expr(e),
+            Expr::Variable(id, name) => {
+                if let Some(scope) = self.scopes.last() {
+                    if scope.get(&name.name) == Some(&false) {
+                        self.error(name, "Can't read local variable in its own initializer.");
+                    }
+                }
+                self.resolve_local(*id, name);
+            }
+            Expr::Assign(id, name, value) => {
+                self.resolve_expr(value);
+                self.resolve_local(*id, name);
+            }
+            Expr::Call(callee, _, args) => {
+                self.resolve_expr(callee);
+                for arg in args {
+                    self.resolve_expr(arg);
+                }
+            }
+            Expr::Get(object, _) => self.resolve_expr(object),
+            Expr::Set(object, _, value) => {
+                self.resolve_expr(value);
+                self.resolve_expr(object);
+            }
+            Expr::List(elements, _) | Expr::Interpolation(elements, _) => {
+                for element in elements {
+                    self.resolve_expr(element);
+                }
+            }
+            Expr::Map(entries, _) => {
+                for (key, value) in entries {
+                    self.resolve_expr(key);
+                    self.resolve_expr(value);
+                }
+            }
+            Expr::Subscript(object, _, index) => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+            }
+            Expr::SetSubscript(object, _, index, value) => {
+                self.resolve_expr(object);
+                self.resolve_expr(index);
+                self.resolve_expr(value);
+            }
+            Expr::Lambda(_, params, body) => {
+                self.resolve_function(params, body, FunctionType::Function)
+            }
+            Expr::Slice(object, _, start, end) => {
+                self.resolve_expr(object);
+                for bound in [start, end].into_iter().flatten() {
+                    self.resolve_expr(bound);
+                }
+            }
+            Expr::Super(id, keyword, _) => {
+                match self.current_class {
+                    ClassType::None => self.error(keyword, "Can't use 'super' outside of a class."),
+                    ClassType::Class => {
+                        self.error(keyword, "Can't use 'super' in a class with no superclass.")
+                    }
+                    ClassType::Subclass => {}
+                }
+                self.resolve_local(*id, keyword);
+            }
+            Expr::This(id, keyword) => {
+                if self.current_class == ClassType::None {
+                    self.error(keyword, "Can't use 'this' outside of a class.");
+                    return;
+                }
+                self.resolve_local(*id, keyword);
+            }
+        }
+    }
+
+    fn resolve_local(&mut self, id: usize, name: &Token) {
+        for (depth, scope) in self.scopes.iter().rev().enumerate() {
+            if scope.contains_key(&name.name) {
+                self.locals.insert(id, depth);
+                return;
+            }
+        }
+        // Not found in any local scope: the variable is global.
+    }
+
+    fn begin_scope(&mut self) {
+        self.scopes.push(HashMap::new());
+    }
+
+    fn end_scope(&mut self) {
+        self.scopes.pop();
+    }
+
+    fn declare(&mut self, name: &Token) {
+        let Some(scope) = self.scopes.last_mut() else {
+            return;
+        };
+
+        if scope.contains_key(&name.name) {
+            self.error(name, "Already a variable with this name in this scope.");
+            return;
+        }
+        scope.insert(name.name.clone(), false);
+    }
+
+    fn define(&mut self, name: &Token) {
+        self.define_name(&name.name);
+    }
+
+    fn define_name(&mut self, name: &str) {
+        if let Some(scope) = self.scopes.last_mut() {
+            scope.insert(name.to_string(), true);
+        }
+    }
+
+    fn error(&mut self, token: &Token, msg: &str) {
+        self.errors.push(Diagnostic::new(&token.span, msg));
+    }
+}
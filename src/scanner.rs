@@ -1,246 +1,328 @@
-use crate::errors::ExitCode;
+use crate::diagnostics::Diagnostic;
+use crate::models::span::Span;
 use crate::models::token_types::TokenType;
 use crate::models::tokens::Token;
 use crate::models::values::Value;
+use std::rc::Rc;
+use std::str::Chars;
 
-pub fn parse_tokens(file_contents: &String) -> (Vec<Token>, i32) {
-    let mut exit_code = 0;
-    let mut tokens: Vec<Token> = vec![];
-
-    let lines = file_contents.lines();
-    for (line_number, line) in lines.enumerate() {
-        let mut chars = line.chars().peekable();
-        let line_number = line_number + 1;
-
-        'line_loop: while let Some(c) = chars.next() {
-            match c {
-                '(' => tokens.push(Token::new(
-                    TokenType::LeftParen,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                ')' => tokens.push(Token::new(
-                    TokenType::RightParen,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                '{' => tokens.push(Token::new(
-                    TokenType::LeftBrace,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                '}' => tokens.push(Token::new(
-                    TokenType::RightBrace,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                ',' => tokens.push(Token::new(
-                    TokenType::Comma,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                '.' => tokens.push(Token::new(
-                    TokenType::Dot,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                '-' => tokens.push(Token::new(
-                    TokenType::Minus,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                '+' => tokens.push(Token::new(
-                    TokenType::Plus,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                ';' => tokens.push(Token::new(
-                    TokenType::Semicolon,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                '*' => tokens.push(Token::new(
-                    TokenType::Star,
-                    c.to_string(),
-                    Value::Nil,
-                    line_number,
-                )),
-                '/' => match chars.peek() {
-                    Some('/') => {
-                        break;
-                    }
-                    _ => tokens.push(Token::new(
-                        TokenType::Slash,
-                        c.to_string(),
-                        Value::Nil,
-                        line_number,
-                    )),
-                },
-                '!' => match chars.peek() {
-                    Some('=') => {
-                        let next = chars.next().unwrap();
-                        let formatted = format!("{}{}", c, next);
-                        tokens.push(Token::new(
-                            TokenType::BangEqual,
-                            formatted.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                    _ => {
-                        tokens.push(Token::new(
-                            TokenType::Bang,
-                            c.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                },
-                '=' => match chars.peek() {
-                    Some('=') => {
-                        let next = chars.next().unwrap();
-                        let formatted = format!("{}{}", c, next);
-                        tokens.push(Token::new(
-                            TokenType::EqualEqual,
-                            formatted.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                    _ => {
-                        tokens.push(Token::new(
-                            TokenType::Equal,
-                            c.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                },
-                '>' => match chars.peek() {
-                    Some('=') => {
-                        let next = chars.next().unwrap();
-                        let formatted = format!("{}{}", c, next);
-                        tokens.push(Token::new(
-                            TokenType::GreaterEqual,
-                            formatted.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                    _ => {
-                        tokens.push(Token::new(
-                            TokenType::Greater,
-                            c.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                },
-                '<' => match chars.peek() {
-                    Some('=') => {
-                        let next = chars.next().unwrap();
-                        let formatted = format!("{}{}", c, next);
-                        tokens.push(Token::new(
-                            TokenType::LessEqual,
-                            formatted.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                    _ => {
-                        tokens.push(Token::new(
-                            TokenType::Less,
-                            c.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
-                },
-                ' ' | '\t' | '\r' => {}
-                '"' => {
-                    let mut str_value = String::new();
-                    loop {
-                        let value = chars.next();
-                        match value {
-                            Some('"') => break,
-                            Some(_) => str_value.push(value.unwrap()),
-                            None => {
-                                eprintln!("[line {}] Error: Unterminated string.", line_number);
-                                exit_code = 65;
-                                break 'line_loop;
-                            }
-                        }
-                    }
-                    tokens.push(Token::new(
-                        TokenType::String,
-                        format!("\"{}\"", str_value),
-                        Value::String(str_value),
-                        line_number,
-                    ));
+const UNTERMINATED_STRING: &str = "Error: Unterminated string.";
+const UNTERMINATED_INTERPOLATION: &str = "Error: Unterminated string interpolation.";
+const UNTERMINATED_COMMENT: &str = "Error: Unterminated block comment.";
+
+/// Whether `error` is a string, interpolation or block comment that the end
+/// of the source left open, which more input could still close.
+pub fn is_unterminated(error: &Diagnostic) -> bool {
+    [
+        UNTERMINATED_STRING,
+        UNTERMINATED_INTERPOLATION,
+        UNTERMINATED_COMMENT,
+    ]
+    .contains(&error.message.as_str())
+}
+
+/// Turns a whole source file into tokens, one character at a time, so that
+/// strings and comments may span several lines.
+pub struct Scanner<'a> {
+    file: Rc<str>,
+    source: &'a str,
+    chars: Chars<'a>,
+    /// Byte offset, line and column of the first character of the lexeme
+    /// being scanned.
+    start: usize,
+    start_line: usize,
+    start_column: usize,
+    /// Byte offset, line and column of the next character.
+    current: usize,
+    line: usize,
+    column: usize,
+    tokens: Vec<Token>,
+    errors: Vec<Diagnostic>,
+    /// The `${` of every interpolation being scanned, innermost last, with
+    /// the number of braces opened inside it and not closed yet.
+    interpolations: Vec<(Span, usize)>,
+}
+
+impl<'a> Scanner<'a> {
+    pub fn new(file: &str, source: &'a str) -> Self {
+        Scanner {
+            file: Rc::from(file),
+            source,
+            chars: source.chars(),
+            start: 0,
+            start_line: 1,
+            start_column: 1,
+            current: 0,
+            line: 1,
+            column: 1,
+            tokens: Vec::new(),
+            errors: Vec::new(),
+            interpolations: Vec::new(),
+        }
+    }
+
+    /// Scans the whole source. Every token carries its span; characters that
+    /// cannot be scanned are reported as diagnostics and skipped.
+    pub fn scan_tokens(mut self) -> (Vec<Token>, Vec<Diagnostic>) {
+        while self.peek().is_some() {
+            self.begin_lexeme();
+            self.scan_token();
+        }
+
+        for (span, _) in std::mem::take(&mut self.interpolations) {
+            self.errors
+                .push(Diagnostic::new(&span, UNTERMINATED_INTERPOLATION));
+        }
+        self.begin_lexeme();
+        self.add_token(TokenType::Eof, Value::Nil);
+        (self.tokens, self.errors)
+    }
+
+    fn begin_lexeme(&mut self) {
+        self.start = self.current;
+        self.start_line = self.line;
+        self.start_column = self.column;
+    }
+
+    fn scan_token(&mut self) {
+        let Some(c) = self.advance() else {
+            return;
+        };
+        match c {
+            '(' => self.add_token(TokenType::LeftParen, Value::Nil),
+            ')' => self.add_token(TokenType::RightParen, Value::Nil),
+            '{' => {
+                if let Some((_, braces)) = self.interpolations.last_mut() {
+                    *braces += 1;
+                }
+                self.add_token(TokenType::LeftBrace, Value::Nil);
+            }
+            '}' => match self.interpolations.last_mut() {
+                // The end of an interpolation: the string goes on.
+                Some((_, 0)) => {
+                    self.interpolations.pop();
+                    self.string();
                 }
-                token if token.is_digit(10) => {
-                    let mut num_value = String::from(token);
-                    let mut is_dot = false;
-
-                    while let Some(t) = chars.peek() {
-                        if t.is_digit(10) {
-                            num_value.push(*t);
-                            chars.next();
-                        } else if *t == '.' && !is_dot {
-                            num_value.push(*t);
-                            chars.next();
-                            is_dot = true;
-                        } else {
-                            break;
-                        }
+                Some((_, braces)) => {
+                    *braces -= 1;
+                    self.add_token(TokenType::RightBrace, Value::Nil);
+                }
+                None => self.add_token(TokenType::RightBrace, Value::Nil),
+            },
+            '[' => self.add_token(TokenType::LeftBracket, Value::Nil),
+            ']' => self.add_token(TokenType::RightBracket, Value::Nil),
+            ':' => self.add_token(TokenType::Colon, Value::Nil),
+            ',' => self.add_token(TokenType::Comma, Value::Nil),
+            '.' => self.add_token(TokenType::Dot, Value::Nil),
+            '-' => self.add_token(TokenType::Minus, Value::Nil),
+            '+' => self.add_token(TokenType::Plus, Value::Nil),
+            ';' => self.add_token(TokenType::Semicolon, Value::Nil),
+            '*' => self.add_token(TokenType::Star, Value::Nil),
+            '!' => self.add_either('=', TokenType::BangEqual, TokenType::Bang),
+            '=' if self.matches('>') => self.add_token(TokenType::Arrow, Value::Nil),
+            '=' => self.add_either('=', TokenType::EqualEqual, TokenType::Equal),
+            '>' => self.add_either('=', TokenType::GreaterEqual, TokenType::Greater),
+            '<' => self.add_either('=', TokenType::LessEqual, TokenType::Less),
+            '/' => {
+                if self.matches('/') {
+                    while self.peek().is_some_and(|c| c != '\n') {
+                        self.advance();
                     }
+                } else if self.matches('*') {
+                    self.block_comment();
+                } else {
+                    self.add_token(TokenType::Slash, Value::Nil);
+                }
+            }
+            ' ' | '\t' | '\r' | '\n' => {}
+            '"' => self.string(),
+            c if c.is_ascii_digit() => self.number(),
+            c if c.is_alphanumeric() || c == '_' => self.identifier(),
+            c => self.error(format!("Error: Unexpected character: {c}")),
+        }
+    }
 
-                    let number: f64 = num_value.clone().parse().unwrap();
-                    tokens.push(Token::new(
-                        TokenType::Number,
-                        number.to_string(),
-                        Value::Number(number),
-                        line_number,
-                    ));
-                    if num_value.ends_with(".") {
-                        tokens.push(Token::new(
-                            TokenType::Dot,
-                            '.'.to_string(),
-                            Value::Nil,
-                            line_number,
-                        ));
-                    }
+    /// Skips a `/* ... */` comment. Comments nest, so every `/*` inside needs
+    /// its own `*/`.
+    fn block_comment(&mut self) {
+        let mut depth = 1;
+        while depth > 0 {
+            match self.advance() {
+                Some('/') if self.matches('*') => depth += 1,
+                Some('*') if self.matches('/') => depth -= 1,
+                Some(_) => {}
+                None => {
+                    self.error(UNTERMINATED_COMMENT);
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Scans a string literal, or the part of one up to the next `${`,
+    /// which starts an interpolation: the tokens of the expression follow,
+    /// then the rest of the string from its closing `}`.
+    fn string(&mut self) {
+        let mut value = String::new();
+        loop {
+            match self.advance() {
+                Some('"') => break,
+                Some('$') if self.matches('{') => {
+                    self.add_token(TokenType::Interpolation, Value::String(value));
+                    self.interpolations.push((self.span(), 0));
+                    return;
                 }
-                token if token.is_alphanumeric() || token == '_' => {
-                    let mut identifier = String::from(token);
-                    while let Some(t) = chars.peek() {
-                        if t.is_alphanumeric() || *t == '_' {
-                            identifier.push(*t);
-                            chars.next();
-                        } else {
-                            break;
-                        }
+                Some('\\') => {
+                    if let Some(c) = self.escape() {
+                        value.push(c);
                     }
-                    let token_type = TokenType::get_keyword_or_identifier(identifier.as_str());
-                    tokens.push(Token::new(token_type, identifier, Value::Nil, line_number));
                 }
-                _ => {
-                    eprintln!("[line {}] Error: Unexpected character: {}", line_number, c);
-                    exit_code = ExitCode::ExitError as i32;
+                Some(c) => value.push(c),
+                None => {
+                    self.error(UNTERMINATED_STRING);
+                    return;
                 }
             }
         }
+        self.add_token(TokenType::String, Value::String(value));
     }
 
-    tokens.push(Token::new(TokenType::Eof, "".to_string(), Value::Nil, 0));
-    (tokens, exit_code)
+    /// Decodes the escape sequence after a backslash, reporting the ones it
+    /// does not know.
+    fn escape(&mut self) -> Option<char> {
+        let start = self.current - 1;
+        let (line, column) = (self.line, self.column - 1);
+        let decoded = match self.advance() {
+            Some('n') => Some('\n'),
+            Some('t') => Some('\t'),
+            Some('r') => Some('\r'),
+            Some('0') => Some('\0'),
+            Some('"') => Some('"'),
+            Some('\\') => Some('\\'),
+            Some('$') => Some('$'),
+            Some('u') => self.unicode_escape(),
+            Some(_) => None,
+            // The end of the source is reported as an unterminated string.
+            None => return None,
+        };
+
+        if decoded.is_none() {
+            let len = self.current - start;
+            let span = Span::new(&self.file, start, len, line, column);
+            let message = format!(
+                "Error: Invalid escape sequence: {}",
+                &self.source[start..self.current]
+            );
+            self.errors.push(Diagnostic::new(&span, message));
+        }
+        decoded
+    }
+
+    /// Decodes the `{XXXX}` part of a `\u{XXXX}` escape: one to six hex digits
+    /// naming a Unicode scalar value.
+    fn unicode_escape(&mut self) -> Option<char> {
+        if !self.matches('{') {
+            return None;
+        }
+
+        let mut digits = String::new();
+        while let Some(c) = self.peek().filter(char::is_ascii_hexdigit) {
+            digits.push(c);
+            self.advance();
+        }
+        if !self.matches('}') || digits.is_empty() || digits.len() > 6 {
+            return None;
+        }
+        u32::from_str_radix(&digits, 16)
+            .ok()
+            .and_then(char::from_u32)
+    }
+
+    fn number(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+        }
+        // A dot without digits after it is a separate `.` token.
+        if self.peek() == Some('.') && self.peek_next().is_some_and(|c| c.is_ascii_digit()) {
+            self.advance();
+            while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+                self.advance();
+            }
+        }
+
+        let number: f64 = self.lexeme().parse().unwrap();
+        self.add_token(TokenType::Number, Value::Number(number));
+    }
+
+    fn identifier(&mut self) {
+        while self.peek().is_some_and(|c| c.is_alphanumeric() || c == '_') {
+            self.advance();
+        }
+        let token_type = TokenType::get_keyword_or_identifier(self.lexeme());
+        self.add_token(token_type, Value::Nil);
+    }
+
+    fn add_either(&mut self, next: char, matched: TokenType, otherwise: TokenType) {
+        let token_type = if self.matches(next) {
+            matched
+        } else {
+            otherwise
+        };
+        self.add_token(token_type, Value::Nil);
+    }
+
+    fn add_token(&mut self, token_type: TokenType, value: Value) {
+        let token = Token::new(token_type, self.lexeme().to_string(), value, self.span());
+        self.tokens.push(token);
+    }
+
+    /// Reports `message` for the lexeme scanned so far.
+    fn error(&mut self, message: impl Into<String>) {
+        let error = Diagnostic::new(&self.span(), message);
+        self.errors.push(error);
+    }
+
+    fn span(&self) -> Span {
+        let len = self.current - self.start;
+        Span::new(
+            &self.file,
+            self.start,
+            len,
+            self.start_line,
+            self.start_column,
+        )
+    }
+
+    fn lexeme(&self) -> &'a str {
+        &self.source[self.start..self.current]
+    }
+
+    fn advance(&mut self) -> Option<char> {
+        let c = self.chars.next()?;
+        self.current += c.len_utf8();
+        if c == '\n' {
+            self.line += 1;
+            self.column = 1;
+        } else {
+            self.column += 1;
+        }
+        Some(c)
+    }
+
+    fn matches(&mut self, expected: char) -> bool {
+        if self.peek() == Some(expected) {
+            self.advance();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.chars.clone().next()
+    }
+
+    fn peek_next(&self) -> Option<char> {
+        self.chars.clone().nth(1)
+    }
 }
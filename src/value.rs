@@ -0,0 +1,381 @@
+use crate::diagnostics::Failure;
+use crate::errors::Error;
+use crate::gc::Heap;
+use crate::host::FromValue;
+use crate::models::lox_map::{map_key, Entries, MapKey};
+use crate::models::values::Value as TreeValue;
+use crate::vm::value::Value as VmValue;
+use std::cell::RefCell;
+use std::fmt::{Display, Formatter};
+use std::rc::Rc;
+
+/// A value passed between a Rust program and the scripts of an `Engine`.
+/// Primitives, lists and maps are copied; every other script value, such as
+/// a function or an instance, is an `Object` handle to the script's own.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    List(Vec<Value>),
+    /// The entries of a map in insertion order.
+    Map(Vec<(Value, Value)>),
+    Object(Object),
+}
+
+/// A script value with no Rust counterpart. It can be printed, compared by
+/// identity and passed back to the engine it came from, for example to call
+/// a function a script returned.
+#[derive(Debug, Clone)]
+pub struct Object(ObjectRef);
+
+#[derive(Debug, Clone)]
+enum ObjectRef {
+    Tree(TreeValue),
+    Vm(VmValue),
+}
+
+impl PartialEq for Object {
+    fn eq(&self, other: &Object) -> bool {
+        match (&self.0, &other.0) {
+            (ObjectRef::Tree(l), ObjectRef::Tree(r)) => l.is_equal(r.clone()),
+            (ObjectRef::Vm(l), ObjectRef::Vm(r)) => l.is_equal(r),
+            _ => false,
+        }
+    }
+}
+
+impl Display for Object {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match &self.0 {
+            ObjectRef::Tree(value) => write!(f, "{value}"),
+            ObjectRef::Vm(value) => write!(f, "{value}"),
+        }
+    }
+}
+
+/// Prints like the scripts' `print` does.
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Nil => write!(f, "nil"),
+            Value::Bool(b) => write!(f, "{b}"),
+            Value::Number(n) => write!(f, "{n}"),
+            Value::String(s) => write!(f, "{s}"),
+            Value::List(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_element(f, item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Map(entries) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in entries.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write_element(f, key)?;
+                    write!(f, ": ")?;
+                    write_element(f, value)?;
+                }
+                write!(f, "}}")
+            }
+            Value::Object(object) => write!(f, "{object}"),
+        }
+    }
+}
+
+/// Writes a value inside a collection, where strings are quoted.
+fn write_element(f: &mut Formatter<'_>, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "\"{s}\""),
+        value => write!(f, "{value}"),
+    }
+}
+
+impl Value {
+    /// The name of the value's type, as conversion errors show it.
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Value::Nil => "nil",
+            Value::Bool(_) => "boolean",
+            Value::Number(_) => "number",
+            Value::String(_) => "string",
+            Value::List(_) => "list",
+            Value::Map(_) => "map",
+            Value::Object(_) => "object",
+        }
+    }
+
+    /// Copies a value of the tree-walking interpreter. A list or map that
+    /// contains itself is kept as an object where it repeats.
+    pub(crate) fn from_tree(value: &TreeValue) -> Value {
+        from_tree(value, &mut Vec::new())
+    }
+
+    pub(crate) fn to_tree(&self, heap: &mut Heap) -> Result<TreeValue, Error> {
+        Ok(match self {
+            Value::Nil => TreeValue::Nil,
+            Value::Bool(b) => TreeValue::Bool(*b),
+            Value::Number(n) => TreeValue::Number(*n),
+            Value::String(s) => TreeValue::String(s.clone()),
+            Value::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.to_tree(heap))
+                    .collect::<Result<_, _>>()?;
+                TreeValue::List(heap.alloc(RefCell::new(items)))
+            }
+            Value::Map(entries) => {
+                let mut map = Entries::default();
+                for (key, value) in entries {
+                    let key = map_key(&key.to_tree(heap)?).map_err(key_error)?;
+                    map.insert(key, value.to_tree(heap)?);
+                }
+                TreeValue::Map(heap.alloc(RefCell::new(map)))
+            }
+            Value::Object(Object(ObjectRef::Tree(value))) => value.clone(),
+            Value::Object(_) => return Err(foreign_object()),
+        })
+    }
+
+    /// Copies a value of the virtual machine, like `from_tree`.
+    pub(crate) fn from_vm(value: &VmValue) -> Value {
+        from_vm(value, &mut Vec::new())
+    }
+
+    pub(crate) fn to_vm(&self, heap: &mut Heap) -> Result<VmValue, Error> {
+        Ok(match self {
+            Value::Nil => VmValue::Nil,
+            Value::Bool(b) => VmValue::Bool(*b),
+            Value::Number(n) => VmValue::Number(*n),
+            Value::String(s) => VmValue::String(Rc::from(s.as_str())),
+            Value::List(items) => {
+                let items = items
+                    .iter()
+                    .map(|item| item.to_vm(heap))
+                    .collect::<Result<_, _>>()?;
+                VmValue::List(heap.alloc(RefCell::new(items)))
+            }
+            Value::Map(entries) => {
+                let mut map = Entries::default();
+                for (key, value) in entries {
+                    let key = key.to_vm(heap)?.to_key().map_err(key_error)?;
+                    map.insert(key, value.to_vm(heap)?);
+                }
+                VmValue::Map(heap.alloc(RefCell::new(map)))
+            }
+            Value::Object(Object(ObjectRef::Vm(value))) => value.clone(),
+            Value::Object(_) => return Err(foreign_object()),
+        })
+    }
+}
+
+/// Copies `value`; `copying` holds the lists and maps being copied around
+/// it, to detect the ones that contain themselves.
+fn from_tree(value: &TreeValue, copying: &mut Vec<*const ()>) -> Value {
+    let object = || Value::Object(Object(ObjectRef::Tree(value.clone())));
+    match value {
+        TreeValue::Nil => Value::Nil,
+        TreeValue::Bool(b) => Value::Bool(*b),
+        TreeValue::Number(n) => Value::Number(*n),
+        TreeValue::String(s) => Value::String(s.clone()),
+        TreeValue::List(list) => {
+            let address = Rc::as_ptr(list) as *const ();
+            if copying.contains(&address) {
+                return object();
+            }
+            copying.push(address);
+            let items = list
+                .borrow()
+                .iter()
+                .map(|item| from_tree(item, copying))
+                .collect();
+            copying.pop();
+            Value::List(items)
+        }
+        TreeValue::Map(map) => {
+            let address = Rc::as_ptr(map) as *const ();
+            if copying.contains(&address) {
+                return object();
+            }
+            copying.push(address);
+            let entries = map
+                .borrow()
+                .iter()
+                .map(|(key, value)| (from_key(key), from_tree(value, copying)))
+                .collect();
+            copying.pop();
+            Value::Map(entries)
+        }
+        _ => object(),
+    }
+}
+
+fn from_vm(value: &VmValue, copying: &mut Vec<*const ()>) -> Value {
+    let object = || Value::Object(Object(ObjectRef::Vm(value.clone())));
+    match value {
+        VmValue::Nil => Value::Nil,
+        VmValue::Bool(b) => Value::Bool(*b),
+        VmValue::Number(n) => Value::Number(*n),
+        VmValue::String(s) => Value::String(s.to_string()),
+        VmValue::List(list) => {
+            let address = Rc::as_ptr(list) as *const ();
+            if copying.contains(&address) {
+                return object();
+            }
+            copying.push(address);
+            let items = list
+                .borrow()
+                .iter()
+                .map(|item| from_vm(item, copying))
+                .collect();
+            copying.pop();
+            Value::List(items)
+        }
+        VmValue::Map(map) => {
+            let address = Rc::as_ptr(map) as *const ();
+            if copying.contains(&address) {
+                return object();
+            }
+            copying.push(address);
+            let entries = map
+                .borrow()
+                .iter()
+                .map(|(key, value)| (from_key(key), from_vm(value, copying)))
+                .collect();
+            copying.pop();
+            Value::Map(entries)
+        }
+        _ => object(),
+    }
+}
+
+fn from_key(key: &MapKey) -> Value {
+    match key {
+        MapKey::Nil => Value::Nil,
+        MapKey::Bool(b) => Value::Bool(*b),
+        MapKey::Number(bits) => Value::Number(f64::from_bits(*bits)),
+        MapKey::String(s) => Value::String(s.to_string()),
+    }
+}
+
+fn key_error((_, message): Failure) -> Error {
+    Error::Conversion(message.to_string())
+}
+
+fn foreign_object() -> Error {
+    Error::Conversion("Object belongs to an engine with another backend.".to_string())
+}
+
+impl From<()> for Value {
+    fn from(_: ()) -> Self {
+        Value::Nil
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(n: f64) -> Self {
+        Value::Number(n)
+    }
+}
+
+impl From<i32> for Value {
+    fn from(n: i32) -> Self {
+        Value::Number(n.into())
+    }
+}
+
+impl From<i64> for Value {
+    fn from(n: i64) -> Self {
+        Value::Number(n as f64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(s: &str) -> Self {
+        Value::String(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::String(s)
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Nil, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(items: Vec<T>) -> Self {
+        Value::List(items.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Converts like a host function parameter of type `T`.
+fn convert<T: FromValue>(value: Value) -> Result<T, Error> {
+    let type_name = value.type_name();
+    T::from_value(value)
+        .ok_or_else(|| Error::Conversion(format!("Expected {}, got {type_name}.", T::EXPECTED)))
+}
+
+impl TryFrom<Value> for bool {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        convert(value)
+    }
+}
+
+impl TryFrom<Value> for f64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        convert(value)
+    }
+}
+
+impl TryFrom<Value> for i64 {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        convert(value)
+    }
+}
+
+impl TryFrom<Value> for String {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        convert(value)
+    }
+}
+
+impl<T: TryFrom<Value, Error = Error>> TryFrom<Value> for Vec<T> {
+    type Error = Error;
+
+    fn try_from(value: Value) -> Result<Self, Error> {
+        match value {
+            Value::List(items) => items.into_iter().map(T::try_from).collect(),
+            value => Err(Error::Conversion(format!(
+                "Expected a list, got {}.",
+                value.type_name()
+            ))),
+        }
+    }
+}
@@ -0,0 +1,215 @@
+use crate::models::span::Span;
+use crate::vm::value::Value;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u8)]
+pub enum OpCode {
+    Constant,
+    Nil,
+    True,
+    False,
+    Pop,
+    GetLocal,
+    SetLocal,
+    GetGlobal,
+    DefineGlobal,
+    SetGlobal,
+    GetUpvalue,
+    SetUpvalue,
+    GetProperty,
+    SetProperty,
+    GetSuper,
+    Equal,
+    Greater,
+    GreaterEqual,
+    Less,
+    LessEqual,
+    Add,
+    Subtract,
+    Multiply,
+    Divide,
+    Not,
+    Negate,
+    Print,
+    Jump,
+    JumpIfFalse,
+    Loop,
+    Call,
+    Closure,
+    CloseUpvalue,
+    Return,
+    Class,
+    Inherit,
+    Method,
+    PushHandler,
+    PopHandler,
+    BuildList,
+    GetIndex,
+    SetIndex,
+    Slice,
+    BuildMap,
+    PushFinally,
+    Throw,
+    EndFinally,
+    Import,
+    Interpolate,
+}
+
+impl OpCode {
+    const ALL: [OpCode; 49] = [
+        OpCode::Constant,
+        OpCode::Nil,
+        OpCode::True,
+        OpCode::False,
+        OpCode::Pop,
+        OpCode::GetLocal,
+        OpCode::SetLocal,
+        OpCode::GetGlobal,
+        OpCode::DefineGlobal,
+        OpCode::SetGlobal,
+        OpCode::GetUpvalue,
+        OpCode::SetUpvalue,
+        OpCode::GetProperty,
+        OpCode::SetProperty,
+        OpCode::GetSuper,
+        OpCode::Equal,
+        OpCode::Greater,
+        OpCode::GreaterEqual,
+        OpCode::Less,
+        OpCode::LessEqual,
+        OpCode::Add,
+        OpCode::Subtract,
+        OpCode::Multiply,
+        OpCode::Divide,
+        OpCode::Not,
+        OpCode::Negate,
+        OpCode::Print,
+        OpCode::Jump,
+        OpCode::JumpIfFalse,
+        OpCode::Loop,
+        OpCode::Call,
+        OpCode::Closure,
+        OpCode::CloseUpvalue,
+        OpCode::Return,
+        OpCode::Class,
+        OpCode::Inherit,
+        OpCode::Method,
+        OpCode::PushHandler,
+        OpCode::PopHandler,
+        OpCode::BuildList,
+        OpCode::GetIndex,
+        OpCode::SetIndex,
+        OpCode::Slice,
+        OpCode::BuildMap,
+        OpCode::PushFinally,
+        OpCode::Throw,
+        OpCode::EndFinally,
+        OpCode::Import,
+        OpCode::Interpolate,
+    ];
+
+    pub fn from_byte(byte: u8) -> Option<OpCode> {
+        Self::ALL.get(byte as usize).copied()
+    }
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            OpCode::Constant => "OP_CONSTANT",
+            OpCode::Nil => "OP_NIL",
+            OpCode::True => "OP_TRUE",
+            OpCode::False => "OP_FALSE",
+            OpCode::Pop => "OP_POP",
+            OpCode::GetLocal => "OP_GET_LOCAL",
+            OpCode::SetLocal => "OP_SET_LOCAL",
+            OpCode::GetGlobal => "OP_GET_GLOBAL",
+            OpCode::DefineGlobal => "OP_DEFINE_GLOBAL",
+            OpCode::SetGlobal => "OP_SET_GLOBAL",
+            OpCode::GetUpvalue => "OP_GET_UPVALUE",
+            OpCode::SetUpvalue => "OP_SET_UPVALUE",
+            OpCode::GetProperty => "OP_GET_PROPERTY",
+            OpCode::SetProperty => "OP_SET_PROPERTY",
+            OpCode::GetSuper => "OP_GET_SUPER",
+            OpCode::Equal => "OP_EQUAL",
+            OpCode::Greater => "OP_GREATER",
+            OpCode::GreaterEqual => "OP_GREATER_EQUAL",
+            OpCode::Less => "OP_LESS",
+            OpCode::LessEqual => "OP_LESS_EQUAL",
+            OpCode::Add => "OP_ADD",
+            OpCode::Subtract => "OP_SUBTRACT",
+            OpCode::Multiply => "OP_MULTIPLY",
+            OpCode::Divide => "OP_DIVIDE",
+            OpCode::Not => "OP_NOT",
+            OpCode::Negate => "OP_NEGATE",
+            OpCode::Print => "OP_PRINT",
+            OpCode::Jump => "OP_JUMP",
+            OpCode::JumpIfFalse => "OP_JUMP_IF_FALSE",
+            OpCode::Loop => "OP_LOOP",
+            OpCode::Call => "OP_CALL",
+            OpCode::Closure => "OP_CLOSURE",
+            OpCode::CloseUpvalue => "OP_CLOSE_UPVALUE",
+            OpCode::Return => "OP_RETURN",
+            OpCode::Class => "OP_CLASS",
+            OpCode::Inherit => "OP_INHERIT",
+            OpCode::Method => "OP_METHOD",
+            OpCode::PushHandler => "OP_PUSH_HANDLER",
+            OpCode::PopHandler => "OP_POP_HANDLER",
+            OpCode::BuildList => "OP_BUILD_LIST",
+            OpCode::GetIndex => "OP_GET_INDEX",
+            OpCode::SetIndex => "OP_SET_INDEX",
+            OpCode::Slice => "OP_SLICE",
+            OpCode::BuildMap => "OP_BUILD_MAP",
+            OpCode::PushFinally => "OP_PUSH_FINALLY",
+            OpCode::Throw => "OP_THROW",
+            OpCode::EndFinally => "OP_END_FINALLY",
+            OpCode::Import => "OP_IMPORT",
+            OpCode::Interpolate => "OP_INTERPOLATE",
+        }
+    }
+}
+
+/// A compiled sequence of instructions together with the constants it refers
+/// to. `spans[i]` is the source span of the byte at `code[i]`.
+#[derive(Debug, Clone, Default)]
+pub struct Chunk {
+    pub code: Vec<u8>,
+    pub constants: Vec<Value>,
+    pub spans: Vec<Span>,
+}
+
+impl Chunk {
+    pub fn write(&mut self, byte: u8, span: Span) {
+        self.code.push(byte);
+        self.spans.push(span);
+    }
+
+    pub fn write_op(&mut self, op: OpCode, span: Span) {
+        self.write(op as u8, span);
+    }
+
+    pub fn write_u16(&mut self, value: u16, span: Span) {
+        let [high, low] = value.to_be_bytes();
+        self.write(high, span.clone());
+        self.write(low, span);
+    }
+
+    pub fn read_u16(&self, offset: usize) -> u16 {
+        u16::from_be_bytes([self.code[offset], self.code[offset + 1]])
+    }
+
+    /// Adds `value` to the constant pool, reusing an equal number or string.
+    pub fn add_constant(&mut self, value: Value) -> usize {
+        let existing = self.constants.iter().position(|c| match (c, &value) {
+            (Value::Number(l), Value::Number(r)) => l.to_bits() == r.to_bits(),
+            (Value::String(l), Value::String(r)) => l == r,
+            _ => false,
+        });
+
+        match existing {
+            Some(index) => index,
+            None => {
+                self.constants.push(value);
+                self.constants.len() - 1
+            }
+        }
+    }
+}
@@ -0,0 +1,940 @@
+use crate::diagnostics::Diagnostic;
+use crate::models::expressions::Expr;
+use crate::models::span::Span;
+use crate::models::statements::Stmt;
+use crate::models::token_types::TokenType;
+use crate::models::tokens::Token;
+use crate::models::values::Value as AstValue;
+use crate::modules::{imported, Modules};
+use crate::vm::chunk::{Chunk, OpCode};
+use crate::vm::value::{Function, Value};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::rc::Rc;
+
+const MAX_LOCALS: usize = 256;
+const MAX_UPVALUES: usize = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum FunctionKind {
+    Script,
+    Function,
+    Method,
+    Initializer,
+}
+
+struct Local {
+    name: String,
+    depth: usize,
+    is_captured: bool,
+}
+
+/// A loop being compiled, for the jumps of `break` and `continue`.
+struct Loop {
+    /// Scope depth outside the loop body; the locals declared deeper are
+    /// popped before jumping out of the body.
+    scope_depth: usize,
+    breaks: Vec<usize>,
+    continues: Vec<usize>,
+}
+
+/// A `try` statement whose body or `catch` clause is being compiled. Jumps
+/// and returns that leave it must uninstall its handler and run its
+/// `finally` block first.
+#[derive(Clone)]
+struct TryContext {
+    finally: Option<Vec<Stmt>>,
+    /// Number of enclosing loops; a `break` or `continue` only leaves the
+    /// `try` statements entered inside its loop.
+    loop_count: usize,
+    /// Scope depth outside the statement.
+    scope_depth: usize,
+    /// Whether a handler is installed for the code being compiled.
+    handler: bool,
+}
+
+struct UpvalueRef {
+    index: u8,
+    is_local: bool,
+}
+
+/// Compilation state of the function currently being emitted. Nested
+/// function declarations push a new state on `Compiler::functions`.
+struct FunctionState {
+    function: Function,
+    kind: FunctionKind,
+    locals: Vec<Local>,
+    upvalues: Vec<UpvalueRef>,
+    scope_depth: usize,
+    loops: Vec<Loop>,
+    tries: Vec<TryContext>,
+}
+
+impl FunctionState {
+    fn new(name: String, kind: FunctionKind) -> Self {
+        // Slot zero holds the callee, which methods expose as `this`.
+        let slot_zero = match kind {
+            FunctionKind::Method | FunctionKind::Initializer => "this",
+            _ => "",
+        };
+
+        FunctionState {
+            function: Function {
+                name,
+                ..Function::default()
+            },
+            kind,
+            locals: vec![Local {
+                name: slot_zero.to_string(),
+                depth: 0,
+                is_captured: false,
+            }],
+            upvalues: Vec::new(),
+            scope_depth: 0,
+            loops: Vec::new(),
+            tries: Vec::new(),
+        }
+    }
+}
+
+/// Turns a resolved syntax tree into bytecode for `vm::machine::Vm`.
+pub struct Compiler {
+    functions: Vec<FunctionState>,
+    class_has_superclass: Vec<bool>,
+    span: Span,
+    /// Modules the program may import, by canonical path.
+    modules: Rc<Modules>,
+    /// Scripts of the modules compiled so far, shared by the compilers of
+    /// the program and of every module so that each is compiled once.
+    compiled: Rc<RefCell<HashMap<PathBuf, Rc<Function>>>>,
+    pub errors: Vec<Diagnostic>,
+}
+
+impl Default for Compiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Compiler {
+    pub fn new() -> Self {
+        Self::with_modules(Modules::new())
+    }
+
+    /// A compiler for a program importing the modules the resolver loaded.
+    pub fn with_modules(modules: Modules) -> Self {
+        Compiler {
+            functions: vec![FunctionState::new(String::new(), FunctionKind::Script)],
+            class_has_superclass: Vec::new(),
+            span: Span::default(),
+            modules: Rc::new(modules),
+            compiled: Rc::default(),
+            errors: Vec::new(),
+        }
+    }
+
+    /// Compiles a whole program into the function run as the top-level script.
+    pub fn compile(mut self, stmts: &[Stmt]) -> Result<Rc<Function>, Vec<Diagnostic>> {
+        self.statements(stmts);
+        self.emit_op(OpCode::Nil);
+        self.emit_op(OpCode::Return);
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        let state = self.functions.pop().unwrap();
+        Ok(Rc::new(state.function))
+    }
+
+    /// Compiles a single expression into a script that returns its value.
+    pub fn compile_expression(mut self, expr: &Expr) -> Result<Rc<Function>, Vec<Diagnostic>> {
+        self.span = expr.span();
+        self.expression(expr);
+        self.emit_op(OpCode::Return);
+
+        if !self.errors.is_empty() {
+            return Err(self.errors);
+        }
+
+        let state = self.functions.pop().unwrap();
+        Ok(Rc::new(state.function))
+    }
+
+    fn state(&mut self) -> &mut FunctionState {
+        self.functions.last_mut().unwrap()
+    }
+
+    fn chunk(&mut self) -> &mut Chunk {
+        &mut self.state().function.chunk
+    }
+
+    fn error(&mut self, msg: &str) {
+        self.errors.push(Diagnostic::new(&self.span, msg));
+    }
+
+    fn emit(&mut self, byte: u8) {
+        let span = self.span.clone();
+        self.chunk().write(byte, span);
+    }
+
+    fn emit_op(&mut self, op: OpCode) {
+        let span = self.span.clone();
+        self.chunk().write_op(op, span);
+    }
+
+    fn emit_op_u16(&mut self, op: OpCode, operand: usize) {
+        self.emit_op(op);
+        let span = self.span.clone();
+        self.chunk().write_u16(operand as u16, span);
+    }
+
+    fn emit_jump(&mut self, op: OpCode) -> usize {
+        self.emit_op_u16(op, u16::MAX as usize);
+        self.chunk().code.len() - 2
+    }
+
+    fn patch_jump(&mut self, offset: usize) {
+        let jump = self.chunk().code.len() - offset - 2;
+        if jump > u16::MAX as usize {
+            self.error("Too much code to jump over.");
+            return;
+        }
+
+        let [high, low] = (jump as u16).to_be_bytes();
+        let chunk = self.chunk();
+        chunk.code[offset] = high;
+        chunk.code[offset + 1] = low;
+    }
+
+    fn emit_loop(&mut self, loop_start: usize) {
+        self.emit_op(OpCode::Loop);
+        let offset = self.chunk().code.len() - loop_start + 2;
+        if offset > u16::MAX as usize {
+            self.error("Loop body too large.");
+        }
+        let span = self.span.clone();
+        self.chunk().write_u16(offset as u16, span);
+    }
+
+    fn make_constant(&mut self, value: Value) -> usize {
+        let index = self.chunk().add_constant(value);
+        if index > u16::MAX as usize {
+            self.error("Too many constants in one chunk.");
+            return 0;
+        }
+        index
+    }
+
+    fn emit_constant(&mut self, value: Value) {
+        let index = self.make_constant(value);
+        self.emit_op_u16(OpCode::Constant, index);
+    }
+
+    fn identifier_constant(&mut self, name: &str) -> usize {
+        self.make_constant(Value::String(Rc::from(name)))
+    }
+
+    fn statements(&mut self, stmts: &[Stmt]) {
+        for stmt in stmts {
+            self.statement(stmt);
+        }
+    }
+
+    fn statement(&mut self, stmt: &Stmt) {
+        // Instructions without a token of their own, such as literals and
+        // pops, are attributed to the statement they belong to.
+        let span = stmt.span();
+        if span.line != 0 {
+            self.span = span;
+        }
+
+        match stmt {
+            Stmt::Block(stmts) => {
+                self.begin_scope();
+                self.statements(stmts);
+                self.end_scope();
+            }
+            Stmt::Class(name, superclass, methods) => {
+                self.class_declaration(name, superclass, methods)
+            }
+            Stmt::Expression(e) => {
+                self.expression(e);
+                self.emit_op(OpCode::Pop);
+            }
+            Stmt::Function(name, params, body) => {
+                self.span = name.span.clone();
+                let global = self.declare_variable(name);
+                self.function(&name.name, params, body, FunctionKind::Function);
+                self.define_variable(global);
+            }
+            Stmt::If(condition, then_branch, else_branch) => {
+                self.expression(condition);
+                let then_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+                self.statement(then_branch);
+
+                let else_jump = self.emit_jump(OpCode::Jump);
+                self.patch_jump(then_jump);
+                self.emit_op(OpCode::Pop);
+                if let Some(else_branch) = else_branch {
+                    self.statement(else_branch);
+                }
+                self.patch_jump(else_jump);
+            }
+            Stmt::Print(e) => {
+                self.expression(e);
+                self.emit_op(OpCode::Print);
+            }
+            Stmt::Return(keyword, value) => {
+                self.span = keyword.span.clone();
+                if self.state().kind == FunctionKind::Initializer {
+                    self.emit_op_u16(OpCode::GetLocal, 0);
+                } else {
+                    match value {
+                        Some(value) => self.expression(value),
+                        None => self.emit_op(OpCode::Nil),
+                    }
+                }
+                if !self.state().tries.is_empty() {
+                    // The result waits in a hidden slot while `finally`
+                    // blocks run.
+                    self.add_local("");
+                    self.exit_tries(0);
+                    self.state().locals.pop();
+                }
+                self.emit_op(OpCode::Return);
+            }
+            Stmt::Var(name, initializer) => {
+                match initializer {
+                    Some(e) => self.expression(e),
+                    None => self.emit_op(OpCode::Nil),
+                }
+                self.span = name.span.clone();
+                let global = self.declare_variable(name);
+                self.define_variable(global);
+            }
+            Stmt::While(condition, body, increment) => {
+                let loop_start = self.chunk().code.len();
+                self.expression(condition);
+                let exit_jump = self.emit_jump(OpCode::JumpIfFalse);
+                self.emit_op(OpCode::Pop);
+
+                let scope_depth = self.state().scope_depth;
+                self.state().loops.push(Loop {
+                    scope_depth,
+                    breaks: Vec::new(),
+                    continues: Vec::new(),
+                });
+                self.statement(body);
+                let body_loop = self.state().loops.pop().unwrap();
+
+                for jump in body_loop.continues {
+                    self.patch_jump(jump);
+                }
+                if let Some(increment) = increment {
+                    self.expression(increment);
+                    self.emit_op(OpCode::Pop);
+                }
+                self.emit_loop(loop_start);
+
+                self.patch_jump(exit_jump);
+                self.emit_op(OpCode::Pop);
+                for jump in body_loop.breaks {
+                    self.patch_jump(jump);
+                }
+            }
+            Stmt::Export(_, declaration) => self.statement(declaration),
+            Stmt::Import(_, path, name) => {
+                self.import(path);
+                self.span = name.span.clone();
+                let global = self.declare_variable(name);
+                self.define_variable(global);
+            }
+            Stmt::FromImport(_, path, names) => {
+                // Only the first import runs the module; each pushes it so
+                // that one name can be read from it.
+                for name in names {
+                    self.import(path);
+                    self.span = name.span.clone();
+                    let constant = self.identifier_constant(&name.name);
+                    self.emit_op_u16(OpCode::GetProperty, constant);
+                    let global = self.declare_variable(name);
+                    self.define_variable(global);
+                }
+            }
+            Stmt::Throw(_, value) => {
+                self.expression(value);
+                self.span = stmt.span();
+                self.emit_op(OpCode::Throw);
+            }
+            Stmt::Try(_, body, catch, finally) => self.try_statement(body, catch, finally),
+            Stmt::Break(keyword) | Stmt::Continue(keyword) => {
+                self.span = keyword.span.clone();
+                let Some(scope_depth) = self.state().loops.last().map(|l| l.scope_depth) else {
+                    let msg = format!("Can't use '{}' outside of a loop.", keyword.name);
+                    self.error(&msg);
+                    return;
+                };
+
+                let loop_count = self.state().loops.len();
+                let first_try = self
+                    .state()
+                    .tries
+                    .iter()
+                    .position(|t| t.loop_count == loop_count)
+                    .unwrap_or(self.state().tries.len());
+                self.exit_tries(first_try);
+                self.discard_locals(scope_depth);
+                let jump = self.emit_jump(OpCode::Jump);
+                let current = self.state().loops.last_mut().unwrap();
+                if matches!(stmt, Stmt::Break(_)) {
+                    current.breaks.push(jump);
+                } else {
+                    current.continues.push(jump);
+                }
+            }
+        }
+    }
+
+    /// Compiles a `try` statement. The body runs under a handler that jumps
+    /// to the `catch` clause, or to a copy of `finally` ending in
+    /// `EndFinally`, which raises the error again; the caught value is on the
+    /// stack there. The `catch` clause itself runs under a handler for that
+    /// copy of `finally`. Every normal exit ends in the other copy.
+    fn try_statement(
+        &mut self,
+        body: &[Stmt],
+        catch: &Option<(Token, Vec<Stmt>)>,
+        finally: &Option<Vec<Stmt>>,
+    ) {
+        let loop_count = self.state().loops.len();
+        let scope_depth = self.state().scope_depth;
+        self.state().tries.push(TryContext {
+            finally: finally.clone(),
+            loop_count,
+            scope_depth,
+            handler: true,
+        });
+
+        let handler_op = match catch {
+            Some(_) => OpCode::PushHandler,
+            None => OpCode::PushFinally,
+        };
+        let handler = self.emit_jump(handler_op);
+        self.begin_scope();
+        self.statements(body);
+        self.end_scope();
+        self.emit_op(OpCode::PopHandler);
+        self.current_try().handler = false;
+        let mut exits = vec![self.emit_jump(OpCode::Jump)];
+        self.patch_jump(handler);
+
+        // Locals in the copy of `finally` run for errors: the `catch`
+        // variable, if any, and the caught value.
+        let mut hidden = 1;
+        if let Some((name, handler_body)) = catch {
+            self.begin_scope();
+            self.add_local(&name.name);
+            let finally_handler = finally.as_ref().map(|_| {
+                self.current_try().handler = true;
+                self.emit_jump(OpCode::PushFinally)
+            });
+            self.statements(handler_body);
+            if let Some(finally_handler) = finally_handler {
+                self.emit_op(OpCode::PopHandler);
+                self.current_try().handler = false;
+                self.end_scope();
+                exits.push(self.emit_jump(OpCode::Jump));
+                self.patch_jump(finally_handler);
+                hidden = 2;
+            } else {
+                self.end_scope();
+            }
+        }
+        self.state().tries.pop();
+
+        if let Some(finally) = finally {
+            self.begin_scope();
+            for _ in 0..hidden {
+                self.add_local("");
+            }
+            self.statements(finally);
+            self.emit_op(OpCode::EndFinally);
+            // `EndFinally` never falls through, so its locals are forgotten
+            // without emitting pops.
+            let state = self.state();
+            state.scope_depth -= 1;
+            let depth = state.scope_depth;
+            state.locals.retain(|local| local.depth <= depth);
+        }
+
+        for exit in exits {
+            self.patch_jump(exit);
+        }
+        if let Some(finally) = finally {
+            self.begin_scope();
+            self.statements(finally);
+            self.end_scope();
+        }
+    }
+
+    fn current_try(&mut self) -> &mut TryContext {
+        self.state().tries.last_mut().unwrap()
+    }
+
+    /// Uninstalls the handlers of the `try` statements from `first` on,
+    /// innermost first, and runs their `finally` blocks, for a jump or
+    /// return that leaves them. Locals declared inside each statement stay
+    /// on the stack but are hidden from its `finally` block.
+    fn exit_tries(&mut self, first: usize) {
+        let tries = self.state().tries.split_off(first);
+        for (i, exited) in tries.iter().enumerate().rev() {
+            if exited.handler {
+                self.emit_op(OpCode::PopHandler);
+            }
+            let Some(finally) = &exited.finally else {
+                continue;
+            };
+
+            // Code in `finally` must not exit the statements it leaves again.
+            let names: Vec<String> = self
+                .state()
+                .locals
+                .iter_mut()
+                .filter(|local| local.depth > exited.scope_depth)
+                .map(|local| std::mem::take(&mut local.name))
+                .collect();
+            self.state().tries.extend(tries.iter().take(i).cloned());
+            self.begin_scope();
+            self.statements(finally);
+            self.end_scope();
+            self.state().tries.truncate(first);
+            let hidden = self
+                .state()
+                .locals
+                .iter_mut()
+                .filter(|local| local.depth > exited.scope_depth);
+            for (local, name) in hidden.zip(names) {
+                local.name = name;
+            }
+        }
+        self.state().tries.extend(tries);
+    }
+
+    /// Emits the code leaving the module `path` refers to on the stack,
+    /// compiling the module's script the first time it is imported.
+    fn import(&mut self, path: &Token) {
+        self.span = path.span.clone();
+        let Some((canonical, module)) = imported(&self.modules, path) else {
+            self.error(&format!("Cannot find module {}.", path.name));
+            return;
+        };
+
+        let cached = self.compiled.borrow().get(&canonical).cloned();
+        let function = match cached {
+            Some(function) => function,
+            None => {
+                let mut compiler = Compiler {
+                    modules: Rc::clone(&self.modules),
+                    compiled: Rc::clone(&self.compiled),
+                    ..Compiler::new()
+                };
+                let script = &mut compiler.functions[0].function;
+                script.name = module.file.clone();
+                script.exports = Some(module.exports.clone());
+                match compiler.compile(&module.stmts) {
+                    Ok(function) => {
+                        let mut compiled = self.compiled.borrow_mut();
+                        compiled.insert(canonical, Rc::clone(&function));
+                        function
+                    }
+                    Err(errors) => {
+                        self.errors.extend(errors);
+                        return;
+                    }
+                }
+            }
+        };
+
+        let existing = self.chunk().constants.iter().position(
+            |constant| matches!(constant, Value::Function(f) if Rc::ptr_eq(f, &function)),
+        );
+        let constant = match existing {
+            Some(index) => index,
+            None => self.make_constant(Value::Function(function)),
+        };
+        self.emit_op_u16(OpCode::Import, constant);
+        self.emit_op(OpCode::Pop);
+    }
+
+    fn class_declaration(&mut self, name: &Token, superclass: &Option<Expr>, methods: &[Stmt]) {
+        self.span = name.span.clone();
+        let name_constant = self.identifier_constant(&name.name);
+        let global = self.declare_variable(name);
+        self.emit_op_u16(OpCode::Class, name_constant);
+        self.define_variable(global);
+
+        self.class_has_superclass.push(superclass.is_some());
+        if let Some(superclass) = superclass {
+            self.expression(superclass);
+
+            self.begin_scope();
+            self.add_local("super");
+            self.named_variable(name, false);
+            self.span = superclass.span();
+            self.emit_op(OpCode::Inherit);
+        }
+
+        self.named_variable(name, false);
+        for method in methods {
+            if let Stmt::Function(method_name, params, body) = method {
+                self.span = method_name.span.clone();
+                let method_constant = self.identifier_constant(&method_name.name);
+                let kind = if method_name.name == "init" {
+                    FunctionKind::Initializer
+                } else {
+                    FunctionKind::Method
+                };
+                self.function(&method_name.name, params, body, kind);
+                self.emit_op_u16(OpCode::Method, method_constant);
+            }
+        }
+        self.emit_op(OpCode::Pop);
+
+        if self.class_has_superclass.pop().unwrap_or_default() {
+            self.end_scope();
+        }
+    }
+
+    fn function(&mut self, name: &str, params: &[Token], body: &[Stmt], kind: FunctionKind) {
+        let mut state = FunctionState::new(name.to_string(), kind);
+        state.function.arity = params.len();
+        self.functions.push(state);
+
+        self.begin_scope();
+        for param in params {
+            self.add_local(&param.name);
+        }
+        self.statements(body);
+
+        if kind == FunctionKind::Initializer {
+            self.emit_op_u16(OpCode::GetLocal, 0);
+        } else {
+            self.emit_op(OpCode::Nil);
+        }
+        self.emit_op(OpCode::Return);
+
+        let mut state = self.functions.pop().unwrap();
+        state.function.upvalue_count = state.upvalues.len();
+        let constant = self.make_constant(Value::Function(Rc::new(state.function)));
+        self.emit_op_u16(OpCode::Closure, constant);
+        for upvalue in state.upvalues {
+            self.emit(upvalue.is_local as u8);
+            self.emit(upvalue.index);
+        }
+    }
+
+    fn begin_scope(&mut self) {
+        self.state().scope_depth += 1;
+    }
+
+    fn end_scope(&mut self) {
+        self.state().scope_depth -= 1;
+
+        loop {
+            let state = self.state();
+            let captured = match state.locals.last() {
+                Some(local) if local.depth > state.scope_depth => local.is_captured,
+                _ => break,
+            };
+            state.locals.pop();
+
+            if captured {
+                self.emit_op(OpCode::CloseUpvalue);
+            } else {
+                self.emit_op(OpCode::Pop);
+            }
+        }
+    }
+
+    /// Emits the pops for every local deeper than `depth` without forgetting
+    /// them, for jumps that leave their scopes early.
+    fn discard_locals(&mut self, depth: usize) {
+        let captured: Vec<bool> = self
+            .state()
+            .locals
+            .iter()
+            .rev()
+            .take_while(|local| local.depth > depth)
+            .map(|local| local.is_captured)
+            .collect();
+
+        for captured in captured {
+            if captured {
+                self.emit_op(OpCode::CloseUpvalue);
+            } else {
+                self.emit_op(OpCode::Pop);
+            }
+        }
+    }
+
+    fn add_local(&mut self, name: &str) {
+        if self.state().locals.len() >= MAX_LOCALS {
+            self.error("Too many local variables in function.");
+            return;
+        }
+
+        let depth = self.state().scope_depth;
+        self.state().locals.push(Local {
+            name: name.to_string(),
+            depth,
+            is_captured: false,
+        });
+    }
+
+    /// Declares `name` as a local in block scopes and returns the constant
+    /// holding its name for globals.
+    fn declare_variable(&mut self, name: &Token) -> Option<usize> {
+        if self.state().scope_depth > 0 {
+            self.add_local(&name.name);
+            return None;
+        }
+
+        Some(self.identifier_constant(&name.name))
+    }
+
+    fn define_variable(&mut self, global: Option<usize>) {
+        if let Some(constant) = global {
+            self.emit_op_u16(OpCode::DefineGlobal, constant);
+        }
+    }
+
+    fn resolve_local(&self, function: usize, name: &str) -> Option<usize> {
+        self.functions[function]
+            .locals
+            .iter()
+            .rposition(|local| local.name == name)
+    }
+
+    fn resolve_upvalue(&mut self, function: usize, name: &str) -> Option<usize> {
+        if function == 0 {
+            return None;
+        }
+
+        if let Some(local) = self.resolve_local(function - 1, name) {
+            self.functions[function - 1].locals[local].is_captured = true;
+            return Some(self.add_upvalue(function, local, true));
+        }
+
+        let upvalue = self.resolve_upvalue(function - 1, name)?;
+        Some(self.add_upvalue(function, upvalue, false))
+    }
+
+    fn add_upvalue(&mut self, function: usize, index: usize, is_local: bool) -> usize {
+        let upvalues = &self.functions[function].upvalues;
+        if let Some(existing) = upvalues
+            .iter()
+            .position(|u| u.index as usize == index && u.is_local == is_local)
+        {
+            return existing;
+        }
+
+        if upvalues.len() >= MAX_UPVALUES {
+            self.error("Too many closure variables in function.");
+            return 0;
+        }
+
+        let upvalues = &mut self.functions[function].upvalues;
+        upvalues.push(UpvalueRef {
+            index: index as u8,
+            is_local,
+        });
+        upvalues.len() - 1
+    }
+
+    fn named_variable(&mut self, name: &Token, assign: bool) {
+        self.span = name.span.clone();
+        let current = self.functions.len() - 1;
+
+        let (get, set, operand) = if let Some(slot) = self.resolve_local(current, &name.name) {
+            (OpCode::GetLocal, OpCode::SetLocal, slot)
+        } else if let Some(index) = self.resolve_upvalue(current, &name.name) {
+            (OpCode::GetUpvalue, OpCode::SetUpvalue, index)
+        } else {
+            let constant = self.identifier_constant(&name.name);
+            (OpCode::GetGlobal, OpCode::SetGlobal, constant)
+        };
+
+        self.emit_op_u16(if assign { set } else { get }, operand);
+    }
+
+    fn expression(&mut self, expr: &Expr) {
+        match expr {
+            Expr::Literal(value, span) => match value {
+                AstValue::Nil => self.emit_op(OpCode::Nil),
+                AstValue::Bool(true) => self.emit_op(OpCode::True),
+                AstValue::Bool(false) => self.emit_op(OpCode::False),
+                AstValue::Number(n) => self.emit_constant(Value::Number(*n)),
+                AstValue::String(s) => self.emit_constant(Value::String(Rc::from(s.as_str()))),
+                _ => {
+                    self.span = span.clone();
+                    self.error("Unsupported literal.")
+                }
+            },
+            Expr::Logical(left, operator, right) => {
+                self.expression(left);
+                if operator.token_type == TokenType::Or {
+                    let else_jump = self.emit_jump(OpCode::JumpIfFalse);
+                    let end_jump = self.emit_jump(OpCode::Jump);
+                    self.patch_jump(else_jump);
+                    self.emit_op(OpCode::Pop);
+                    self.expression(right);
+                    self.patch_jump(end_jump);
+                } else {
+                    let end_jump = self.emit_jump(OpCode::JumpIfFalse);
+                    self.emit_op(OpCode::Pop);
+                    self.expression(right);
+                    self.patch_jump(end_jump);
+                }
+            }
+            Expr::Unary(operator, right) => {
+                self.expression(right);
+                self.span = operator.span.clone();
+                match operator.token_type {
+                    TokenType::Minus => self.emit_op(OpCode::Negate),
+                    _ => self.emit_op(OpCode::Not),
+                }
+            }
+            Expr::Binary(left, operator, right) => {
+                self.expression(left);
+                self.expression(right);
+                self.span = operator.span.clone();
+                match operator.token_type {
+                    TokenType::Plus => self.emit_op(OpCode::Add),
+                    TokenType::Minus => self.emit_op(OpCode::Subtract),
+                    TokenType::Star => self.emit_op(OpCode::Multiply),
+                    TokenType::Slash => self.emit_op(OpCode::Divide),
+                    TokenType::Greater => self.emit_op(OpCode::Greater),
+                    TokenType::GreaterEqual => self.emit_op(OpCode::GreaterEqual),
+                    TokenType::Less => self.emit_op(OpCode::Less),
+                    TokenType::LessEqual => self.emit_op(OpCode::LessEqual),
+                    TokenType::EqualEqual => self.emit_op(OpCode::Equal),
+                    TokenType::BangEqual => {
+                        self.emit_op(OpCode::Equal);
+                        self.emit_op(OpCode::Not);
+                    }
+                    _ => self.error(&format!(
+                        "Invalid operation {} for binary expression.",
+                        operator.token_type
+                    )),
+                }
+            }
+            Expr::Variable(_, name) => self.named_variable(name, false),
+            Expr::Assign(_, name, value) => {
+                self.expression(value);
+                self.named_variable(name, true);
+            }
+            Expr::Call(callee, paren, args) => {
+                self.expression(callee);
+                for arg in args {
+                    self.expression(arg);
+                }
+                self.span = callee.span().to(&paren.span);
+                self.emit_op(OpCode::Call);
+                self.emit(args.len() as u8);
+            }
+            Expr::Get(object, name) => {
+                self.expression(object);
+                self.span = name.span.clone();
+                let constant = self.identifier_constant(&name.name);
+                self.emit_op_u16(OpCode::GetProperty, constant);
+            }
+            Expr::Set(object, name, value) => {
+                self.expression(object);
+                self.expression(value);
+                self.span = name.span.clone();
+                let constant = self.identifier_constant(&name.name);
+                self.emit_op_u16(OpCode::SetProperty, constant);
+            }
+            Expr::Super(_, keyword, method) => {
+                self.named_variable(&synthetic_token(keyword, "this"), false);
+                self.named_variable(keyword, false);
+                self.span = method.span.clone();
+                let constant = self.identifier_constant(&method.name);
+                self.emit_op_u16(OpCode::GetSuper, constant);
+            }
+            Expr::This(_, keyword) => self.named_variable(keyword, false),
+            Expr::Grouping(e) => self.expression(e),
+            Expr::List(elements, span) => {
+                for element in elements {
+                    self.expression(element);
+                }
+                self.span = span.clone();
+                if elements.len() > u16::MAX as usize {
+                    self.error("Too many elements in list literal.");
+                }
+                self.emit_op_u16(OpCode::BuildList, elements.len());
+            }
+            Expr::Interpolation(parts, span) => {
+                for part in parts {
+                    self.expression(part);
+                }
+                self.span = span.clone();
+                if parts.len() > u16::MAX as usize {
+                    self.error("Too many parts in interpolated string.");
+                }
+                self.emit_op_u16(OpCode::Interpolate, parts.len());
+            }
+            Expr::Map(entries, span) => {
+                for (key, value) in entries {
+                    self.expression(key);
+                    self.expression(value);
+                }
+                self.span = span.clone();
+                if entries.len() > u16::MAX as usize {
+                    self.error("Too many entries in map literal.");
+                }
+                self.emit_op_u16(OpCode::BuildMap, entries.len());
+            }
+            Expr::Subscript(object, _, index) => {
+                self.expression(object);
+                self.expression(index);
+                self.span = expr.span();
+                self.emit_op(OpCode::GetIndex);
+            }
+            Expr::SetSubscript(object, bracket, index, value) => {
+                self.expression(object);
+                self.expression(index);
+                self.expression(value);
+                self.span = object.span().to(&bracket.span);
+                self.emit_op(OpCode::SetIndex);
+            }
+            Expr::Slice(object, _, start, end) => {
+                self.expression(object);
+                for bound in [start, end] {
+                    match bound {
+                        Some(bound) => self.expression(bound),
+                        None => self.emit_op(OpCode::Nil),
+                    }
+                }
+                self.span = expr.span();
+                self.emit_op(OpCode::Slice);
+            }
+            Expr::Lambda(name, params, body) => {
+                self.span = name.span.clone();
+                self.function(&name.name, params, body, FunctionKind::Function);
+            }
+        }
+    }
+}
+
+fn synthetic_token(token: &Token, name: &str) -> Token {
+    Token::new(
+        TokenType::Identifier,
+        name.to_string(),
+        AstValue::Nil,
+        token.span.clone(),
+    )
+}
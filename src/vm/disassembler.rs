@@ -0,0 +1,153 @@
+use crate::vm::chunk::{Chunk, OpCode};
+use crate::vm::value::{Function, Value};
+use std::fmt::Write;
+
+/// Renders `function` and every function nested in its constant pool as a
+/// listing of offsets, source lines and instructions. When the `source` of
+/// the program is known, each new line is preceded by its text.
+pub fn disassemble(function: &Function, source: Option<&str>) -> String {
+    let lines: Vec<&str> = source.map(|s| s.lines().collect()).unwrap_or_default();
+    let mut out = String::new();
+    disassemble_function(function, &lines, &mut out);
+    out
+}
+
+fn disassemble_function(function: &Function, source: &[&str], out: &mut String) {
+    writeln!(out, "== {function} ==").unwrap();
+
+    let chunk = &function.chunk;
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        let line = chunk.spans[offset].line;
+        let new_line = offset == 0 || chunk.spans[offset - 1].line != line;
+        if new_line {
+            if let Some(text) = source.get(line.wrapping_sub(1)) {
+                writeln!(out, "; line {line}: {}", text.trim()).unwrap();
+            }
+        }
+        offset = disassemble_instruction(chunk, offset, new_line, out);
+    }
+
+    for constant in &chunk.constants {
+        if let Value::Function(nested) = constant {
+            // A module comes from another file than `source`.
+            let source = if nested.exports.is_some() {
+                &[]
+            } else {
+                source
+            };
+            writeln!(out).unwrap();
+            disassemble_function(nested, source, out);
+        }
+    }
+}
+
+/// Writes the instruction at `offset` and returns the offset of the next one.
+fn disassemble_instruction(
+    chunk: &Chunk,
+    offset: usize,
+    new_line: bool,
+    out: &mut String,
+) -> usize {
+    write!(out, "{offset:04} ").unwrap();
+    if new_line {
+        write!(out, "{:>4} ", chunk.spans[offset].line).unwrap();
+    } else {
+        write!(out, "   | ").unwrap();
+    }
+
+    let byte = chunk.code[offset];
+    let Some(op) = OpCode::from_byte(byte) else {
+        writeln!(out, "Unknown opcode {byte}").unwrap();
+        return offset + 1;
+    };
+
+    match op {
+        OpCode::Constant
+        | OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetProperty
+        | OpCode::SetProperty
+        | OpCode::GetSuper
+        | OpCode::Class
+        | OpCode::Method
+        | OpCode::Import => {
+            let index = chunk.read_u16(offset + 1) as usize;
+            writeln!(
+                out,
+                "{:<18} {index:4} '{}'",
+                op.name(),
+                constant(chunk, index)
+            )
+            .unwrap();
+            offset + 3
+        }
+        OpCode::GetLocal
+        | OpCode::SetLocal
+        | OpCode::GetUpvalue
+        | OpCode::SetUpvalue
+        | OpCode::BuildList
+        | OpCode::BuildMap
+        | OpCode::Interpolate => {
+            let slot = chunk.read_u16(offset + 1);
+            writeln!(out, "{:<18} {slot:4}", op.name()).unwrap();
+            offset + 3
+        }
+        OpCode::Call => {
+            let arg_count = chunk.code[offset + 1];
+            writeln!(out, "{:<18} {arg_count:4}", op.name()).unwrap();
+            offset + 2
+        }
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::PushHandler | OpCode::PushFinally => {
+            let jump = chunk.read_u16(offset + 1) as usize;
+            writeln!(out, "{:<18} {offset:4} -> {}", op.name(), offset + 3 + jump).unwrap();
+            offset + 3
+        }
+        OpCode::Loop => {
+            let jump = chunk.read_u16(offset + 1) as usize;
+            let target = (offset + 3).saturating_sub(jump);
+            writeln!(out, "{:<18} {offset:4} -> {target}", op.name()).unwrap();
+            offset + 3
+        }
+        OpCode::Closure => {
+            let index = chunk.read_u16(offset + 1) as usize;
+            writeln!(
+                out,
+                "{:<18} {index:4} {}",
+                op.name(),
+                constant(chunk, index)
+            )
+            .unwrap();
+
+            let upvalue_count = match &chunk.constants.get(index) {
+                Some(Value::Function(function)) => function.upvalue_count,
+                _ => 0,
+            };
+            let mut next = offset + 3;
+            for _ in 0..upvalue_count {
+                let kind = if chunk.code[next] == 1 {
+                    "local"
+                } else {
+                    "upvalue"
+                };
+                let index = chunk.code[next + 1];
+                writeln!(out, "{next:04}    |                     {kind} {index}").unwrap();
+                next += 2;
+            }
+            next
+        }
+        _ => {
+            writeln!(out, "{}", op.name()).unwrap();
+            offset + 1
+        }
+    }
+}
+
+fn constant(chunk: &Chunk, index: usize) -> String {
+    chunk
+        .constants
+        .get(index)
+        .map(|value| value.to_string())
+        .unwrap_or_else(|| "<missing>".to_string())
+}
@@ -0,0 +1,1155 @@
+use crate::diagnostics::{arity_message, module_frame, Diagnostic, ErrorKind, Failure};
+use crate::gc::Heap;
+use crate::host::{HostFunction, NativeModule};
+use crate::limits::{Limits, Meter, STACK_OVERFLOW};
+use crate::models::lox_error::{uncaught, ErrorProperty, LoxError};
+use crate::models::lox_list::{element_index, insert_index, slice_range, sort, LIST_METHODS};
+use crate::models::lox_map::{key_not_found, Entries, MAP_METHODS, NOT_INDEXABLE};
+use crate::models::lox_string::{char_at, char_slice, IMMUTABLE};
+use crate::models::span::Span;
+use crate::modules::not_exported;
+use crate::native_funcs::{builtin_modules, builtins, string};
+use crate::value::Value as HostValue;
+use crate::vm::chunk::OpCode;
+use crate::vm::value::{
+    BoundMethod, Class, Closure, Function, Globals, Instance, ListMethod, MapMethod, Module,
+    Upvalue, Value,
+};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+
+struct CallFrame {
+    closure: Rc<Closure>,
+    ip: usize,
+    /// Index of the stack slot holding the callee, i.e. local slot zero.
+    base: usize,
+}
+
+/// Recovery point installed by `PushHandler` or `PushFinally`: on a runtime
+/// error the VM drops everything above it, pushes the caught value and
+/// resumes at `target`.
+struct Handler {
+    frames: usize,
+    stack: usize,
+    target: usize,
+    /// Length of `Vm::pending` when the handler was installed.
+    pending: usize,
+    /// Whether the handler runs a `finally` block, which raises the error
+    /// again at its `EndFinally`.
+    finally: bool,
+}
+
+pub struct Vm {
+    stack: Vec<Value>,
+    frames: Vec<CallFrame>,
+    handlers: Vec<Handler>,
+    /// Globals of the program; every module has its own.
+    globals: Globals,
+    /// Native functions, defined in the globals of the program and of
+    /// every module.
+    natives: HashMap<Rc<str>, Value>,
+    /// Native modules, by name.
+    native_modules: HashMap<String, NativeModule>,
+    /// Modules that have run, by name.
+    modules: HashMap<String, Rc<Module>>,
+    open_upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    /// The value of the `throw` being unwound; unset while the error in
+    /// flight is a runtime error.
+    thrown: Option<Value>,
+    /// Errors whose `finally` block is running, with their thrown value.
+    pending: Vec<(Diagnostic, Option<Value>)>,
+    no_span: Span,
+    pub errors: Vec<Diagnostic>,
+    meter: Meter,
+    /// The closures, upvalues, classes, instances, bound methods, lists and
+    /// maps the program created, for collecting the cycles among them.
+    pub heap: Heap,
+    /// The `gc_stats` native, which the VM answers itself since it reports
+    /// on the heap.
+    gc_stats: Rc<HostFunction>,
+}
+
+impl Default for Vm {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Vm {
+    pub fn new() -> Self {
+        let mut vm = Vm {
+            stack: Vec::with_capacity(256),
+            frames: Vec::with_capacity(64),
+            handlers: Vec::new(),
+            globals: Globals::default(),
+            natives: HashMap::new(),
+            native_modules: builtin_modules()
+                .into_iter()
+                .map(|module| (module.name.clone(), module))
+                .collect(),
+            modules: HashMap::new(),
+            open_upvalues: Vec::new(),
+            thrown: None,
+            pending: Vec::new(),
+            no_span: Span::default(),
+            errors: Vec::new(),
+            meter: Meter::new(Limits::default()),
+            heap: Heap::new(),
+            gc_stats: Rc::new(HostFunction::new("gc_stats", 0, false, |_, _| {
+                Err((
+                    ErrorKind::Error,
+                    "Only the VM can call gc_stats.".to_string(),
+                ))
+            })),
+        };
+        for function in builtins() {
+            vm.define_native(function);
+        }
+        let gc_stats = Value::Native(Rc::clone(&vm.gc_stats));
+        vm.natives.insert(Rc::from("gc_stats"), gc_stats.clone());
+        vm.globals
+            .borrow_mut()
+            .insert(Rc::from("gc_stats"), gc_stats);
+        vm
+    }
+
+    /// Defines a host function in the globals of the program and of the
+    /// modules it imports from now on.
+    pub fn define_native(&mut self, function: HostFunction) {
+        let name: Rc<str> = Rc::from(function.name.as_str());
+        let native = Value::Native(Rc::new(function));
+        self.natives.insert(Rc::clone(&name), native.clone());
+        self.globals.borrow_mut().insert(name, native);
+    }
+
+    /// Removes the host function `name` from the globals of the program and
+    /// of the modules it imports from now on.
+    pub fn undefine_native(&mut self, name: &str) {
+        self.globals.borrow_mut().remove(name);
+        self.natives.remove(name);
+    }
+
+    /// Sets the limits of the runs that follow, starting a new run.
+    pub fn set_limits(&mut self, limits: Limits) {
+        self.meter = Meter::new(limits);
+    }
+
+    /// Starts counting the steps, time and memory of a new run.
+    pub fn restart_meter(&mut self) {
+        self.meter.restart();
+    }
+
+    /// Makes the functions and constants of `module` the globals of the
+    /// module that imports of its name run.
+    pub fn add_native_module(&mut self, module: NativeModule) {
+        self.native_modules.insert(module.name.clone(), module);
+    }
+
+    /// Runs a compiled script and returns the value it returned. A runtime
+    /// error stops the program and is recorded in `errors` together with its
+    /// stack trace.
+    pub fn interpret(&mut self, function: Rc<Function>) -> Value {
+        let closure = self.heap.alloc(Closure {
+            function,
+            upvalues: Vec::new(),
+            globals: Rc::clone(&self.globals),
+        });
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: 0,
+        });
+
+        let result = match self.run() {
+            Ok(()) => self.pop(),
+            Err(error) => {
+                let error = if error.trace.is_empty() {
+                    self.stack_trace(error)
+                } else {
+                    error
+                };
+                self.errors.push(error);
+                Value::Nil
+            }
+        };
+        self.reset();
+        result
+    }
+
+    /// Calls `callee` with `args` on behalf of the host program, while no
+    /// script is running.
+    pub fn call_from_host(&mut self, callee: &Value, args: &[Value]) -> Result<Value, Diagnostic> {
+        let result = self.call_function(callee, args).map_err(|error| {
+            if error.trace.is_empty() {
+                self.stack_trace(error)
+            } else {
+                error
+            }
+        });
+        self.reset();
+        result
+    }
+
+    pub fn globals(&self) -> &Globals {
+        &self.globals
+    }
+
+    fn reset(&mut self) {
+        self.stack.clear();
+        self.frames.clear();
+        self.handlers.clear();
+        self.thrown = None;
+        self.pending.clear();
+        self.open_upvalues.clear();
+    }
+
+    fn run(&mut self) -> Result<(), Diagnostic> {
+        loop {
+            match self.step() {
+                Ok(true) => return Ok(()),
+                Ok(false) => {}
+                Err(error) => self.recover(error)?,
+            }
+        }
+    }
+
+    /// Runs until the frame count drops back to `depth`, i.e. until the
+    /// function called on top of it returns. Only handlers installed above
+    /// `depth` may catch errors; the others belong to the caller.
+    fn run_until(&mut self, depth: usize) -> Result<(), Diagnostic> {
+        while self.frames.len() > depth {
+            if let Err(error) = self.step() {
+                if self.handlers.last().is_some_and(|h| h.frames > depth) {
+                    self.recover(error)?;
+                } else {
+                    return Err(error);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Calls `callee` with `args` from native code and returns its result.
+    fn call_function(&mut self, callee: &Value, args: &[Value]) -> Result<Value, Diagnostic> {
+        // Unlike calls from scripts, these recurse on the Rust stack.
+        if let Err((kind, msg)) = self.meter.check_stack() {
+            return Err(self.error(kind, msg));
+        }
+        let depth = self.frames.len();
+        self.stack.push(callee.clone());
+        self.stack.extend_from_slice(args);
+        self.call_value(callee.clone(), args.len())?;
+        self.run_until(depth)?;
+        Ok(self.pop())
+    }
+
+    fn recover(&mut self, error: Diagnostic) -> Result<(), Diagnostic> {
+        if !error.kind.catchable() {
+            return Err(error);
+        }
+        let Some(handler) = self.handlers.pop() else {
+            return Err(error);
+        };
+
+        let thrown = self.thrown.take();
+        let exception = match &thrown {
+            Some(value) => value.clone(),
+            None => Value::Error(Rc::new(LoxError::new(&error))),
+        };
+        self.pending.truncate(handler.pending);
+        if handler.finally {
+            // The trace is taken now, while the frames the error unwound
+            // through still exist.
+            let error = if error.trace.is_empty() {
+                self.stack_trace(error)
+            } else {
+                error
+            };
+            self.pending.push((error, thrown));
+        }
+
+        self.frames.truncate(handler.frames);
+        self.close_upvalues(handler.stack);
+        self.stack.truncate(handler.stack);
+        self.stack.push(exception);
+        self.frame().ip = handler.target;
+        Ok(())
+    }
+
+    /// Attaches every active function above the script with the span of the
+    /// call that entered it, innermost first.
+    fn stack_trace(&self, mut error: Diagnostic) -> Diagnostic {
+        error.trace = (1..self.frames.len())
+            .rev()
+            .map(|i| {
+                let function = &self.frames[i].closure.function;
+                let name = match function.exports {
+                    Some(_) => module_frame(&function.name),
+                    None => function.name.clone(),
+                };
+                (name, current_span(&self.frames[i - 1]).clone())
+            })
+            .collect();
+        error
+    }
+
+    fn frame(&mut self) -> &mut CallFrame {
+        self.frames.last_mut().unwrap()
+    }
+
+    fn read_byte(&mut self) -> u8 {
+        let frame = self.frame();
+        let byte = frame.closure.function.chunk.code[frame.ip];
+        frame.ip += 1;
+        byte
+    }
+
+    fn read_u16(&mut self) -> u16 {
+        let frame = self.frame();
+        let value = frame.closure.function.chunk.read_u16(frame.ip);
+        frame.ip += 2;
+        value
+    }
+
+    fn read_constant(&mut self) -> Value {
+        let index = self.read_u16() as usize;
+        self.frame().closure.function.chunk.constants[index].clone()
+    }
+
+    fn read_string(&mut self) -> Rc<str> {
+        match self.read_constant() {
+            Value::String(s) => s,
+            other => Rc::from(other.to_string().as_str()),
+        }
+    }
+
+    /// Reports `msg` at the span of the instruction being executed.
+    fn error(&self, kind: ErrorKind, msg: &str) -> Diagnostic {
+        Diagnostic::runtime(self.span(), kind, msg)
+    }
+
+    fn failure(&self, failure: Failure) -> Diagnostic {
+        Diagnostic::failure(self.span(), failure)
+    }
+
+    /// The span of the instruction being executed, or one pointing nowhere
+    /// when a host call fails before its callee starts.
+    fn span(&self) -> &Span {
+        self.frames.last().map_or(&self.no_span, current_span)
+    }
+
+    fn pop(&mut self) -> Value {
+        self.stack.pop().unwrap_or(Value::Nil)
+    }
+
+    fn peek(&self, distance: usize) -> &Value {
+        &self.stack[self.stack.len() - 1 - distance]
+    }
+
+    /// Executes one instruction and reports whether the script has finished.
+    fn step(&mut self) -> Result<bool, Diagnostic> {
+        // Instruction boundaries are safe points: no object is borrowed
+        // while an instruction starts.
+        if self.heap.should_collect() {
+            self.heap.collect();
+        }
+        if let Err((kind, msg)) = self.meter.step() {
+            return Err(self.error(kind, msg));
+        }
+        let byte = self.read_byte();
+        let Some(op) = OpCode::from_byte(byte) else {
+            return Err(self.error(ErrorKind::Error, &format!("Unknown opcode {byte}.")));
+        };
+
+        match op {
+            OpCode::Constant => {
+                let constant = self.read_constant();
+                self.stack.push(constant);
+            }
+            OpCode::Nil => self.stack.push(Value::Nil),
+            OpCode::True => self.stack.push(Value::Bool(true)),
+            OpCode::False => self.stack.push(Value::Bool(false)),
+            OpCode::Pop => {
+                self.pop();
+            }
+            OpCode::GetLocal => {
+                let slot = self.read_u16() as usize;
+                let base = self.frame().base;
+                self.stack.push(self.stack[base + slot].clone());
+            }
+            OpCode::SetLocal => {
+                let slot = self.read_u16() as usize;
+                let base = self.frame().base;
+                self.stack[base + slot] = self.peek(0).clone();
+            }
+            OpCode::GetGlobal => {
+                let name = self.read_string();
+                let value = self.frame().closure.globals.borrow().get(&name).cloned();
+                match value {
+                    Some(value) => self.stack.push(value),
+                    None => {
+                        return Err(
+                            self.error(ErrorKind::Name, &format!("Undefined variable '{name}'."))
+                        )
+                    }
+                }
+            }
+            OpCode::DefineGlobal => {
+                let name = self.read_string();
+                let value = self.pop();
+                self.frame()
+                    .closure
+                    .globals
+                    .borrow_mut()
+                    .insert(name, value);
+            }
+            OpCode::SetGlobal => {
+                let name = self.read_string();
+                let value = self.peek(0).clone();
+                let globals = Rc::clone(&self.frame().closure.globals);
+                let mut globals = globals.borrow_mut();
+                let Some(slot) = globals.get_mut(&name) else {
+                    return Err(
+                        self.error(ErrorKind::Name, &format!("Undefined variable '{name}'."))
+                    );
+                };
+                *slot = value;
+            }
+            OpCode::GetUpvalue => {
+                let index = self.read_u16() as usize;
+                let upvalue = Rc::clone(&self.frame().closure.upvalues[index]);
+                let value = match &*upvalue.borrow() {
+                    Upvalue::Open(slot) => self.stack[*slot].clone(),
+                    Upvalue::Closed(value) => value.clone(),
+                };
+                self.stack.push(value);
+            }
+            OpCode::SetUpvalue => {
+                let index = self.read_u16() as usize;
+                let upvalue = Rc::clone(&self.frame().closure.upvalues[index]);
+                let value = self.peek(0).clone();
+                let mut upvalue = upvalue.borrow_mut();
+                match &mut *upvalue {
+                    Upvalue::Open(slot) => self.stack[*slot] = value,
+                    Upvalue::Closed(closed) => *closed = value,
+                }
+            }
+            OpCode::GetProperty => {
+                let name = self.read_string();
+                if let Value::List(list) = self.peek(0).clone() {
+                    let method = self.bind_list_method(list, &name)?;
+                    self.pop();
+                    self.stack.push(method);
+                    return Ok(false);
+                }
+                if let Value::Map(map) = self.peek(0).clone() {
+                    let method = self.bind_map_method(map, &name)?;
+                    self.pop();
+                    self.stack.push(method);
+                    return Ok(false);
+                }
+                if let Value::Error(error) = self.peek(0).clone() {
+                    let value = match error.get(&name, self.span())? {
+                        ErrorProperty::String(s) => Value::String(Rc::from(s)),
+                        ErrorProperty::Number(n) => Value::Number(n),
+                    };
+                    self.pop();
+                    self.stack.push(value);
+                    return Ok(false);
+                }
+                if let Value::Module(module) = self.peek(0).clone() {
+                    let value = self.module_export(&module, &name)?;
+                    self.pop();
+                    self.stack.push(value);
+                    return Ok(false);
+                }
+                if let Value::String(s) = self.peek(0).clone() {
+                    let Some(method) = string::method(&s, &name) else {
+                        let msg = format!("Undefined property '{name}'.");
+                        return Err(self.error(ErrorKind::Property, &msg));
+                    };
+                    self.pop();
+                    self.stack.push(Value::Native(Rc::new(method)));
+                    return Ok(false);
+                }
+                let Value::Instance(instance) = self.peek(0).clone() else {
+                    return Err(self.error(ErrorKind::Type, "Only instances have properties."));
+                };
+
+                let field = instance.borrow().fields.get(&*name).cloned();
+                let value = match field {
+                    Some(value) => value,
+                    None => {
+                        let class = Rc::clone(&instance.borrow().class);
+                        self.bind_method(&class, &name, Value::Instance(instance))?
+                    }
+                };
+                self.pop();
+                self.stack.push(value);
+            }
+            OpCode::SetProperty => {
+                let name = self.read_string();
+                let Value::Instance(instance) = self.peek(1).clone() else {
+                    return Err(self.error(ErrorKind::Type, "Only instances have fields."));
+                };
+
+                let value = self.pop();
+                instance
+                    .borrow_mut()
+                    .fields
+                    .insert(name.to_string(), value.clone());
+                self.pop();
+                self.stack.push(value);
+            }
+            OpCode::GetSuper => {
+                let name = self.read_string();
+                let Value::Class(superclass) = self.pop() else {
+                    return Err(self.error(ErrorKind::Type, "Superclass must be a class."));
+                };
+                let receiver = self.pop();
+                let bound = self.bind_method(&superclass, &name, receiver)?;
+                self.stack.push(bound);
+            }
+            OpCode::Equal => {
+                let right = self.pop();
+                let left = self.pop();
+                self.stack.push(Value::Bool(left.is_equal(&right)));
+            }
+            OpCode::Greater
+            | OpCode::GreaterEqual
+            | OpCode::Less
+            | OpCode::LessEqual
+            | OpCode::Add
+            | OpCode::Subtract
+            | OpCode::Multiply
+            | OpCode::Divide => {
+                let right = self.pop();
+                let left = self.pop();
+                let result = self.binary(op, left, right)?;
+                self.stack.push(result);
+            }
+            OpCode::Not => {
+                let value = self.pop();
+                self.stack.push(Value::Bool(!value.is_truthy()));
+            }
+            OpCode::Negate => match self.pop() {
+                Value::Number(n) => self.stack.push(Value::Number(-n)),
+                _ => return Err(self.error(ErrorKind::Type, "Not a number for MINUS operation.")),
+            },
+            OpCode::Print => {
+                let value = self.pop();
+                println!("{value}");
+            }
+            OpCode::Jump => {
+                let offset = self.read_u16() as usize;
+                self.frame().ip += offset;
+            }
+            OpCode::JumpIfFalse => {
+                let offset = self.read_u16() as usize;
+                if !self.peek(0).is_truthy() {
+                    self.frame().ip += offset;
+                }
+            }
+            OpCode::Loop => {
+                let offset = self.read_u16() as usize;
+                self.frame().ip -= offset;
+            }
+            OpCode::Call => {
+                let arg_count = self.read_byte() as usize;
+                let callee = self.peek(arg_count).clone();
+                self.call_value(callee, arg_count)?;
+            }
+            OpCode::Closure => {
+                let Value::Function(function) = self.read_constant() else {
+                    return Err(self.error(ErrorKind::Error, "Closure constant is not a function."));
+                };
+
+                let mut upvalues = Vec::with_capacity(function.upvalue_count);
+                for _ in 0..function.upvalue_count {
+                    let is_local = self.read_byte() == 1;
+                    let index = self.read_byte() as usize;
+                    if is_local {
+                        let slot = self.frame().base + index;
+                        upvalues.push(self.capture_upvalue(slot));
+                    } else {
+                        upvalues.push(Rc::clone(&self.frame().closure.upvalues[index]));
+                    }
+                }
+
+                let closure = Closure {
+                    function,
+                    upvalues,
+                    globals: Rc::clone(&self.frame().closure.globals),
+                };
+                self.stack.push(Value::Closure(self.heap.alloc(closure)));
+            }
+            OpCode::Import => {
+                let Value::Function(function) = self.read_constant() else {
+                    return Err(self.error(ErrorKind::Error, "Import constant is not a function."));
+                };
+                self.import(function)?;
+            }
+            OpCode::CloseUpvalue => {
+                self.close_upvalues(self.stack.len() - 1);
+                self.pop();
+            }
+            OpCode::Return => {
+                let result = self.pop();
+                let frame = self.frames.pop().unwrap();
+                self.close_upvalues(frame.base);
+
+                // Handlers installed by the returning function are gone too.
+                while let Some(handler) = self.handlers.last() {
+                    if handler.frames <= self.frames.len() {
+                        break;
+                    }
+                    self.handlers.pop();
+                }
+
+                self.stack.truncate(frame.base);
+                self.stack.push(result);
+                if self.frames.is_empty() {
+                    return Ok(true);
+                }
+            }
+            OpCode::Class => {
+                let name = self.read_string();
+                let class = Class {
+                    name: name.to_string(),
+                    methods: HashMap::new(),
+                };
+                self.stack
+                    .push(Value::Class(self.heap.alloc(RefCell::new(class))));
+            }
+            OpCode::Inherit => {
+                let Value::Class(superclass) = self.peek(1).clone() else {
+                    return Err(self.error(ErrorKind::Type, "Superclass must be a class."));
+                };
+                let Value::Class(subclass) = self.peek(0).clone() else {
+                    return Err(self.error(ErrorKind::Type, "Superclass must be a class."));
+                };
+
+                let methods = superclass.borrow().methods.clone();
+                subclass.borrow_mut().methods.extend(methods);
+                self.pop();
+            }
+            OpCode::Method => {
+                let name = self.read_string();
+                let Value::Closure(method) = self.pop() else {
+                    return Err(self.error(ErrorKind::Error, "Method is not a function."));
+                };
+                if let Value::Class(class) = self.peek(0) {
+                    class.borrow_mut().methods.insert(name.to_string(), method);
+                }
+            }
+            OpCode::PushHandler | OpCode::PushFinally => {
+                let offset = self.read_u16() as usize;
+                let target = self.frame().ip + offset;
+                self.handlers.push(Handler {
+                    frames: self.frames.len(),
+                    stack: self.stack.len(),
+                    target,
+                    pending: self.pending.len(),
+                    finally: op == OpCode::PushFinally,
+                });
+            }
+            OpCode::PopHandler => {
+                self.handlers.pop();
+            }
+            OpCode::Throw => {
+                let value = self.pop();
+                let error = match &value {
+                    Value::Error(error) => error.diagnostic.clone(),
+                    value => uncaught(self.span(), value),
+                };
+                self.thrown = Some(value);
+                return Err(error);
+            }
+            OpCode::EndFinally => {
+                self.pop();
+                let Some((error, thrown)) = self.pending.pop() else {
+                    return Err(self.error(ErrorKind::Error, "No error to raise again."));
+                };
+                self.thrown = thrown;
+                return Err(error);
+            }
+            OpCode::BuildList => {
+                let count = self.read_u16() as usize;
+                let items = self.stack.split_off(self.stack.len() - count);
+                self.stack
+                    .push(Value::List(self.heap.alloc(RefCell::new(items))));
+            }
+            OpCode::Interpolate => {
+                let count = self.read_u16() as usize;
+                let parts = self.stack.split_off(self.stack.len() - count);
+                let text: String = parts.iter().map(Value::to_string).collect();
+                self.stack.push(Value::String(Rc::from(text.as_str())));
+            }
+            OpCode::BuildMap => {
+                let count = self.read_u16() as usize;
+                let pairs = self.stack.split_off(self.stack.len() - 2 * count);
+                let mut map = Entries::default();
+                for pair in pairs.chunks(2) {
+                    let key = pair[0].to_key().map_err(|e| self.failure(e))?;
+                    map.insert(key, pair[1].clone());
+                }
+                self.stack
+                    .push(Value::Map(self.heap.alloc(RefCell::new(map))));
+            }
+            OpCode::GetIndex => {
+                let index = self.pop();
+                let item = match self.pop() {
+                    Value::List(list) => {
+                        let items = list.borrow();
+                        let index = element_index(number(&index), items.len())
+                            .map_err(|e| self.failure(e))?;
+                        items[index].clone()
+                    }
+                    Value::Map(map) => {
+                        let key = index.to_key().map_err(|e| self.failure(e))?;
+                        let item = map.borrow().get(&key).cloned();
+                        item.ok_or_else(|| key_not_found(self.span(), &key))?
+                    }
+                    Value::String(s) => {
+                        let c = char_at(&s, number(&index)).map_err(|e| self.failure(e))?;
+                        Value::String(Rc::from(c.as_str()))
+                    }
+                    _ => return Err(self.failure(NOT_INDEXABLE)),
+                };
+                self.stack.push(item);
+            }
+            OpCode::SetIndex => {
+                let value = self.pop();
+                let index = self.pop();
+                match self.pop() {
+                    Value::List(list) => {
+                        let len = list.borrow().len();
+                        let index =
+                            element_index(number(&index), len).map_err(|e| self.failure(e))?;
+                        list.borrow_mut()[index] = value.clone();
+                    }
+                    Value::Map(map) => {
+                        let key = index.to_key().map_err(|e| self.failure(e))?;
+                        map.borrow_mut().insert(key, value.clone());
+                    }
+                    Value::String(_) => return Err(self.failure(IMMUTABLE)),
+                    _ => return Err(self.failure(NOT_INDEXABLE)),
+                }
+                self.stack.push(value);
+            }
+            OpCode::Slice => {
+                let end = bound(&self.pop());
+                let start = bound(&self.pop());
+                let list = match self.pop() {
+                    Value::List(list) => list,
+                    Value::String(s) => {
+                        let slice = char_slice(&s, start, end).map_err(|e| self.failure(e))?;
+                        self.stack.push(Value::String(Rc::from(slice.as_str())));
+                        return Ok(false);
+                    }
+                    _ => {
+                        let msg = "Only lists and strings can be sliced.";
+                        return Err(self.error(ErrorKind::Type, msg));
+                    }
+                };
+                let items = list.borrow();
+                let range = slice_range(start, end, items.len()).map_err(|e| self.failure(e))?;
+                let slice = items[range].to_vec();
+                drop(items);
+                self.stack
+                    .push(Value::List(self.heap.alloc(RefCell::new(slice))));
+            }
+        }
+
+        Ok(false)
+    }
+
+    fn binary(&self, op: OpCode, left: Value, right: Value) -> Result<Value, Diagnostic> {
+        let result = match (op, &left, &right) {
+            (OpCode::Add, Value::Number(l), Value::Number(r)) => Value::Number(l + r),
+            (OpCode::Add, Value::String(l), Value::String(r)) => {
+                Value::String(Rc::from(format!("{l}{r}").as_str()))
+            }
+            (OpCode::Subtract, Value::Number(l), Value::Number(r)) => Value::Number(l - r),
+            (OpCode::Multiply, Value::Number(l), Value::Number(r)) => Value::Number(l * r),
+            (OpCode::Divide, Value::Number(l), Value::Number(r)) => Value::Number(l / r),
+            (OpCode::Greater, Value::Number(l), Value::Number(r)) => Value::Bool(l > r),
+            (OpCode::Greater, Value::String(l), Value::String(r)) => Value::Bool(l > r),
+            (OpCode::GreaterEqual, Value::Number(l), Value::Number(r)) => Value::Bool(l >= r),
+            (OpCode::GreaterEqual, Value::String(l), Value::String(r)) => Value::Bool(l >= r),
+            (OpCode::Less, Value::Number(l), Value::Number(r)) => Value::Bool(l < r),
+            (OpCode::Less, Value::String(l), Value::String(r)) => Value::Bool(l < r),
+            (OpCode::LessEqual, Value::Number(l), Value::Number(r)) => Value::Bool(l <= r),
+            (OpCode::LessEqual, Value::String(l), Value::String(r)) => Value::Bool(l <= r),
+            _ => {
+                let msg = match op {
+                    OpCode::Add => "Not a number or string for plus operation",
+                    OpCode::Subtract => "Not a number for minus operation",
+                    OpCode::Multiply => "Not a number for multiply operation",
+                    OpCode::Divide => "Not a number for division operation",
+                    OpCode::Greater => "Not a number or string for greater operation",
+                    OpCode::GreaterEqual => "Not a number or string for greater equal operation",
+                    OpCode::Less => "Not a number or string for less operation",
+                    _ => "Not a number or string for less equal operation",
+                };
+                return Err(self.error(ErrorKind::Type, msg));
+            }
+        };
+
+        Ok(result)
+    }
+
+    fn call_value(&mut self, callee: Value, arg_count: usize) -> Result<(), Diagnostic> {
+        match callee {
+            Value::Closure(closure) => self.call(closure, arg_count),
+            Value::Native(native) if Rc::ptr_eq(&native, &self.gc_stats) => {
+                if arg_count != 0 {
+                    let msg = native.arity_message(arg_count);
+                    return Err(self.error(ErrorKind::Arity, &msg));
+                }
+                let stats = self.heap.stats().to_string();
+                self.stack.pop();
+                self.stack.push(Value::String(Rc::from(stats.as_str())));
+                Ok(())
+            }
+            Value::Native(native) => {
+                if !native.accepts(arg_count) {
+                    let msg = native.arity_message(arg_count);
+                    return Err(self.error(ErrorKind::Arity, &msg));
+                }
+
+                let args_start = self.stack.len() - arg_count;
+                let args = self.stack[args_start..].iter().map(HostValue::from_vm);
+                let result = native
+                    .invoke(args.collect())
+                    .and_then(|value| {
+                        value
+                            .to_vm(&mut self.heap)
+                            .map_err(|error| (ErrorKind::Type, error.to_string()))
+                    })
+                    .map_err(|(kind, msg)| self.error(kind, &msg))?;
+                self.stack.truncate(args_start - 1);
+                self.stack.push(result);
+                Ok(())
+            }
+            Value::Class(class) => {
+                let instance = Instance {
+                    class: Rc::clone(&class),
+                    fields: HashMap::new(),
+                };
+                let slot = self.stack.len() - arg_count - 1;
+                self.stack[slot] = Value::Instance(self.heap.alloc(RefCell::new(instance)));
+
+                let initializer = class.borrow().methods.get("init").cloned();
+                match initializer {
+                    Some(initializer) => self.call(initializer, arg_count),
+                    None if arg_count != 0 => Err(self.arity_error(0, arg_count)),
+                    None => Ok(()),
+                }
+            }
+            Value::BoundMethod(bound) => {
+                let slot = self.stack.len() - arg_count - 1;
+                self.stack[slot] = bound.receiver.clone();
+                self.call(Rc::clone(&bound.method), arg_count)
+            }
+            Value::ListMethod(method) => {
+                if arg_count != method.arity {
+                    return Err(self.arity_error(method.arity, arg_count));
+                }
+
+                let args = self.stack.split_off(self.stack.len() - arg_count);
+                let result = self.call_list_method(&method, &args)?;
+                self.pop();
+                self.stack.push(result);
+                Ok(())
+            }
+            Value::MapMethod(method) => {
+                if arg_count != method.arity {
+                    return Err(self.arity_error(method.arity, arg_count));
+                }
+
+                let args = self.stack.split_off(self.stack.len() - arg_count);
+                let result = self.call_map_method(&method, &args)?;
+                self.pop();
+                self.stack.push(result);
+                Ok(())
+            }
+            _ => Err(self.error(ErrorKind::Type, "Can only call functions and classes.")),
+        }
+    }
+
+    fn call(&mut self, closure: Rc<Closure>, arg_count: usize) -> Result<(), Diagnostic> {
+        if arg_count != closure.function.arity {
+            return Err(self.arity_error(closure.function.arity, arg_count));
+        }
+
+        // The script's own frame is not a call.
+        if self.frames.len() > self.meter.limits.max_call_depth {
+            let (kind, msg) = STACK_OVERFLOW;
+            return Err(self.error(kind, msg));
+        }
+
+        self.frames.push(CallFrame {
+            closure,
+            ip: 0,
+            base: self.stack.len() - arg_count - 1,
+        });
+        Ok(())
+    }
+
+    /// Pushes the module `function` is the script of, then the value of
+    /// running it. The module runs, in globals of its own, only the first
+    /// time it is imported; later imports push `nil` as its value.
+    fn import(&mut self, function: Rc<Function>) -> Result<(), Diagnostic> {
+        if let Some(module) = self.modules.get(&function.name) {
+            self.stack.push(Value::Module(Rc::clone(module)));
+            self.stack.push(Value::Nil);
+            return Ok(());
+        }
+
+        let mut globals = self.natives.clone();
+        if let Some(native) = self.native_modules.get(&function.name) {
+            for host_function in &native.functions {
+                let name = Rc::from(host_function.name.as_str());
+                globals.insert(name, Value::Native(Rc::new(host_function.clone())));
+            }
+            for (name, value) in &native.constants {
+                let value = value
+                    .to_vm(&mut self.heap)
+                    .map_err(|error| self.error(ErrorKind::Type, &error.to_string()))?;
+                globals.insert(Rc::from(name.as_str()), value);
+            }
+        }
+        let globals = Rc::new(RefCell::new(globals));
+        let module = Rc::new(Module {
+            name: function.name.clone(),
+            globals: Rc::clone(&globals),
+            exports: function.exports.clone().unwrap_or_default(),
+        });
+        self.modules
+            .insert(function.name.clone(), Rc::clone(&module));
+        self.stack.push(Value::Module(module));
+
+        let closure = self.heap.alloc(Closure {
+            function,
+            upvalues: Vec::new(),
+            globals,
+        });
+        self.stack.push(Value::Closure(Rc::clone(&closure)));
+        self.call(closure, 0)
+    }
+
+    fn module_export(&self, module: &Module, name: &str) -> Result<Value, Diagnostic> {
+        if !module.exports.iter().any(|export| export == name) {
+            let msg = not_exported(&module.name, name);
+            return Err(self.error(ErrorKind::Property, &msg));
+        }
+        match module.globals.borrow().get(name) {
+            Some(value) => Ok(value.clone()),
+            None => Err(self.error(ErrorKind::Name, &format!("Undefined variable '{name}'."))),
+        }
+    }
+
+    fn arity_error(&self, arity: usize, arg_count: usize) -> Diagnostic {
+        self.error(ErrorKind::Arity, &arity_message(arity, false, arg_count))
+    }
+
+    fn bind_method(
+        &mut self,
+        class: &Rc<RefCell<Class>>,
+        name: &str,
+        receiver: Value,
+    ) -> Result<Value, Diagnostic> {
+        let method = class.borrow().methods.get(name).cloned();
+        match method {
+            Some(method) => Ok(Value::BoundMethod(
+                self.heap.alloc(BoundMethod { receiver, method }),
+            )),
+            None => Err(self.error(
+                ErrorKind::Property,
+                &format!("Undefined property '{name}'."),
+            )),
+        }
+    }
+
+    fn bind_list_method(
+        &self,
+        list: Rc<RefCell<Vec<Value>>>,
+        name: &str,
+    ) -> Result<Value, Diagnostic> {
+        match LIST_METHODS.iter().find(|(method, _)| *method == name) {
+            Some(&(name, arity)) => {
+                Ok(Value::ListMethod(Rc::new(ListMethod { list, name, arity })))
+            }
+            None => Err(self.error(
+                ErrorKind::Property,
+                &format!("Undefined property '{name}'."),
+            )),
+        }
+    }
+
+    /// Runs a list method; errors are reported at the call, like the ones of
+    /// native functions.
+    fn call_list_method(
+        &mut self,
+        method: &ListMethod,
+        args: &[Value],
+    ) -> Result<Value, Diagnostic> {
+        let list = &method.list;
+        let len = list.borrow().len();
+        match method.name {
+            "push" => {
+                list.borrow_mut().push(args[0].clone());
+                Ok(Value::Nil)
+            }
+            "pop" => list
+                .borrow_mut()
+                .pop()
+                .ok_or_else(|| self.error(ErrorKind::Index, "Can't pop from an empty list.")),
+            "len" => Ok(Value::Number(len as f64)),
+            "insert" => {
+                let index = insert_index(number(&args[0]), len).map_err(|e| self.failure(e))?;
+                list.borrow_mut().insert(index, args[1].clone());
+                Ok(Value::Nil)
+            }
+            "remove" => {
+                let index = element_index(number(&args[0]), len).map_err(|e| self.failure(e))?;
+                Ok(list.borrow_mut().remove(index))
+            }
+            "map" => {
+                let items = list.borrow().clone();
+                let mut mapped = Vec::with_capacity(items.len());
+                for item in items {
+                    mapped.push(self.call_function(&args[0], &[item])?);
+                }
+                Ok(Value::List(self.heap.alloc(RefCell::new(mapped))))
+            }
+            "filter" => {
+                let items = list.borrow().clone();
+                let mut kept = Vec::new();
+                for item in items {
+                    if self
+                        .call_function(&args[0], std::slice::from_ref(&item))?
+                        .is_truthy()
+                    {
+                        kept.push(item);
+                    }
+                }
+                Ok(Value::List(self.heap.alloc(RefCell::new(kept))))
+            }
+            "reduce" => {
+                let items = list.borrow().clone();
+                let mut accumulator = args[1].clone();
+                for item in items {
+                    accumulator = self.call_function(&args[0], &[accumulator, item])?;
+                }
+                Ok(accumulator)
+            }
+            "sort" => {
+                sort(&mut list.borrow_mut(), number, text)
+                    .map_err(|(kind, msg)| self.error(kind, msg))?;
+                Ok(Value::Nil)
+            }
+            name => unreachable!("unknown list method {name}"),
+        }
+    }
+
+    fn bind_map_method(
+        &self,
+        map: Rc<RefCell<Entries<Value>>>,
+        name: &str,
+    ) -> Result<Value, Diagnostic> {
+        match MAP_METHODS.iter().find(|(method, _)| *method == name) {
+            Some(&(name, arity)) => Ok(Value::MapMethod(Rc::new(MapMethod { map, name, arity }))),
+            None => Err(self.error(
+                ErrorKind::Property,
+                &format!("Undefined property '{name}'."),
+            )),
+        }
+    }
+
+    fn call_map_method(&mut self, method: &MapMethod, args: &[Value]) -> Result<Value, Diagnostic> {
+        let map = &method.map;
+        match method.name {
+            "keys" => {
+                let keys = map.borrow().iter().map(|(key, _)| key.into()).collect();
+                Ok(Value::List(self.heap.alloc(RefCell::new(keys))))
+            }
+            "values" => {
+                let values = map.borrow().iter().map(|(_, v)| v.clone()).collect();
+                Ok(Value::List(self.heap.alloc(RefCell::new(values))))
+            }
+            "has" => {
+                let key = args[0].to_key().map_err(|e| self.failure(e))?;
+                Ok(Value::Bool(map.borrow().contains_key(&key)))
+            }
+            "remove" => {
+                let key = args[0].to_key().map_err(|e| self.failure(e))?;
+                let removed = map.borrow_mut().remove(&key);
+                removed.ok_or_else(|| key_not_found(self.span(), &key))
+            }
+            "len" => Ok(Value::Number(map.borrow().len() as f64)),
+            name => unreachable!("unknown map method {name}"),
+        }
+    }
+
+    fn capture_upvalue(&mut self, slot: usize) -> Rc<RefCell<Upvalue>> {
+        for upvalue in &self.open_upvalues {
+            if matches!(*upvalue.borrow(), Upvalue::Open(open) if open == slot) {
+                return Rc::clone(upvalue);
+            }
+        }
+
+        let upvalue = self.heap.alloc(RefCell::new(Upvalue::Open(slot)));
+        self.open_upvalues.push(Rc::clone(&upvalue));
+        upvalue
+    }
+
+    /// Moves every captured stack slot at or above `last` into its upvalue.
+    fn close_upvalues(&mut self, last: usize) {
+        let stack = &self.stack;
+        self.open_upvalues.retain(|upvalue| {
+            let slot = match *upvalue.borrow() {
+                Upvalue::Open(slot) => slot,
+                Upvalue::Closed(_) => return false,
+            };
+            if slot < last {
+                return true;
+            }
+
+            let value = stack.get(slot).cloned().unwrap_or(Value::Nil);
+            *upvalue.borrow_mut() = Upvalue::Closed(value);
+            false
+        });
+    }
+}
+
+/// The span of the instruction `frame` last read.
+fn current_span(frame: &CallFrame) -> &Span {
+    &frame.closure.function.chunk.spans[frame.ip.saturating_sub(1)]
+}
+
+fn number(value: &Value) -> Option<f64> {
+    match value {
+        Value::Number(n) => Some(*n),
+        _ => None,
+    }
+}
+
+fn text(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(s) => Some(s),
+        _ => None,
+    }
+}
+
+/// A slice bound: `nil` when omitted, anything else must be an integer.
+fn bound(value: &Value) -> Option<f64> {
+    match value {
+        Value::Nil => None,
+        Value::Number(n) => Some(*n),
+        _ => Some(f64::NAN),
+    }
+}
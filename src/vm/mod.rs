@@ -0,0 +1,7 @@
+pub mod chunk;
+pub mod compiler;
+pub mod disassembler;
+pub mod machine;
+pub mod serializer;
+pub mod value;
+pub mod verifier;
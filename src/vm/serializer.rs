@@ -0,0 +1,260 @@
+use crate::models::span::Span;
+use crate::vm::chunk::Chunk;
+use crate::vm::value::{Function, Value};
+use crate::vm::verifier::verify;
+use std::rc::Rc;
+
+/// Every `.loxc` file starts with these bytes.
+pub const MAGIC: &[u8; 4] = b"LOXC";
+
+/// Bumped whenever the encoding or the instruction set changes. Files written
+/// with another version are rejected instead of being misinterpreted.
+pub const FORMAT_VERSION: u16 = 7;
+
+const HEADER_LEN: usize = 4 + 2 + 4 + 4;
+
+const TAG_NUMBER: u8 = 0;
+const TAG_STRING: u8 = 1;
+const TAG_FUNCTION: u8 = 2;
+
+/// Returns true when `bytes` look like a compiled `.loxc` file.
+pub fn is_compiled(bytes: &[u8]) -> bool {
+    bytes.starts_with(MAGIC)
+}
+
+/// Encodes the top-level `function` and every prototype it references.
+///
+/// Layout: magic, format version (u16), CRC-32 of the payload (u32), payload
+/// length (u32), payload. All integers are little-endian.
+pub fn serialize(function: &Function) -> Vec<u8> {
+    let mut payload = Vec::new();
+    write_function(function, &mut payload);
+
+    let mut bytes = Vec::with_capacity(HEADER_LEN + payload.len());
+    bytes.extend_from_slice(MAGIC);
+    bytes.extend_from_slice(&FORMAT_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&crc32(&payload).to_le_bytes());
+    bytes.extend_from_slice(&(payload.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(&payload);
+    bytes
+}
+
+/// Decodes a `.loxc` file, validating its header and checksum first and its
+/// bytecode last.
+pub fn deserialize(bytes: &[u8]) -> Result<Rc<Function>, String> {
+    if !is_compiled(bytes) {
+        return Err("Not a compiled Lox file (bad magic header).".to_string());
+    }
+    if bytes.len() < HEADER_LEN {
+        return Err("Compiled file is truncated.".to_string());
+    }
+
+    let version = u16::from_le_bytes([bytes[4], bytes[5]]);
+    if version != FORMAT_VERSION {
+        return Err(format!(
+            "Unsupported bytecode format version {version} (expected {FORMAT_VERSION}). Recompile the script from source."
+        ));
+    }
+
+    let checksum = u32::from_le_bytes([bytes[6], bytes[7], bytes[8], bytes[9]]);
+    let length = u32::from_le_bytes([bytes[10], bytes[11], bytes[12], bytes[13]]) as usize;
+    let payload = &bytes[HEADER_LEN..];
+    if payload.len() != length {
+        return Err("Compiled file is truncated.".to_string());
+    }
+    if crc32(payload) != checksum {
+        return Err("Compiled file is corrupt (checksum mismatch).".to_string());
+    }
+
+    let mut reader = Reader {
+        bytes: payload,
+        position: 0,
+    };
+    let function = reader.function()?;
+    if reader.position != payload.len() {
+        return Err("Compiled file has trailing data.".to_string());
+    }
+    verify(&function)?;
+    Ok(Rc::new(function))
+}
+
+fn write_function(function: &Function, out: &mut Vec<u8>) {
+    write_string(&function.name, out);
+    write_u32(function.arity as u32, out);
+    write_u32(function.upvalue_count as u32, out);
+    // The exported names of a module, or `u32::MAX` for other functions.
+    match &function.exports {
+        Some(exports) => {
+            write_u32(exports.len() as u32, out);
+            for name in exports {
+                write_string(name, out);
+            }
+        }
+        None => write_u32(u32::MAX, out),
+    }
+
+    let chunk = &function.chunk;
+    write_u32(chunk.code.len() as u32, out);
+    out.extend_from_slice(&chunk.code);
+
+    // Span table: the source file once, then (start, length, line, column,
+    // count) runs of equal spans.
+    let file = chunk.spans.first().map(|span| span.file.clone());
+    write_string(file.as_deref().unwrap_or_default(), out);
+    let mut runs: Vec<(&Span, u32)> = Vec::new();
+    for span in &chunk.spans {
+        match runs.last_mut() {
+            Some((last, count)) if *last == span => *count += 1,
+            _ => runs.push((span, 1)),
+        }
+    }
+    write_u32(runs.len() as u32, out);
+    for (span, count) in runs {
+        write_u32(span.start as u32, out);
+        write_u32(span.len as u32, out);
+        write_u32(span.line as u32, out);
+        write_u32(span.column as u32, out);
+        write_u32(count, out);
+    }
+
+    write_u32(chunk.constants.len() as u32, out);
+    for constant in &chunk.constants {
+        match constant {
+            Value::Number(n) => {
+                out.push(TAG_NUMBER);
+                out.extend_from_slice(&n.to_le_bytes());
+            }
+            Value::String(s) => {
+                out.push(TAG_STRING);
+                write_string(s, out);
+            }
+            Value::Function(nested) => {
+                out.push(TAG_FUNCTION);
+                write_function(nested, out);
+            }
+            other => unreachable!("the compiler never emits {other:?} as a constant"),
+        }
+    }
+}
+
+fn write_u32(value: u32, out: &mut Vec<u8>) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn write_string(value: &str, out: &mut Vec<u8>) {
+    write_u32(value.len() as u32, out);
+    out.extend_from_slice(value.as_bytes());
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8], String> {
+        let end = self
+            .position
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| "Compiled file is truncated.".to_string())?;
+        let slice = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(slice)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(f64::from_le_bytes(bytes))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        let len = self.u32()? as usize;
+        let bytes = self.take(len)?.to_vec();
+        String::from_utf8(bytes).map_err(|_| "Compiled file contains invalid UTF-8.".to_string())
+    }
+
+    fn function(&mut self) -> Result<Function, String> {
+        let name = self.string()?;
+        let arity = self.u32()? as usize;
+        let upvalue_count = self.u32()? as usize;
+        let exports = match self.u32()? {
+            u32::MAX => None,
+            count => Some(
+                (0..count)
+                    .map(|_| self.string())
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+
+        let code_len = self.u32()? as usize;
+        let code = self.take(code_len)?.to_vec();
+
+        let file: Rc<str> = Rc::from(self.string()?);
+        let mut spans = Vec::with_capacity(code_len);
+        let runs = self.u32()?;
+        for _ in 0..runs {
+            let start = self.u32()? as usize;
+            let len = self.u32()? as usize;
+            let line = self.u32()? as usize;
+            let column = self.u32()? as usize;
+            let count = self.u32()? as usize;
+            if spans.len() + count > code_len {
+                return Err("Compiled file has an invalid span table.".to_string());
+            }
+            let span = Span::new(&file, start, len, line, column);
+            spans.extend(std::iter::repeat(span).take(count));
+        }
+        if spans.len() != code_len {
+            return Err("Compiled file has an invalid span table.".to_string());
+        }
+
+        let constant_count = self.u32()?;
+        let mut constants = Vec::new();
+        for _ in 0..constant_count {
+            let constant = match self.u8()? {
+                TAG_NUMBER => Value::Number(self.f64()?),
+                TAG_STRING => Value::String(self.string()?.into()),
+                TAG_FUNCTION => Value::Function(Rc::new(self.function()?)),
+                tag => return Err(format!("Compiled file has an unknown constant tag {tag}.")),
+            };
+            constants.push(constant);
+        }
+
+        Ok(Function {
+            name,
+            arity,
+            upvalue_count,
+            exports,
+            chunk: Chunk {
+                code,
+                constants,
+                spans,
+            },
+        })
+    }
+}
+
+/// CRC-32 (IEEE 802.3), computed bitwise; compiled files are small enough
+/// that a lookup table is not worth it.
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
@@ -0,0 +1,439 @@
+use crate::diagnostics::Failure;
+use crate::gc::{Trace, Tracer};
+use crate::host::HostFunction;
+use crate::models::lox_error::LoxError;
+use crate::models::lox_map::{Entries, MapKey, KEY_NOT_HASHABLE};
+use crate::vm::chunk::Chunk;
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::fmt::{Debug, Display, Formatter};
+use std::rc::Rc;
+
+/// Runtime value of the bytecode virtual machine. Primitives mirror
+/// `models::values::Value`; heap objects are the VM's own representations.
+#[derive(Debug, Clone)]
+pub enum Value {
+    Nil,
+    Bool(bool),
+    Number(f64),
+    String(Rc<str>),
+    Function(Rc<Function>),
+    Closure(Rc<Closure>),
+    Native(Rc<HostFunction>),
+    Class(Rc<RefCell<Class>>),
+    Instance(Rc<RefCell<Instance>>),
+    BoundMethod(Rc<BoundMethod>),
+    List(Rc<RefCell<Vec<Value>>>),
+    ListMethod(Rc<ListMethod>),
+    Map(Rc<RefCell<Entries<Value>>>),
+    MapMethod(Rc<MapMethod>),
+    Error(Rc<LoxError>),
+    Module(Rc<Module>),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Nil => write!(f, "nil"),
+            Self::Bool(b) => write!(f, "{b}"),
+            Self::Number(n) => write!(f, "{n}"),
+            Self::String(s) => write!(f, "{s}"),
+            Self::Function(func) => write!(f, "{func}"),
+            Self::Closure(closure) => write!(f, "{}", closure.function),
+            Self::Native(native) => write!(f, "<fn {}>", native.name),
+            Self::Class(class) => write!(f, "{}", class.borrow().name),
+            Self::Instance(instance) => {
+                write!(f, "{} instance", instance.borrow().class.borrow().name)
+            }
+            Self::BoundMethod(bound) => write!(f, "{}", bound.method.function),
+            Self::List(list) => write_list(f, list),
+            Self::ListMethod(method) => write!(f, "<fn {}>", method.name),
+            Self::Map(map) => write_map(f, map),
+            Self::MapMethod(method) => write!(f, "<fn {}>", method.name),
+            Self::Error(error) => write!(f, "{error}"),
+            Self::Module(module) => write!(f, "<module {}>", module.name),
+        }
+    }
+}
+
+thread_local! {
+    /// Lists and maps being printed, so that one containing itself prints
+    /// `[...]` or `{...}` instead of recursing forever.
+    static PRINTING: RefCell<Vec<*const ()>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Writes the elements of a collection with `write_items`, or `elided` when
+/// the collection at `address` is already being printed.
+fn write_once(
+    f: &mut Formatter<'_>,
+    address: *const (),
+    elided: &str,
+    write_items: impl FnOnce(&mut Formatter<'_>) -> std::fmt::Result,
+) -> std::fmt::Result {
+    if PRINTING.with_borrow(|printing| printing.contains(&address)) {
+        return write!(f, "{elided}");
+    }
+
+    PRINTING.with_borrow_mut(|printing| printing.push(address));
+    let result = write_items(f);
+    PRINTING.with_borrow_mut(|printing| printing.pop());
+    result
+}
+
+fn write_list(f: &mut Formatter<'_>, list: &Rc<RefCell<Vec<Value>>>) -> std::fmt::Result {
+    write_once(f, Rc::as_ptr(list) as *const (), "[...]", |f| {
+        write!(f, "[")?;
+        for (i, item) in list.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write_element(f, item)?;
+        }
+        write!(f, "]")
+    })
+}
+
+fn write_map(f: &mut Formatter<'_>, map: &Rc<RefCell<Entries<Value>>>) -> std::fmt::Result {
+    write_once(f, Rc::as_ptr(map) as *const (), "{...}", |f| {
+        write!(f, "{{")?;
+        for (i, (key, value)) in map.borrow().iter().enumerate() {
+            if i > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{key}: ")?;
+            write_element(f, value)?;
+        }
+        write!(f, "}}")
+    })
+}
+
+/// Writes a value inside a collection, where strings are quoted.
+fn write_element(f: &mut Formatter<'_>, value: &Value) -> std::fmt::Result {
+    match value {
+        Value::String(s) => write!(f, "\"{s}\""),
+        value => write!(f, "{value}"),
+    }
+}
+
+impl Value {
+    /// Same truthiness rules as the tree-walking interpreter.
+    pub fn is_truthy(&self) -> bool {
+        match self {
+            Self::Nil => false,
+            Self::Bool(b) => *b,
+            Self::String(s) => !s.is_empty(),
+            Self::Number(n) => *n != 0.0,
+            _ => true,
+        }
+    }
+
+    pub fn is_equal(&self, other: &Value) -> bool {
+        // The elements of lists and maps are compared from a worklist rather
+        // than by recursion, so that deeply nested ones cannot overflow the
+        // stack. `comparing` holds the pairs of lists and maps compared so
+        // far: one that comes around again through a cycle is equal unless
+        // something else tells the two sides apart.
+        let mut comparing: HashSet<(*const (), *const ())> = HashSet::new();
+        let mut pending = vec![(self.clone(), other.clone())];
+        while let Some((l, r)) = pending.pop() {
+            if !l.equals(&r, &mut comparing, &mut pending) {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Compares `self` and `other` like `is_equal`, except that the elements
+    /// of two lists or maps are left in `pending` to compare.
+    fn equals(
+        &self,
+        other: &Value,
+        comparing: &mut HashSet<(*const (), *const ())>,
+        pending: &mut Vec<(Value, Value)>,
+    ) -> bool {
+        match (self, other) {
+            (Self::Nil, Self::Nil) => true,
+            (Self::Bool(l), Self::Bool(r)) => l == r,
+            (Self::Number(l), Self::Number(r)) => l == r,
+            (Self::String(l), Self::String(r)) => l == r,
+            (Self::Function(l), Self::Function(r)) => Rc::ptr_eq(l, r),
+            (Self::Closure(l), Self::Closure(r)) => Rc::ptr_eq(l, r),
+            (Self::Native(l), Self::Native(r)) => Rc::ptr_eq(l, r),
+            (Self::Class(l), Self::Class(r)) => Rc::ptr_eq(l, r),
+            (Self::Instance(l), Self::Instance(r)) => Rc::ptr_eq(l, r),
+            (Self::BoundMethod(l), Self::BoundMethod(r)) => Rc::ptr_eq(l, r),
+            (Self::List(l), Self::List(r)) => {
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if Rc::ptr_eq(l, r) || !comparing.insert(pair) {
+                    return true;
+                }
+                let (l, r) = (l.borrow(), r.borrow());
+                pending.extend(l.iter().cloned().zip(r.iter().cloned()));
+                l.len() == r.len()
+            }
+            (Self::ListMethod(l), Self::ListMethod(r)) => Rc::ptr_eq(l, r),
+            (Self::Map(l), Self::Map(r)) => {
+                let pair = (Rc::as_ptr(l) as *const (), Rc::as_ptr(r) as *const ());
+                if Rc::ptr_eq(l, r) || !comparing.insert(pair) {
+                    return true;
+                }
+                let (l, r) = (l.borrow(), r.borrow());
+                l.len() == r.len()
+                    && l.iter().all(|(key, l)| match r.get(key) {
+                        Some(r) => {
+                            pending.push((l.clone(), r.clone()));
+                            true
+                        }
+                        None => false,
+                    })
+            }
+            (Self::MapMethod(l), Self::MapMethod(r)) => Rc::ptr_eq(l, r),
+            (Self::Error(l), Self::Error(r)) => Rc::ptr_eq(l, r),
+            (Self::Module(l), Self::Module(r)) => Rc::ptr_eq(l, r),
+            _ => false,
+        }
+    }
+
+    /// The map key this value stands for, if it is hashable.
+    pub fn to_key(&self) -> Result<MapKey, Failure> {
+        match self {
+            Self::Nil => Ok(MapKey::Nil),
+            Self::Bool(b) => Ok(MapKey::Bool(*b)),
+            Self::Number(n) => Ok(MapKey::number(*n)),
+            Self::String(s) => Ok(MapKey::String(Rc::clone(s))),
+            _ => Err(KEY_NOT_HASHABLE),
+        }
+    }
+}
+
+impl From<&MapKey> for Value {
+    fn from(key: &MapKey) -> Self {
+        match key {
+            MapKey::Nil => Value::Nil,
+            MapKey::Bool(b) => Value::Bool(*b),
+            MapKey::Number(bits) => Value::Number(f64::from_bits(*bits)),
+            MapKey::String(s) => Value::String(Rc::clone(s)),
+        }
+    }
+}
+
+/// A compiled function body. The top-level script is a function too, with an
+/// empty name.
+#[derive(Debug, Default)]
+pub struct Function {
+    pub name: String,
+    pub arity: usize,
+    pub upvalue_count: usize,
+    /// The names a module exports; set only on the script of a module.
+    pub exports: Option<Vec<String>>,
+    pub chunk: Chunk,
+}
+
+impl Display for Function {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        if self.name.is_empty() {
+            write!(f, "<script>")
+        } else if self.exports.is_some() {
+            write!(f, "<module {}>", self.name)
+        } else {
+            write!(f, "<fn {}>", self.name)
+        }
+    }
+}
+
+/// Variable captured by a closure: it points into the stack while the
+/// enclosing function runs and owns the value once it returned.
+#[derive(Debug)]
+pub enum Upvalue {
+    Open(usize),
+    Closed(Value),
+}
+
+/// Global variables of the program or of one module.
+pub type Globals = Rc<RefCell<HashMap<Rc<str>, Value>>>;
+
+#[derive(Debug)]
+pub struct Closure {
+    pub function: Rc<Function>,
+    pub upvalues: Vec<Rc<RefCell<Upvalue>>>,
+    /// The globals of the module the closure was created in.
+    pub globals: Globals,
+}
+
+/// A module that has run, as the name it is imported as sees it.
+#[derive(Debug)]
+pub struct Module {
+    pub name: String,
+    pub globals: Globals,
+    pub exports: Vec<String>,
+}
+
+#[derive(Debug)]
+pub struct Class {
+    pub name: String,
+    pub methods: HashMap<String, Rc<Closure>>,
+}
+
+#[derive(Debug)]
+pub struct Instance {
+    pub class: Rc<RefCell<Class>>,
+    pub fields: HashMap<String, Value>,
+}
+
+#[derive(Debug)]
+pub struct BoundMethod {
+    pub receiver: Value,
+    pub method: Rc<Closure>,
+}
+
+/// A list method looked up on a list, such as `xs.push`, bound to that list.
+#[derive(Debug)]
+pub struct ListMethod {
+    pub list: Rc<RefCell<Vec<Value>>>,
+    pub name: &'static str,
+    pub arity: usize,
+}
+
+/// A map method looked up on a map, such as `m.keys`, bound to that map.
+#[derive(Debug)]
+pub struct MapMethod {
+    pub map: Rc<RefCell<Entries<Value>>>,
+    pub name: &'static str,
+    pub arity: usize,
+}
+
+impl Value {
+    /// Reports the heap object this value holds, if any, to `tracer`.
+    pub fn trace(&self, tracer: &mut Tracer) {
+        match self {
+            Self::Closure(closure) => tracer.object(closure),
+            Self::Class(class) => tracer.object(class),
+            Self::Instance(instance) => tracer.object(instance),
+            Self::BoundMethod(bound) => tracer.object(bound),
+            Self::List(list) => tracer.object(list),
+            Self::Map(map) => tracer.object(map),
+            _ => {}
+        }
+    }
+}
+
+impl Trace for Closure {
+    fn trace(&self, tracer: &mut Tracer) {
+        for upvalue in &self.upvalues {
+            tracer.object(upvalue);
+        }
+    }
+
+    // The upvalues are fixed once the closure exists; cycles through it are
+    // broken by clearing the upvalue on the other side.
+    fn clear(&self) {}
+}
+
+impl Trace for RefCell<Upvalue> {
+    fn trace(&self, tracer: &mut Tracer) {
+        match self.try_borrow() {
+            Ok(upvalue) => {
+                if let Upvalue::Closed(value) = &*upvalue {
+                    value.trace(tracer);
+                }
+            }
+            Err(_) => tracer.pin(),
+        }
+    }
+
+    fn clear(&self) {
+        let value = match self.try_borrow_mut() {
+            Ok(mut upvalue) => std::mem::replace(&mut *upvalue, Upvalue::Closed(Value::Nil)),
+            Err(_) => return,
+        };
+        drop(value);
+    }
+}
+
+impl Trace for RefCell<Class> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(class) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        for method in class.methods.values() {
+            tracer.object(method);
+        }
+    }
+
+    // Methods only reach their class through an upvalue or an instance, and
+    // clearing those is enough.
+    fn clear(&self) {}
+}
+
+impl Trace for RefCell<Instance> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(instance) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        tracer.object(&instance.class);
+        for value in instance.fields.values() {
+            value.trace(tracer);
+        }
+    }
+
+    fn clear(&self) {
+        let fields = match self.try_borrow_mut() {
+            Ok(mut instance) => std::mem::take(&mut instance.fields),
+            Err(_) => return,
+        };
+        drop(fields);
+    }
+}
+
+impl Trace for BoundMethod {
+    fn trace(&self, tracer: &mut Tracer) {
+        self.receiver.trace(tracer);
+        tracer.object(&self.method);
+    }
+
+    // Bound methods are immutable; cycles through one also run through the
+    // fields of its receiver.
+    fn clear(&self) {}
+}
+
+impl Trace for RefCell<Vec<Value>> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(items) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        for item in items.iter() {
+            item.trace(tracer);
+        }
+    }
+
+    fn clear(&self) {
+        let items = match self.try_borrow_mut() {
+            Ok(mut items) => std::mem::take(&mut *items),
+            Err(_) => return,
+        };
+        drop(items);
+    }
+}
+
+impl Trace for RefCell<Entries<Value>> {
+    fn trace(&self, tracer: &mut Tracer) {
+        let Ok(entries) = self.try_borrow() else {
+            tracer.pin();
+            return;
+        };
+        for (_, value) in entries.iter() {
+            value.trace(tracer);
+        }
+    }
+
+    fn clear(&self) {
+        let entries = match self.try_borrow_mut() {
+            Ok(mut entries) => std::mem::take(&mut *entries),
+            Err(_) => return,
+        };
+        drop(entries);
+    }
+}
@@ -0,0 +1,312 @@
+//! Checks the bytecode of functions loaded from compiled files, so that a
+//! crafted or damaged `.loxc` file is rejected when it is loaded instead of
+//! making the virtual machine index out of bounds while it runs.
+//!
+//! Besides decoding every instruction, the verifier follows the control flow
+//! of each function and tracks the least number of values the function can
+//! have on the stack before each instruction, counting its callee and
+//! arguments. Instructions that pop or address more values than that are
+//! rejected.
+
+use crate::vm::chunk::{Chunk, OpCode};
+use crate::vm::value::{Function, Value};
+
+/// Verifies `function` and every function in its constants.
+pub fn verify(function: &Function) -> Result<(), String> {
+    check_function(function).map_err(|(offset, problem)| {
+        format!("Compiled file has invalid bytecode in {function} at offset {offset}: {problem}.")
+    })?;
+
+    for constant in &function.chunk.constants {
+        if let Value::Function(nested) = constant {
+            verify(nested)?;
+        }
+    }
+    Ok(())
+}
+
+/// A problem with the instruction at an offset of the code.
+type Problem = (usize, String);
+
+/// What the constant operand of an instruction must be.
+#[derive(Clone, Copy, PartialEq)]
+enum Constant {
+    Any,
+    Name,
+    Function,
+}
+
+/// The operands of an instruction.
+#[derive(Clone, Copy)]
+enum Operand {
+    None,
+    Constant(Constant),
+    Local,
+    Upvalue,
+    Count,
+    ArgCount,
+    Forward,
+    Backward,
+    Closure,
+}
+
+fn operand(op: OpCode) -> Operand {
+    match op {
+        OpCode::Constant => Operand::Constant(Constant::Any),
+        OpCode::GetGlobal
+        | OpCode::DefineGlobal
+        | OpCode::SetGlobal
+        | OpCode::GetProperty
+        | OpCode::SetProperty
+        | OpCode::GetSuper
+        | OpCode::Class
+        | OpCode::Method => Operand::Constant(Constant::Name),
+        OpCode::Import => Operand::Constant(Constant::Function),
+        OpCode::GetLocal | OpCode::SetLocal => Operand::Local,
+        OpCode::GetUpvalue | OpCode::SetUpvalue => Operand::Upvalue,
+        OpCode::BuildList | OpCode::BuildMap | OpCode::Interpolate => Operand::Count,
+        OpCode::Call => Operand::ArgCount,
+        OpCode::Jump | OpCode::JumpIfFalse | OpCode::PushHandler | OpCode::PushFinally => {
+            Operand::Forward
+        }
+        OpCode::Loop => Operand::Backward,
+        OpCode::Closure => Operand::Closure,
+        _ => Operand::None,
+    }
+}
+
+/// A decoded instruction.
+struct Instruction {
+    op: OpCode,
+    /// The u16 or u8 operand, if any.
+    value: usize,
+    /// The offset of the next instruction.
+    next: usize,
+}
+
+fn decode(function: &Function, offset: usize) -> Result<Instruction, String> {
+    let chunk = &function.chunk;
+    let byte = chunk.code[offset];
+    let op = OpCode::from_byte(byte).ok_or_else(|| format!("unknown opcode {byte}"))?;
+    let truncated = || format!("{} has a truncated operand", op.name());
+    let byte_at = |at: usize| chunk.code.get(at).copied().ok_or_else(truncated);
+    let u16_at = |at: usize| -> Result<usize, String> {
+        Ok(u16::from_be_bytes([byte_at(at)?, byte_at(at + 1)?]) as usize)
+    };
+
+    let (value, next) = match operand(op) {
+        Operand::None => (0, offset + 1),
+        Operand::ArgCount => (byte_at(offset + 1)? as usize, offset + 2),
+        Operand::Closure => {
+            let index = u16_at(offset + 1)?;
+            let Some(Value::Function(closed)) = chunk.constants.get(index) else {
+                return Err(format!("{} needs a function constant", op.name()));
+            };
+            let next = offset + 3 + 2 * closed.upvalue_count;
+            byte_at(next - 1)?;
+            (index, next)
+        }
+        _ => (u16_at(offset + 1)?, offset + 3),
+    };
+    Ok(Instruction { op, value, next })
+}
+
+/// Checks the instructions of `function` on their own: operands within the
+/// code and constants of the right kind.
+fn check_operands(
+    function: &Function,
+    offset: usize,
+    instruction: &Instruction,
+) -> Result<(), String> {
+    let Instruction { op, value, .. } = *instruction;
+    let chunk = &function.chunk;
+    match operand(op) {
+        Operand::Constant(kind) => {
+            let constant = chunk
+                .constants
+                .get(value)
+                .ok_or_else(|| format!("constant {value} does not exist"))?;
+            match (kind, constant) {
+                (Constant::Name, Value::String(_))
+                | (Constant::Function, Value::Function(_))
+                | (Constant::Any, _) => Ok(()),
+                (Constant::Name, _) => Err(format!("{} needs a string constant", op.name())),
+                (Constant::Function, _) => Err(format!("{} needs a function constant", op.name())),
+            }
+        }
+        Operand::Upvalue if value >= function.upvalue_count => {
+            Err(format!("upvalue {value} does not exist"))
+        }
+        Operand::Closure => {
+            let Some(Value::Function(closed)) = chunk.constants.get(value) else {
+                unreachable!("decode checks the constant of a closure");
+            };
+            for capture in 0..closed.upvalue_count {
+                let at = offset + 3 + 2 * capture;
+                let (is_local, index) = (chunk.code[at], chunk.code[at + 1] as usize);
+                if is_local != 1 && index >= function.upvalue_count {
+                    return Err(format!("upvalue {index} does not exist"));
+                }
+            }
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// The values `op` pops, or peeks at, and the values it pushes.
+fn stack_effect(op: OpCode, value: usize) -> (usize, usize) {
+    match op {
+        OpCode::Constant
+        | OpCode::Nil
+        | OpCode::True
+        | OpCode::False
+        | OpCode::GetLocal
+        | OpCode::GetGlobal
+        | OpCode::GetUpvalue
+        | OpCode::Closure
+        | OpCode::Class => (0, 1),
+        OpCode::Import => (0, 2),
+        OpCode::Pop
+        | OpCode::DefineGlobal
+        | OpCode::Print
+        | OpCode::CloseUpvalue
+        | OpCode::Return
+        | OpCode::Throw
+        | OpCode::EndFinally => (1, 0),
+        OpCode::SetLocal
+        | OpCode::SetGlobal
+        | OpCode::SetUpvalue
+        | OpCode::JumpIfFalse
+        | OpCode::GetProperty
+        | OpCode::Not
+        | OpCode::Negate => (1, 1),
+        OpCode::SetProperty
+        | OpCode::GetSuper
+        | OpCode::Equal
+        | OpCode::Greater
+        | OpCode::GreaterEqual
+        | OpCode::Less
+        | OpCode::LessEqual
+        | OpCode::Add
+        | OpCode::Subtract
+        | OpCode::Multiply
+        | OpCode::Divide
+        | OpCode::GetIndex
+        | OpCode::Inherit
+        | OpCode::Method => (2, 1),
+        OpCode::SetIndex | OpCode::Slice => (3, 1),
+        OpCode::Call => (value + 1, 1),
+        OpCode::BuildList | OpCode::Interpolate => (value, 1),
+        OpCode::BuildMap => (2 * value, 1),
+        OpCode::Jump
+        | OpCode::Loop
+        | OpCode::PushHandler
+        | OpCode::PushFinally
+        | OpCode::PopHandler => (0, 0),
+    }
+}
+
+fn check_function(function: &Function) -> Result<(), Problem> {
+    let chunk: &Chunk = &function.chunk;
+    if chunk.code.is_empty() {
+        return Err((0, "the function has no code".to_string()));
+    }
+
+    // Decode every instruction, reachable or not, to know where they start.
+    let mut instructions = Vec::new();
+    let mut starts = vec![false; chunk.code.len()];
+    let mut offset = 0;
+    while offset < chunk.code.len() {
+        let instruction = decode(function, offset).map_err(|problem| (offset, problem))?;
+        check_operands(function, offset, &instruction).map_err(|problem| (offset, problem))?;
+        starts[offset] = true;
+        let next = instruction.next;
+        instructions.push((offset, instruction));
+        offset = next;
+    }
+    let index_of = |offset: usize| instructions.binary_search_by_key(&offset, |(at, _)| *at);
+
+    // The least stack height before each instruction, following every path
+    // from the entry. Heights only decrease, so this terminates.
+    let mut heights: Vec<Option<usize>> = vec![None; instructions.len()];
+    heights[0] = Some(function.arity + 1);
+    let mut pending = vec![0];
+    while let Some(index) = pending.pop() {
+        let (offset, instruction) = &instructions[index];
+        let (offset, height) = (*offset, heights[index].unwrap());
+        let Instruction { op, value, next } = *instruction;
+        let problem = |problem: String| (offset, problem);
+
+        let (pops, pushes) = stack_effect(op, value);
+        if pops > height {
+            return Err(problem(format!(
+                "{} pops more values than the stack holds",
+                op.name()
+            )));
+        }
+        let uses_slot = match op {
+            OpCode::GetLocal | OpCode::SetLocal => Some(value),
+            _ => None,
+        };
+        let captures = match op {
+            OpCode::Closure => {
+                let Some(Value::Function(closed)) = chunk.constants.get(value) else {
+                    unreachable!("decode checks the constant of a closure");
+                };
+                (0..closed.upvalue_count)
+                    .map(|capture| offset + 3 + 2 * capture)
+                    .filter(|at| chunk.code[*at] == 1)
+                    .map(|at| chunk.code[at + 1] as usize)
+                    .collect()
+            }
+            _ => Vec::new(),
+        };
+        if let Some(slot) = uses_slot
+            .into_iter()
+            .chain(captures)
+            .find(|slot| *slot >= height)
+        {
+            return Err(problem(format!("local {slot} does not exist")));
+        }
+        let after = height - pops + pushes;
+
+        let target = |distance: usize, backward: bool| {
+            let target = if backward {
+                next.checked_sub(distance)
+            } else {
+                next.checked_add(distance)
+            };
+            target
+                .filter(|target| starts.get(*target).copied().unwrap_or(false))
+                .ok_or_else(|| problem(format!("{} jumps outside the instructions", op.name())))
+        };
+        let mut successors = Vec::new();
+        match op {
+            OpCode::Return | OpCode::Throw | OpCode::EndFinally => {}
+            OpCode::Jump => successors.push((target(value, false)?, after)),
+            OpCode::Loop => successors.push((target(value, true)?, after)),
+            OpCode::JumpIfFalse => {
+                successors.push((target(value, false)?, after));
+                successors.push((next, after));
+            }
+            // The handler runs with the stack as it is now and the caught
+            // value on top.
+            OpCode::PushHandler | OpCode::PushFinally => {
+                successors.push((target(value, false)?, after + 1));
+                successors.push((next, after));
+            }
+            _ => successors.push((next, after)),
+        }
+
+        for (successor, height) in successors {
+            let index = index_of(successor)
+                .map_err(|_| problem("execution runs past the end of the code".to_string()))?;
+            if heights[index].map_or(true, |known| height < known) {
+                heights[index] = Some(height);
+                pending.push(index);
+            }
+        }
+    }
+    Ok(())
+}
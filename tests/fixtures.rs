@@ -1,20 +1,142 @@
+use std::env;
 use std::fs;
 use std::io::{self, Write};
 use std::path::Path;
-use std::process::{Command, Output};
-use tempfile::NamedTempFile;
+use std::process::{Command, Output, Stdio};
+use tempfile::TempDir;
 
+/// Runs `content` as a program. Set `LOX_BACKEND=vm` to run the whole suite
+/// on the bytecode virtual machine instead of the tree-walking interpreter.
+#[allow(dead_code)]
 pub fn interpret_temp_file_with_content(content: &str) -> io::Result<Output> {
-    let mut temp_file = NamedTempFile::new()?;
-    write!(temp_file, "{}", content)?;
+    let backend = env::var("LOX_BACKEND").unwrap_or_else(|_| "tree".to_string());
+    interpret_temp_file_with_backend(content, &backend)
+}
+
+#[allow(dead_code)]
+pub fn interpret_temp_file_with_backend(content: &str, backend: &str) -> io::Result<Output> {
+    interpret_temp_file_with_args(content, &[&format!("--backend={backend}")])
+}
+
+/// The values of `--backend`, for tests that run a program on each of them.
+#[allow(dead_code)]
+pub const BACKENDS: [&str; 2] = ["tree", "vm"];
+
+/// Runs `content` on every backend, checking that it succeeds and prints
+/// `expected`.
+#[allow(dead_code)]
+pub fn assert_output_on_all_backends(content: &str, expected: &str) -> io::Result<()> {
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_backend(content, backend)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(output.status.success(), "{backend}: {stderr}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            expected,
+            "{backend}"
+        );
+    }
+    Ok(())
+}
+
+/// Runs `content` on every backend, checking that it stops with `exit_code`
+/// and that the first line it reports is `expected`.
+#[allow(dead_code)]
+pub fn assert_error_on_all_backends(
+    content: &str,
+    exit_code: i32,
+    expected: &str,
+) -> io::Result<()> {
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_backend(content, backend)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(
+            output.status.code(),
+            Some(exit_code),
+            "{backend}: {content}"
+        );
+        assert_eq!(
+            stderr.lines().next(),
+            Some(expected),
+            "{backend}: {content}"
+        );
+    }
+    Ok(())
+}
+
+#[allow(dead_code)]
+pub fn run_command_with_content(command: &str, content: &str) -> io::Result<Output> {
+    interpret_temp_file_with_args(content, &[command])
+}
 
-    let filename = temp_file.path();
-    let output = Command::new("bash")
+/// File name the programs written by these helpers are run as, so that the
+/// paths in diagnostics do not depend on the temporary directory.
+#[allow(dead_code)]
+pub const PROGRAM_FILE: &str = "main.lox";
+
+/// Writes `content` to `PROGRAM_FILE` in a fresh temporary directory and
+/// runs it from there, with `args` before the file name.
+fn run_program_file(content: &str, args: &[&str]) -> io::Result<Output> {
+    run_program_with_files(content, &[], args, &[], &[], None)
+}
+
+/// Like `interpret_temp_file_with_args`, but first writes every
+/// `(path, content)` of `files`, such as modules the program imports, into
+/// the program's directory, passes `script_args` to the program after its
+/// file name, sets the environment variables `envs` and writes `input`, if
+/// any, to the program's stdin.
+#[allow(dead_code)]
+pub fn run_program_with_files(
+    content: &str,
+    files: &[(&str, &str)],
+    args: &[&str],
+    script_args: &[&str],
+    envs: &[(&str, &str)],
+    input: Option<&str>,
+) -> io::Result<Output> {
+    let dir = TempDir::new()?;
+    fs::write(dir.path().join(PROGRAM_FILE), content)?;
+    for (path, content) in files {
+        let path = dir.path().join(path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, content)?;
+    }
+
+    let script = Path::new(env!("CARGO_MANIFEST_DIR")).join("your_program.sh");
+    let mut child = Command::new("bash")
+        .arg(script)
+        .args(args)
+        .arg(PROGRAM_FILE)
+        .args(script_args)
+        .current_dir(dir.path())
+        .envs(envs.iter().copied())
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
+
+    let mut stdin = child.stdin.take().unwrap();
+    stdin.write_all(input.unwrap_or_default().as_bytes())?;
+    drop(stdin);
+    child.wait_with_output()
+}
+
+#[allow(dead_code)]
+pub fn run_repl_with_input(input: &str) -> io::Result<Output> {
+    let mut child = Command::new("bash")
         .arg("your_program.sh")
-        .arg(filename)
-        .output()?;
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()?;
 
-    Ok(output)
+    child.stdin.take().unwrap().write_all(input.as_bytes())?;
+    child.wait_with_output()
 }
 
 #[allow(dead_code)]
@@ -22,3 +144,17 @@ pub fn read_temp_file(filename: &Path) -> io::Result<String> {
     let contents = fs::read_to_string(filename)?;
     Ok(contents)
 }
+
+#[allow(dead_code)]
+pub fn run_program_with_args(args: &[&str]) -> io::Result<Output> {
+    Command::new("bash")
+        .arg("your_program.sh")
+        .args(args)
+        .output()
+}
+
+/// Runs `content` as a program with extra command line `args`.
+#[allow(dead_code)]
+pub fn interpret_temp_file_with_args(content: &str, args: &[&str]) -> io::Result<Output> {
+    run_program_file(content, args)
+}
@@ -0,0 +1,155 @@
+use crate::fixtures::interpret_temp_file_with_content;
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_class_init_and_methods_success() -> io::Result<()> {
+    let content = r#"
+    class Counter {
+        init(start) {
+            this.count = start;
+        }
+
+        increment() {
+            this.count = this.count + 1;
+            return this;
+        }
+    }
+
+    var counter = Counter(5);
+    counter.increment().increment();
+    print counter.count;
+    print counter;
+    print Counter;
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+7
+Counter instance
+Counter
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+    assert!(output.stderr.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_bound_method_keeps_this_success() -> io::Result<()> {
+    let content = r#"
+    class Person {
+        greet() {
+            print "Hi, " + this.name;
+        }
+    }
+
+    var person = Person();
+    person.name = "Jane";
+    var greet = person.greet;
+    greet();
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "Hi, Jane");
+
+    Ok(())
+}
+
+#[test]
+fn test_undefined_property_error() -> io::Result<()> {
+    let content = r#"
+    class Empty {}
+    var empty = Empty();
+    print empty.missing;
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+[line 3] Undefined property 'missing'.
+ --> main.lox:3:17
+  |
+3 |     print empty.missing;
+  |                 ^^^^^^^
+    "#;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_inheritance_with_super_success() -> io::Result<()> {
+    let content = r#"
+    class A {
+        init(name) {
+            this.name = name;
+        }
+
+        method() {
+            print "A method " + this.name;
+        }
+    }
+
+    class B < A {
+        init(name) {
+            super.init(name + "!");
+        }
+
+        method() {
+            print "B method";
+            super.method();
+        }
+    }
+
+    class C < B {}
+
+    C("c").method();
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+B method
+A method c!
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_superclass_must_be_a_class_error() -> io::Result<()> {
+    let content = r#"
+    var NotClass = "nope";
+    class Sub < NotClass {}
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+[line 2] Superclass must be a class.
+ --> main.lox:2:17
+  |
+2 |     class Sub < NotClass {}
+  |                 ^^^^^^^^
+    "#;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
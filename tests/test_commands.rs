@@ -0,0 +1,123 @@
+use crate::fixtures::run_command_with_content;
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_tokenize_success() -> io::Result<()> {
+    let content = r#"var total = (12 + 3.50) * "x";"#;
+    let output = run_command_with_content("tokenize", content)?;
+    let expected = r#"
+VAR var null
+IDENTIFIER total null
+EQUAL = null
+LEFT_PAREN ( null
+NUMBER 12 12.0
+PLUS + null
+NUMBER 3.50 3.5
+RIGHT_PAREN ) null
+STAR * null
+STRING "x" x
+SEMICOLON ; null
+EOF  null
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_tokenize_unexpected_character_error() -> io::Result<()> {
+    let content = "1 @ 2";
+    let output = run_command_with_content("tokenize", content)?;
+
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "NUMBER 1 1.0\nNUMBER 2 2.0\nEOF  null"
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        concat!(
+            "[line 1] Error: Unexpected character: @\n",
+            " --> main.lox:1:3\n",
+            "  |\n",
+            "1 | 1 @ 2\n",
+            "  |   ^",
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_success() -> io::Result<()> {
+    let content = r#"(5 - (3 - 1)) + -1 == !true"#;
+    let output = run_command_with_content("parse", content)?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        "(== (+ (group (- 5.0 (group (- 3.0 1.0)))) (- 1.0)) (! true))"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_parse_error() -> io::Result<()> {
+    let content = "(72 +)";
+    let output = run_command_with_content("parse", content)?;
+
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert!(output.stdout.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_success() -> io::Result<()> {
+    let content = r#"("hello" + " world") == "hello world""#;
+    let output = run_command_with_content("evaluate", content)?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout).trim(), "true");
+
+    Ok(())
+}
+
+#[test]
+fn test_evaluate_runtime_error() -> io::Result<()> {
+    let content = r#"-"muffin""#;
+    let output = run_command_with_content("evaluate", content)?;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        concat!(
+            "[line 1] Not a number for MINUS operation.\n",
+            " --> main.lox:1:1\n",
+            "  |\n",
+            "1 | -\"muffin\"\n",
+            "  | ^",
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_run_command_success() -> io::Result<()> {
+    let content = r#"print 6 * 7;"#;
+    let output = run_command_with_content("run", content)?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "42\n");
+
+    Ok(())
+}
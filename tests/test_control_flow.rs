@@ -13,10 +13,18 @@ fn test_if_condition_error_without_paren() -> io::Result<()> {
     let output = interpret_temp_file_with_content(content.trim())?;
     let expected = r#"
 [line 1] Expect '(' after 'if'.
+ --> main.lox:1:1
+  |
+1 | if true == !nil {
+  | ^^
 [line 3] Expect expression.
+ --> main.lox:3:5
+  |
+3 |     }
+  |     ^
     "#;
 
-    assert_ne!(output.status.success(), true);
+    assert!(!output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stderr).trim(),
         expected.trim()
@@ -34,9 +42,13 @@ fn test_if_condition_error_without_brace() -> io::Result<()> {
     let output = interpret_temp_file_with_content(content.trim())?;
     let expected = r#"
 [line 2] Expect { before if body
+ --> main.lox:2:9
+  |
+2 |         print "Not none";
+  |         ^^^^^
     "#;
 
-    assert_ne!(output.status.success(), true);
+    assert!(!output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stderr).trim(),
         expected.trim()
@@ -57,7 +69,7 @@ fn test_if_condition_success() -> io::Result<()> {
 Not none
     "#;
 
-    assert_eq!(output.status.success(), true);
+    assert!(output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stdout).trim(),
         expected.trim()
@@ -82,7 +94,7 @@ yes
 1
     "#;
 
-    assert_eq!(output.status.success(), true);
+    assert!(output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stdout).trim(),
         expected.trim()
@@ -110,7 +122,7 @@ fn test_while_loop_success() -> io::Result<()> {
 5
     "#;
 
-    assert_eq!(output.status.success(), true);
+    assert!(output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stdout).trim(),
         expected.trim()
@@ -133,7 +145,7 @@ fn test_for_loop_success() -> io::Result<()> {
 2
     "#;
 
-    assert_eq!(output.status.success(), true);
+    assert!(output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stdout).trim(),
         expected.trim()
@@ -153,9 +165,13 @@ fn test_for_loop_error() -> io::Result<()> {
     let output = interpret_temp_file_with_content(content.trim())?;
     let expected = r#"
 [line 4] Undefined variable 'i'.
+ --> main.lox:4:11
+  |
+4 |     print i;
+  |           ^
     "#;
 
-    assert_ne!(output.status.success(), true);
+    assert!(!output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stderr).trim(),
         expected.trim()
@@ -163,3 +179,135 @@ fn test_for_loop_error() -> io::Result<()> {
 
     Ok(())
 }
+
+#[test]
+fn test_break_and_continue() -> io::Result<()> {
+    let content = r#"
+    for (var i = 0; i < 10; i = i + 1) {
+        if (i == 1) { continue; }
+        if (i == 4) { break; }
+        print i;
+    }
+    var n = 0;
+    while (true) {
+        n = n + 1;
+        var skipped = n < 3;
+        if (skipped) { continue; }
+        print n;
+        break;
+    }
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+0
+2
+3
+3
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_break_and_continue_in_nested_loops_and_closures() -> io::Result<()> {
+    let content = r#"
+    var getters = [];
+    for (var i = 0; i < 3; i = i + 1) {
+        for (var j = 0; j < 3; j = j + 1) {
+            var captured = i * 10 + j;
+            fun get() { return captured; }
+            if (j == 1) { continue; }
+            getters.push(get);
+            if (j == 2) { break; }
+        }
+        if (i == 1) { break; }
+    }
+    for (var k = 0; k < getters.len(); k = k + 1) {
+        print getters[k]();
+    }
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+0
+2
+10
+12
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_break_and_continue_outside_of_loops() -> io::Result<()> {
+    let content = r#"
+    break;
+    while (true) {
+        fun escape() {
+            continue;
+        }
+        break;
+    }
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors: Vec<&str> = stderr.lines().filter(|l| l.starts_with('[')).collect();
+
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert_eq!(
+        errors,
+        [
+            "[line 1] Can't use 'break' outside of a loop.",
+            "[line 4] Can't use 'continue' outside of a loop.",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_logical_chains_blocks_and_bare_return() -> io::Result<()> {
+    let content = r#"
+    print nil or false or "third";
+    print true and 1 and nil;
+    var a = "outer";
+    {
+        var a = "inner";
+    }
+    print a;
+    fun nothing() {
+        return;
+    }
+    print nothing();
+    if (clock) {
+        print "functions are truthy";
+    }
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+third
+nil
+outer
+nil
+functions are truthy
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
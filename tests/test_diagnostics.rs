@@ -0,0 +1,123 @@
+use crate::fixtures::{interpret_temp_file_with_args, run_command_with_content, BACKENDS};
+use std::io;
+
+mod fixtures;
+
+const PROGRAM: &str = r#"fun scale(value) {
+  return value * "twice";
+}
+print scale(2);
+"#;
+
+#[test]
+fn test_human_format_underlines_the_span() -> io::Result<()> {
+    let content = r#"var greeting = "hello";
+print greeting.length;
+"#;
+    let output = interpret_temp_file_with_args(content, &[])?;
+    let expected = r#"
+[line 2] Undefined property 'length'.
+ --> main.lox:2:16
+  |
+2 | print greeting.length;
+  |                ^^^^^^
+    "#;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_human_format_underlines_unterminated_string() -> io::Result<()> {
+    let content = "print \"never closed;";
+    let output = run_command_with_content("tokenize", content)?;
+    let expected = r#"
+[line 1] Error: Unterminated string.
+ --> main.lox:1:7
+  |
+1 | print "never closed;
+  |       ^^^^^^^^^^^^^^
+    "#;
+
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_short_format() -> io::Result<()> {
+    let output = interpret_temp_file_with_args(PROGRAM, &["--error-format=short"])?;
+    let expected = r#"
+[line 2] Not a number for multiply operation
+[line 2] in scale()
+[line 4] in script
+    "#;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_json_format_on_both_backends() -> io::Result<()> {
+    let expected = concat!(
+        r#"{"severity":"error","message":"Not a number for multiply operation","#,
+        r#""span":{"file":"main.lox","line":2,"column":16,"start":34,"length":1},"#,
+        r#""trace":[{"function":"scale","#,
+        r#""call":{"file":"main.lox","line":4,"column":7,"start":53,"length":8}}]}"#,
+    );
+
+    for backend in BACKENDS {
+        let backend = format!("--backend={backend}");
+        let output = interpret_temp_file_with_args(PROGRAM, &[&backend, "--error-format=json"])?;
+
+        assert_eq!(output.status.code().unwrap(), 70);
+        assert_eq!(String::from_utf8_lossy(&output.stderr).trim(), expected);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_json_format_reports_every_static_error() -> io::Result<()> {
+    let content = r#"{
+  var a = a;
+}
+return "\nope";
+"#;
+    let output = interpret_temp_file_with_args(content, &["--error-format=json"])?;
+    let expected = concat!(
+        r#"{"severity":"error","message":"Can't read local variable in its own initializer.","#,
+        r#""span":{"file":"main.lox","line":2,"column":11,"start":12,"length":1},"trace":[]}"#,
+        "\n",
+        r#"{"severity":"error","message":"Can't return from top-level code.","#,
+        r#""span":{"file":"main.lox","line":4,"column":1,"start":17,"length":6},"trace":[]}"#,
+    );
+
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert_eq!(String::from_utf8_lossy(&output.stderr).trim(), expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_error_format_is_a_usage_error() -> io::Result<()> {
+    let output = interpret_temp_file_with_args("print 1;", &["--error-format=xml"])?;
+
+    assert_eq!(output.status.code().unwrap(), 64);
+
+    Ok(())
+}
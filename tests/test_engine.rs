@@ -0,0 +1,391 @@
+use interpreter_starter_rust::{
+    Backend, Engine, Error, ErrorKind, ExitCode, Limits, NativeModule, Rest, Value,
+};
+use std::{fs, thread};
+use tempfile::TempDir;
+
+const BACKENDS: [Backend; 2] = [Backend::Tree, Backend::Vm];
+
+#[test]
+fn test_eval_returns_expression_values() {
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+
+        assert_eq!(engine.eval("var x = 20;").unwrap(), Value::Nil);
+        assert_eq!(engine.eval("x + 22;").unwrap(), Value::Number(42.0));
+        assert_eq!(
+            engine.eval(r#"[1, "a", {"k": true}];"#).unwrap(),
+            Value::List(vec![
+                1.into(),
+                "a".into(),
+                Value::Map(vec![("k".into(), true.into())]),
+            ]),
+            "{backend:?}"
+        );
+    }
+}
+
+#[test]
+fn test_globals_and_calls() {
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+        engine.set_global("greeting", "hello").unwrap();
+        engine.set_global("scores", vec![3, 4]).unwrap();
+        engine
+            .eval(
+                r#"fun greet(name) { return greeting + ", " + name; }
+fun total() { return scores.reduce(fun (a, b) { return a + b; }, 0); }
+fun adder(n) { return (x) => x + n; }
+var count = scores.len();"#,
+            )
+            .unwrap();
+
+        let greeting = engine.call_function("greet", &["lox".into()]).unwrap();
+        assert_eq!(String::try_from(greeting).unwrap(), "hello, lox");
+        let total = engine.call_function("total", &[]).unwrap();
+        assert_eq!(i64::try_from(total).unwrap(), 7);
+        assert_eq!(engine.get_global("count"), Some(Value::Number(2.0)));
+        assert_eq!(engine.get_global("missing"), None);
+
+        // Functions come back as objects that can be called later.
+        let add_five = engine.call_function("adder", &[5.into()]).unwrap();
+        assert!(matches!(add_five, Value::Object(_)), "{backend:?}");
+        assert_eq!(add_five.to_string(), "<fn anonymous>");
+        let sum = engine.call(&add_five, &[1.into()]).unwrap();
+        assert_eq!(f64::try_from(sum).unwrap(), 6.0);
+    }
+}
+
+#[test]
+fn test_typed_errors() {
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+
+        let Err(Error::Compile(errors)) = engine.eval("var = 1;") else {
+            panic!("{backend:?}: expected a compile error");
+        };
+        assert_eq!(errors[0].message, "Expect variable name.");
+
+        engine.eval("fun fail(x) { return x.field; }").unwrap();
+        let Err(Error::Runtime(error)) = engine.call_function("fail", &[1.into()]) else {
+            panic!("{backend:?}: expected a runtime error");
+        };
+        assert_eq!(error.kind, ErrorKind::Type);
+        assert_eq!(error.message, "Only instances have properties.");
+        assert_eq!(error.span.line, 1);
+
+        let error = engine.call_function("nothing", &[]).unwrap_err();
+        assert!(matches!(&error, Error::UndefinedGlobal(name) if name == "nothing"));
+        assert_eq!(engine.render(&error), "Undefined variable 'nothing'.");
+        let error = engine.call_function("fail", &[]).unwrap_err();
+        assert!(matches!(&error, Error::Runtime(e) if e.kind == ErrorKind::Arity));
+        assert_eq!(error.exit_code(), ExitCode::RuntimeError);
+
+        // Engines keep working after an error.
+        assert_eq!(engine.eval("1 + 1;").unwrap(), Value::Number(2.0));
+    }
+
+    let error = String::try_from(Value::Number(1.0)).unwrap_err();
+    assert_eq!(error.to_string(), "Expected a string, got number.");
+    let map = Value::Map(vec![(Value::List(vec![]), Value::Nil)]);
+    assert!(matches!(
+        Engine::new().set_global("m", map),
+        Err(Error::Conversion(_))
+    ));
+}
+
+#[test]
+fn test_run_file() {
+    let dir = TempDir::new().unwrap();
+    let main = dir.path().join("main.lox");
+    fs::write(dir.path().join("lib.lox"), "export var answer = 42;").unwrap();
+    fs::write(
+        &main,
+        "from \"lib.lox\" import answer;\nvar doubled = answer * 2;",
+    )
+    .unwrap();
+
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+        engine.run_file(&main).unwrap();
+
+        assert_eq!(engine.get_global("doubled"), Some(Value::Number(84.0)));
+
+        let missing = dir.path().join("missing.lox");
+        let error = engine.run_file(&missing).unwrap_err();
+        assert!(matches!(error, Error::Read(..)), "{backend:?}");
+        assert_eq!(error.exit_code(), ExitCode::IoError);
+    }
+}
+
+#[test]
+fn test_register_host_functions() {
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+        engine.register("add", |a: f64, b: f64| a + b);
+        engine.register("join", |sep: String, parts: Rest<String>| {
+            parts.0.join(&sep)
+        });
+        engine.register("repeat", |s: String, n: i64| -> Result<String, String> {
+            let n = usize::try_from(n).map_err(|_| "Count must not be negative.")?;
+            Ok(s.repeat(n))
+        });
+        engine.register("tag", |value: Option<f64>| value.map(|n| vec![n]));
+
+        assert_eq!(engine.eval("add(1, 2);").unwrap(), Value::Number(3.0));
+        assert_eq!(engine.eval("add;").unwrap().to_string(), "<fn add>");
+        assert_eq!(
+            engine
+                .eval(r#"join("-", "a", "b", "c") + join(",");"#)
+                .unwrap(),
+            "a-b-c".into(),
+            "{backend:?}"
+        );
+        assert_eq!(engine.eval(r#"repeat("ab", 2);"#).unwrap(), "abab".into());
+        assert_eq!(engine.eval("tag(nil);").unwrap(), Value::Nil);
+        assert_eq!(engine.eval("tag(4);").unwrap(), vec![4.0].into());
+
+        let cases = [
+            (
+                "add(1);",
+                ErrorKind::Arity,
+                "Expected 2 arguments, but got 1.",
+            ),
+            (
+                "join();",
+                ErrorKind::Arity,
+                "Expected at least 1 argument, but got 0.",
+            ),
+            (
+                r#"add(1, "2");"#,
+                ErrorKind::Type,
+                "Expected a number for argument 2 of 'add', got string.",
+            ),
+            (
+                r#"join("", "a", 1);"#,
+                ErrorKind::Type,
+                "Expected a string for argument 3 of 'join', got number.",
+            ),
+            (
+                r#"repeat("a", 1.5);"#,
+                ErrorKind::Type,
+                "Expected an integer for argument 2 of 'repeat', got number.",
+            ),
+            (
+                r#"repeat("a", -1);"#,
+                ErrorKind::Error,
+                "Count must not be negative.",
+            ),
+        ];
+        for (source, kind, message) in cases {
+            let Err(Error::Runtime(error)) = engine.eval(&format!("\n{source}")) else {
+                panic!("{backend:?}: expected a runtime error from {source}");
+            };
+            assert_eq!(error.kind, kind, "{backend:?}: {source}");
+            assert_eq!(error.message, message, "{backend:?}: {source}");
+            assert_eq!(error.span.line, 2, "{backend:?}: {source}");
+        }
+
+        // Scripts catch host function errors like their own.
+        let caught = engine.eval(r#"try { add(nil, 1); } catch (e) { print e; }"#);
+        assert!(caught.is_ok(), "{backend:?}");
+    }
+}
+
+#[test]
+fn test_native_modules() {
+    let dir = TempDir::new().unwrap();
+    let main = dir.path().join("main.lox");
+    fs::write(
+        dir.path().join("stats.lox"),
+        "export fun mean(xs) { return total(xs) / xs.len(); }",
+    )
+    .unwrap();
+    fs::write(
+        &main,
+        r#"import "geometry" as geo;
+from "geometry" import square;
+from "stats.lox" import mean;
+var area = geo.square(3) + square(1);
+var average = mean([1, 2, 3]);
+"#,
+    )
+    .unwrap();
+
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+        let mut geometry = NativeModule::new("geometry");
+        geometry
+            .register("square", |x: f64| x * x)
+            .register("origin", || vec![0, 0]);
+        engine.register_module(geometry);
+        // Registered globals are visible inside module files too.
+        engine.register("total", |xs: Vec<f64>| xs.iter().sum::<f64>());
+        engine.run_file(&main).unwrap();
+
+        assert_eq!(engine.get_global("area"), Some(Value::Number(10.0)));
+        assert_eq!(engine.get_global("average"), Some(Value::Number(2.0)));
+        let module = engine.get_global("geo").unwrap();
+        assert_eq!(module.to_string(), "<module geometry>", "{backend:?}");
+        assert_eq!(
+            engine.get_global("square").unwrap().to_string(),
+            "<fn square>"
+        );
+
+        let Err(Error::Compile(errors)) = engine.eval(r#"from "geometry" import cube;"#) else {
+            panic!("{backend:?}: expected a compile error");
+        };
+        assert_eq!(
+            errors[0].message,
+            "Module \"geometry\" does not export 'cube'."
+        );
+        let Err(Error::Compile(errors)) = engine.eval(r#"import "algebra" as a;"#) else {
+            panic!("{backend:?}: expected a compile error");
+        };
+        assert_eq!(errors[0].message, "Cannot find module \"algebra\".");
+    }
+}
+
+#[test]
+fn test_exit_ends_the_program() {
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+        let source = r#"var reached = false;
+try { exit(3); } catch (e) { reached = true; } finally { reached = true; }
+reached = true;
+"#;
+        let error = engine.eval(source).unwrap_err();
+        assert!(matches!(error, Error::Exit(3)), "{backend:?}: {error:?}");
+        assert_eq!(error.status(), 3);
+        assert_eq!(engine.get_global("reached"), Some(Value::Bool(false)));
+
+        let Err(Error::Runtime(error)) = engine.eval("exit(256);") else {
+            panic!("{backend:?}: expected a runtime error");
+        };
+        assert_eq!(error.message, "Exit status must be between 0 and 255.");
+        assert_eq!(engine.eval("1 + 1;").unwrap(), Value::Number(2.0));
+    }
+}
+
+#[test]
+fn test_limits() {
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+        engine.set_limits(Limits {
+            max_steps: Some(1000),
+            max_call_depth: 32,
+            ..Limits::default()
+        });
+        engine
+            .eval("fun count(n) { if (n == 0) { return 0; } return 1 + count(n - 1); }")
+            .unwrap();
+
+        // Every run gets the whole budget.
+        for _ in 0..3 {
+            let result = engine.call_function("count", &[20.into()]);
+            assert_eq!(result.unwrap(), Value::Number(20.0), "{backend:?}");
+        }
+
+        let Err(Error::Runtime(error)) = engine.call_function("count", &[40.into()]) else {
+            panic!("{backend:?}: expected a runtime error");
+        };
+        assert_eq!(error.kind, ErrorKind::StackOverflow);
+        assert_eq!(error.message, "Stack overflow.");
+
+        let error = engine.eval("while (true) {}").unwrap_err();
+        assert!(matches!(&error, Error::Runtime(e) if e.kind == ErrorKind::StepLimit));
+        assert_eq!(error.exit_code(), ExitCode::StepLimit);
+    }
+}
+
+#[test]
+fn test_deep_recursion_is_a_stack_overflow_on_an_ordinary_thread() {
+    let programs = [
+        "fun f(n) { return f(n + 1); } f(0);",
+        "fun g(n) { return [n].map(g); } g(0);",
+    ];
+
+    // The stack Rust gives the threads it spawns by default.
+    let thread = thread::Builder::new().stack_size(2 * 1024 * 1024);
+    let run = thread.spawn(move || {
+        for backend in BACKENDS {
+            let mut engine = Engine::with_backend(backend);
+            engine.set_limits(Limits {
+                max_call_depth: 100_000,
+                ..Limits::default()
+            });
+            for program in programs {
+                let Err(Error::Runtime(error)) = engine.eval(program) else {
+                    panic!("{backend:?}: expected a runtime error from {program}");
+                };
+                assert_eq!(error.kind, ErrorKind::StackOverflow, "{backend:?}");
+            }
+
+            engine
+                .eval("var kind; try { f(0); } catch (e) { kind = e.kind; }")
+                .unwrap();
+            let kind = engine.get_global("kind").unwrap();
+            assert_eq!(kind, Value::String("StackOverflowError".to_string()));
+        }
+    });
+
+    run.unwrap().join().unwrap();
+}
+
+#[test]
+fn test_comparing_deeply_nested_lists_on_an_ordinary_thread() {
+    let program = r#"fun nest(innermost) {
+  var list = [innermost];
+  var map = {"x": innermost};
+  for (var i = 0; i < 20000; i = i + 1) {
+    list = [list];
+    map = {"x": map};
+  }
+  return [list, map];
+}
+"#;
+
+    let thread = thread::Builder::new().stack_size(2 * 1024 * 1024);
+    let run = thread.spawn(move || {
+        for backend in BACKENDS {
+            let mut engine = Engine::with_backend(backend);
+            engine.eval(program).unwrap();
+            engine
+                .eval("var a = nest(1); var b = nest(1); var c = nest(2);")
+                .unwrap();
+
+            assert_eq!(
+                engine.eval("a == b;").unwrap(),
+                Value::Bool(true),
+                "{backend:?}"
+            );
+            assert_eq!(
+                engine.eval("a == c;").unwrap(),
+                Value::Bool(false),
+                "{backend:?}"
+            );
+        }
+    });
+
+    run.unwrap().join().unwrap();
+}
+
+#[test]
+fn test_errors_show_the_source_of_their_eval() {
+    for backend in BACKENDS {
+        let mut engine = Engine::with_backend(backend);
+        engine.eval("fun fail() {\n  return nil + 1;\n}").unwrap();
+        engine.eval("var a = 1;\nvar b = 2;").unwrap();
+
+        let error = engine.call_function("fail", &[]).unwrap_err();
+        let rendered = engine.render(&error);
+        assert!(
+            rendered.contains(" --> <eval-1>:2:14\n"),
+            "{backend:?}: {rendered}"
+        );
+        assert!(
+            rendered.contains("2 |   return nil + 1;\n"),
+            "{backend:?}: {rendered}"
+        );
+    }
+}
@@ -0,0 +1,171 @@
+use crate::fixtures::{
+    assert_error_on_all_backends, assert_output_on_all_backends, interpret_temp_file_with_args,
+    interpret_temp_file_with_backend, BACKENDS,
+};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_runtime_errors_are_catchable() -> io::Result<()> {
+    let content = r#"try {
+  print 1 / nil;
+} catch (e) {
+  print e;
+  print e.kind;
+  print e.message;
+  print e.line;
+}
+try { [1, 2][5]; } catch (e) { print e; }
+try { {"a": 1}["b"]; } catch (e) { print e; }
+try { missing; } catch (e) { print e.kind; }
+fun first(x) { return x.first; }
+try { [1].map(first); } catch (e) { print e; }
+var caught;
+try { nil.x; } catch (e) { caught = e; }
+try { throw caught; } catch (e) { print e == caught; }
+"#;
+    let expected = concat!(
+        "TypeError: Not a number for division operation\n",
+        "TypeError\nNot a number for division operation\n2\n",
+        "IndexError: List index out of range.\n",
+        "KeyError: Undefined key \"b\".\n",
+        "NameError\n",
+        "TypeError: Only instances have properties.\n",
+        "true\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_throw_any_value() -> io::Result<()> {
+    let content = r#"fun check(n) {
+  if (n < 0) { throw "negative"; }
+  return n;
+}
+try { check(-1); } catch (e) { print e; }
+try { throw [1, 2]; } catch (e) { print e.len(); }
+try {
+  try { throw 1; } catch (e) { throw e + 1; }
+} catch (e) {
+  print e;
+}
+"#;
+
+    assert_output_on_all_backends(content, "negative\n2\n2\n")
+}
+
+#[test]
+fn test_finally_runs_on_every_exit() -> io::Result<()> {
+    let content = r#"fun early() {
+  try { return "returned"; } finally { print "after return"; }
+}
+print early();
+for (var i = 0; i < 3; i = i + 1) {
+  var x = i * 10;
+  try {
+    if (i == 0) { continue; }
+    if (i == 2) { break; }
+    print x;
+  } finally {
+    print i;
+  }
+}
+try {
+  try { throw "inner"; } finally { print "unwinding"; }
+} catch (e) {
+  print e;
+}
+fun replaced() {
+  try { throw "lost"; } finally { return "finally wins"; }
+}
+print replaced();
+"#;
+    let expected = concat!(
+        "after return\nreturned\n",
+        "0\n10\n1\n2\n",
+        "unwinding\ninner\n",
+        "finally wins\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_uncaught_errors() -> io::Result<()> {
+    let content = r#"fun fail() {
+  try { throw "bye"; } finally { print "cleanup"; }
+}
+fail();
+"#;
+
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_backend(content, backend)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let lines: Vec<&str> = stderr.lines().filter(|l| l.starts_with('[')).collect();
+
+        assert_eq!(output.status.code().unwrap(), 70, "{backend}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "cleanup\n");
+        assert_eq!(
+            lines,
+            [
+                "[line 2] Uncaught exception: bye",
+                "[line 2] in fail()",
+                "[line 4] in script",
+            ],
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_try_syntax_errors() -> io::Result<()> {
+    let cases = [
+        (
+            "try { }",
+            "[line 1] Expect 'catch' or 'finally' after try block.",
+        ),
+        ("try { } catch { }", "[line 1] Expect '(' after 'catch'."),
+        ("throw;", "[line 1] Expect expression."),
+    ];
+
+    for (content, expected) in cases {
+        assert_error_on_all_backends(content, 65, expected)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_syntax_errors_around_try_are_reported_once() -> io::Result<()> {
+    let cases = [
+        (
+            "try {\n  var x = ;\n  print x;\n} catch (e) {\n  print e;\n}\n",
+            "[line 2] Expect expression.\n",
+        ),
+        (
+            "try {\n  print 1 +\n  throw \"x\";\n} finally {\n  print 2;\n}\n",
+            "[line 3] Expect expression.\n",
+        ),
+        (
+            "print 1 +\ntry { print 2; } catch (e) { print e; }\n",
+            "[line 2] Expect expression.\n",
+        ),
+    ];
+
+    for (content, expected) in cases {
+        let output = interpret_temp_file_with_args(content, &["--error-format=short"])?;
+
+        assert_eq!(output.status.code(), Some(65), "{content}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr),
+            expected,
+            "{content}"
+        );
+    }
+
+    Ok(())
+}
@@ -12,9 +12,9 @@ fn test_func_without_brace_error() -> io::Result<()> {
         "#;
 
     let output = interpret_temp_file_with_content(content)?;
-    assert_eq!(output.status.success(), false);
-    assert_eq!(output.stdout.is_empty(), true);
-    assert_eq!(output.stderr.is_empty(), false);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(!output.stderr.is_empty());
 
     Ok(())
 }
@@ -27,9 +27,9 @@ fn test_func_without_parent_error() -> io::Result<()> {
         "#;
 
     let output = interpret_temp_file_with_content(content)?;
-    assert_eq!(output.status.success(), false);
-    assert_eq!(output.stdout.is_empty(), true);
-    assert_eq!(output.stderr.is_empty(), false);
+    assert!(!output.status.success());
+    assert!(output.stdout.is_empty());
+    assert!(!output.stderr.is_empty());
 
     Ok(())
 }
@@ -46,18 +46,12 @@ fn test_func_return_none_successful() -> io::Result<()> {
 
     let output = interpret_temp_file_with_content(content.trim())?;
 
-    assert_eq!(output.status.success(), true);
-    assert_eq!(
-        String::from_utf8_lossy(&output.stdout).trim().contains("3"),
-        true
-    );
-    assert_eq!(
-        String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .contains("nil"),
-        true
-    );
-    assert_eq!(output.stderr.is_empty(), true);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).trim().contains("3"));
+    assert!(String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .contains("nil"));
+    assert!(output.stderr.is_empty());
 
     Ok(())
 }
@@ -74,18 +68,94 @@ fn test_func_return_result_successful() -> io::Result<()> {
 
     let output = interpret_temp_file_with_content(content.trim())?;
 
-    assert_eq!(output.status.success(), true);
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).trim().contains("3"));
+    assert!(!String::from_utf8_lossy(&output.stdout)
+        .trim()
+        .contains("nil"));
+    assert!(output.stderr.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_anonymous_functions() -> io::Result<()> {
+    let content = r#"
+        var add = fun (a, b) { return a + b; };
+        print add(1, 2);
+        print add;
+        print [1, 2, 3].map((x) => x * 10);
+        fun counter() {
+            var n = 0;
+            return () => n = n + 1;
+        }
+        var next = counter();
+        next();
+        print next();
+        print ((x) => (y) => x + y)(1)(2);
+        print (1 + 2);
+        "#;
+
+    let output = interpret_temp_file_with_content(content.trim())?;
+
+    assert!(output.status.success());
     assert_eq!(
-        String::from_utf8_lossy(&output.stdout).trim().contains("3"),
-        true
+        String::from_utf8_lossy(&output.stdout),
+        "3\n<fn anonymous>\n[10, 20, 30]\n2\n3\n3\n"
     );
+
+    Ok(())
+}
+
+#[test]
+fn test_arrow_functions_with_a_block_body_are_rejected() -> io::Result<()> {
+    let block =
+        "[line 1] Arrow function bodies are expressions; use fun (...) { ... } for a block.";
+    let cases = [
+        ("var f = (a) => { return a; };", Some(block)),
+        ("var f = () => {};", Some(block)),
+        ("print ((a) => {\"a\": a})(1);", None),
+    ];
+
+    for (content, expected) in cases {
+        let output = interpret_temp_file_with_content(content)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        match expected {
+            Some(expected) => {
+                assert_eq!(output.status.code(), Some(65), "{content}");
+                assert_eq!(stderr.lines().next(), Some(expected), "{content}");
+            }
+            None => {
+                assert!(output.status.success(), "{content}: {stderr}");
+                assert_eq!(String::from_utf8_lossy(&output.stdout), "{\"a\": 1}\n");
+            }
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_anonymous_function_in_stack_trace() -> io::Result<()> {
+    let content = r#"
+        var fail = fun () { return nil.x; };
+        fail();
+        "#;
+
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let trace: Vec<&str> = stderr.lines().filter(|l| l.starts_with('[')).collect();
+
+    assert_eq!(output.status.code().unwrap(), 70);
     assert_eq!(
-        String::from_utf8_lossy(&output.stdout)
-            .trim()
-            .contains("nil"),
-        false
+        trace,
+        [
+            "[line 1] Only instances have properties.",
+            "[line 1] in anonymous()",
+            "[line 2] in script",
+        ]
     );
-    assert_eq!(output.stderr.is_empty(), true);
 
     Ok(())
 }
@@ -0,0 +1,154 @@
+use crate::fixtures::{interpret_temp_file_with_args, BACKENDS};
+use std::io;
+
+mod fixtures;
+
+/// Every iteration leaves a recursive closure and a self-referencing
+/// instance behind, both only reachable through their own cycles.
+const CYCLES: &str = r#"
+fun make() {
+  fun count(n) {
+    if (n < 1) { return 0; }
+    return count(n - 1) + 1;
+  }
+  return count;
+}
+
+class Node {
+  init() {
+    this.self = this;
+  }
+}
+
+var total = 0;
+for (var i = 0; i < 3000; i = i + 1) {
+  var count = make();
+  total = total + count(3);
+  Node();
+}
+print total;
+print gc_stats();
+"#;
+
+fn stat(stats: &str, name: &str) -> usize {
+    stats
+        .split(", ")
+        .find_map(|entry| entry.strip_prefix(&format!("{name}: ")))
+        .and_then(|value| value.parse().ok())
+        .unwrap_or_else(|| panic!("no {name} in {stats}"))
+}
+
+#[test]
+fn test_cyclic_closures_are_collected() -> io::Result<()> {
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_args(CYCLES, &[&format!("--backend={backend}")])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+
+        assert!(output.status.success());
+        assert_eq!(lines.next(), Some("9000"));
+
+        let stats = lines.next().unwrap();
+        assert!(stat(stats, "collections") > 0);
+        assert!(stat(stats, "freed") >= 6000);
+        assert!(stat(stats, "live") < 1024);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_gc_stress_keeps_reachable_objects() -> io::Result<()> {
+    let content = r#"
+fun counter() {
+  var i = 0;
+  fun next() {
+    i = i + 1;
+    return i;
+  }
+  return next;
+}
+
+class Box {
+  init(value) {
+    this.value = value;
+    this.self = this;
+  }
+  get() {
+    return this.self.value;
+  }
+}
+
+var next = counter();
+var box = Box(next);
+for (var j = 0; j < 5; j = j + 1) {
+  Box(j).get();
+  next();
+}
+print box.get()();
+print gc_stats();
+"#;
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_args(
+            content,
+            &[&format!("--backend={backend}"), "--gc-stress"],
+        )?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+
+        assert!(output.status.success());
+        assert_eq!(lines.next(), Some("6"));
+        assert!(stat(lines.next().unwrap(), "freed") >= 5);
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_gc_log() -> io::Result<()> {
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_args(
+            CYCLES,
+            &[&format!("--backend={backend}"), "--gc-stress", "--gc-log"],
+        )?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert!(output.status.success());
+        assert!(stderr.starts_with("[gc] collection 1: "));
+        assert!(stderr.lines().all(|line| line.starts_with("[gc] ")));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_cyclic_lists_and_maps_are_collected() -> io::Result<()> {
+    let content = r#"
+fun len_of(xs) { return xs.len(); }
+var kept = [1];
+kept.push(kept);
+for (var i = 0; i < 2000; i = i + 1) {
+  var xs = [i];
+  xs.push(xs);
+  [xs].map(len_of);
+  var m = {"i": i};
+  m["self"] = m;
+}
+print kept;
+print gc_stats();
+"#;
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_args(content, &[&format!("--backend={backend}")])?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let mut lines = stdout.lines();
+
+        assert!(output.status.success());
+        assert_eq!(lines.next(), Some("[1, [...]]"));
+
+        let stats = lines.next().unwrap();
+        assert!(stat(stats, "freed") >= 1000);
+        assert!(stat(stats, "live") < 1024);
+    }
+
+    Ok(())
+}
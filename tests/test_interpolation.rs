@@ -0,0 +1,71 @@
+use crate::fixtures::{
+    assert_error_on_all_backends, assert_output_on_all_backends, run_command_with_content,
+};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_interpolated_strings() -> io::Result<()> {
+    let content = r#"var a = 2;
+var b = 3;
+print "total: ${a + b}";
+print "${a}${b}";
+print "nested: ${"inner ${a * 10}"}!";
+print "map: ${{"k": [1, "s"]}["k"]} block: ${(fun () { return "}"; })()}";
+print "${nil} ${true} ${1.5} ${[1, "two"]}";
+print "escaped \${a} and $ alone";
+var greet = (name) => "hi ${name.upper()}";
+print greet("lox");
+"#;
+    let expected = concat!(
+        "total: 5\n23\nnested: inner 20!\n",
+        "map: [1, \"s\"] block: }\n",
+        "nil true 1.5 [1, \"two\"]\n",
+        "escaped ${a} and $ alone\nhi LOX\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_interpolation_tokens() -> io::Result<()> {
+    let output = run_command_with_content("tokenize", r#""a ${b} c""#)?;
+    let expected = "INTERPOLATION \"a ${ a \nIDENTIFIER b null\nSTRING } c\"  c\nEOF  null\n";
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    let output = run_command_with_content("parse", r#""x ${1 + 2}""#)?;
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "(interpolate x  (+ 1.0 2.0))\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_interpolation_errors() -> io::Result<()> {
+    let cases = [
+        ("print \"a ${}\";", "[line 1] Expect expression."),
+        (
+            "print \"a ${1 2}\";",
+            "[line 1] Expect '}' after interpolated expression.",
+        ),
+        (
+            "print \"a ${1 + 2\";",
+            "[line 1] Error: Unterminated string.",
+        ),
+        (
+            "print \"a ${x",
+            "[line 1] Error: Unterminated string interpolation.",
+        ),
+    ];
+
+    for (content, expected) in cases {
+        assert_error_on_all_backends(content, 65, expected)?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use crate::fixtures::{run_program_with_files, BACKENDS};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_file_natives() -> io::Result<()> {
+    let content = r#"write_file("out.txt", "one\ntwo\n");
+append_file("out.txt", "three\n");
+append_file("new.txt", "fresh");
+print read_file("out.txt");
+print read_lines("out.txt");
+print read_file("new.txt");
+print [exists("out.txt"), exists("data"), exists("missing.txt")];
+print list_dir("data");
+"#;
+    let files = [("data/b.txt", ""), ("data/a.txt", "")];
+    let expected = concat!(
+        "one\ntwo\nthree\n\n",
+        "[\"one\", \"two\", \"three\"]\n",
+        "fresh\n",
+        "[true, true, false]\n",
+        "[\"a.txt\", \"b.txt\"]\n",
+    );
+
+    for backend in BACKENDS {
+        let args = [format!("--backend={backend}")];
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+
+        assert!(output.status.success(), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            expected,
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_io_failures_name_the_path() -> io::Result<()> {
+    let content = r#"try { read_file("missing.txt"); } catch (e) { print e; }
+try { list_dir("missing"); } catch (e) { print e; }
+write_file("missing/out.txt", "text");
+"#;
+
+    for backend in BACKENDS {
+        let args = [format!("--backend={backend}")];
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = run_program_with_files(content, &[], &args, &[], &[], None)?;
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(70), "{backend}");
+        assert!(
+            stdout.starts_with("Error: Cannot read \"missing.txt\": "),
+            "{backend}: {stdout}"
+        );
+        assert!(
+            stdout.contains("Error: Cannot list \"missing\": "),
+            "{backend}: {stdout}"
+        );
+        assert!(
+            stderr.contains("[line 3] Cannot write \"missing/out.txt\": "),
+            "{backend}: {stderr}"
+        );
+        assert!(stderr.contains("(os error 2)."), "{backend}: {stderr}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_no_fs_leaves_file_natives_undefined() -> io::Result<()> {
+    let content = r#"print clock() > 0;
+read_file("data.txt");
+"#;
+    let files = [("data.txt", "secret")];
+
+    for backend in BACKENDS {
+        let args = [format!("--backend={backend}")];
+        let args = [args[0].as_str(), "--no-fs"];
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+
+        assert_eq!(output.status.code(), Some(70), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "true\n",
+            "{backend}"
+        );
+        assert!(
+            String::from_utf8_lossy(&output.stderr).contains("Undefined variable 'read_file'."),
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_no_fs_only_imports_native_modules() -> io::Result<()> {
+    let files = [("lib.lox", "export var secret = \"hidden\";")];
+
+    for backend in BACKENDS {
+        let args = [format!("--backend={backend}")];
+        let args = [args[0].as_str(), "--no-fs"];
+
+        let content = "import \"math\" as m;\nprint m.floor(1.5);\n";
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+        assert_eq!(output.status.code(), Some(0), "{backend}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "1\n", "{backend}");
+
+        let content = "import \"lib.lox\" as lib;\nprint lib.secret;\n";
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        assert_eq!(output.status.code(), Some(65), "{backend}");
+        assert!(output.stdout.is_empty(), "{backend}");
+        assert!(
+            stderr.contains("Cannot import \"lib.lox\" without filesystem access."),
+            "{backend}: {stderr}"
+        );
+        assert!(!stderr.contains("hidden"), "{backend}");
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_input_reads_stdin_lines() -> io::Result<()> {
+    let content = r#"var name = input("Name? ");
+print "Hello, ${name}!";
+print input();
+print input();
+"#;
+
+    for backend in BACKENDS {
+        let args = [format!("--backend={backend}")];
+        let args = [args[0].as_str(), "--no-fs"];
+        let output = run_program_with_files(content, &[], &args, &[], &[], Some("Ada\r\nlast"))?;
+
+        assert!(output.status.success(), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "Name? Hello, Ada!\nlast\nnil\n",
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
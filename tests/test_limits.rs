@@ -0,0 +1,105 @@
+use crate::fixtures::{interpret_temp_file_with_args, BACKENDS};
+use std::io;
+
+mod fixtures;
+
+/// Runs `content` on every backend with `args`, checking that it stops with
+/// `exit_code` and reports `message`.
+fn assert_stops(content: &str, args: &[&str], exit_code: i32, message: &str) -> io::Result<()> {
+    for backend in BACKENDS {
+        let backend = format!("--backend={backend}");
+        let mut all_args = vec![backend.as_str()];
+        all_args.extend_from_slice(args);
+        let output = interpret_temp_file_with_args(content, &all_args)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(exit_code), "{backend}: {stderr}");
+        assert!(stderr.contains(message), "{backend}: {stderr}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_unbounded_recursion_is_a_stack_overflow() -> io::Result<()> {
+    let content = "fun f(n) { return f(n + 1); }\nf(0);\n";
+    assert_stops(content, &[], 80, "[line 1] Stack overflow.")?;
+
+    let content = r#"fun depth(n) { if (n == 0) { return 0; } return 1 + depth(n - 1); }
+print depth(50);
+try { depth(100); } catch (e) { print e.kind; }
+depth(100);
+"#;
+    for backend in BACKENDS {
+        let args = [format!("--backend={backend}"), "--max-depth=64".to_string()];
+        let args: Vec<&str> = args.iter().map(String::as_str).collect();
+        let output = interpret_temp_file_with_args(content, &args)?;
+
+        assert_eq!(output.status.code(), Some(80), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "50\nStackOverflowError\n",
+            "{backend}"
+        );
+    }
+    Ok(())
+}
+
+#[test]
+fn test_stack_overflow_traces_show_only_their_ends() -> io::Result<()> {
+    let content = "fun f(n) { return f(n + 1); }\nf(0);\n";
+    let mut expected = vec!["[line 1] in f()"; 10];
+    expected.push("... 4077 more frames");
+    expected.extend(["[line 1] in f()"; 9]);
+    expected.push("[line 2] in script");
+
+    for backend in BACKENDS {
+        let args = [format!("--backend={backend}")];
+        let output = interpret_temp_file_with_args(content, &[args[0].as_str()])?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let trace: Vec<&str> = stderr.lines().skip_while(|l| !l.contains(" in ")).collect();
+
+        assert_eq!(output.status.code(), Some(80), "{backend}");
+        assert_eq!(trace, expected, "{backend}");
+    }
+    Ok(())
+}
+
+#[test]
+fn test_step_limit() -> io::Result<()> {
+    let content = r#"var i = 0;
+while (true) {
+  try { i = i + 1; } catch (e) { print "caught"; } finally { print "finally"; }
+}
+"#;
+    assert_stops(content, &["--max-steps=10000"], 81, "Step limit exceeded.")?;
+    assert_stops(
+        "while (true) {}\n",
+        &["--max-steps=100"],
+        81,
+        "[line 1] Step limit",
+    )?;
+
+    let output = interpret_temp_file_with_args("print 1 + 2;\n", &["--max-steps=10"])?;
+    assert!(output.status.success());
+    Ok(())
+}
+
+#[test]
+fn test_timeout() -> io::Result<()> {
+    let content = "fun spin() { for (;;) {} }\nspin();\n";
+    assert_stops(content, &["--timeout=0.2"], 82, "Time limit exceeded.")
+}
+
+#[test]
+fn test_memory_limit() -> io::Result<()> {
+    let content = "var s = \"x\";\nwhile (true) { s = s + s; }\n";
+    assert_stops(
+        content,
+        &["--max-memory=16M"],
+        83,
+        "[line 2] Memory limit exceeded.",
+    )?;
+
+    let content = "var items = [];\nfor (var i = 0; i < 100000000; i = i + 1) { items.push(i); }\n";
+    assert_stops(content, &["--max-memory=1M"], 83, "Memory limit exceeded.")
+}
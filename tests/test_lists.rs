@@ -0,0 +1,151 @@
+use crate::fixtures::{
+    assert_error_on_all_backends, assert_output_on_all_backends, interpret_temp_file_with_backend,
+    BACKENDS,
+};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_literals_indexing_and_slicing() -> io::Result<()> {
+    let content = r#"var xs = [3, "two", [1],];
+print xs;
+print xs[0] + xs[-3];
+xs[1] = nil;
+print xs[-2];
+var nums = [1, 2, 3, 4, 5];
+print nums[1:3];
+print nums[-2:];
+print nums[:-10];
+print nums[:];
+"#;
+    let expected = "[3, \"two\", [1]]\n6\nnil\n[2, 3]\n[4, 5]\n[]\n[1, 2, 3, 4, 5]\n";
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_methods() -> io::Result<()> {
+    let content = r#"var xs = [5, 3];
+xs.push(9);
+xs.insert(0, 1);
+print xs;
+print xs.len();
+print xs.pop();
+print xs.remove(-1);
+print xs;
+fun double(x) { return x * 2; }
+fun big(x) { return x > 2; }
+fun add(total, x) { return total + x; }
+print xs.map(double);
+print xs.filter(big);
+print xs.reduce(add, 100);
+var words = ["pear", "fig", "apple"];
+words.sort();
+print words;
+var push = xs.push;
+push(0);
+xs.sort();
+print xs;
+"#;
+    let expected = concat!(
+        "[1, 5, 3, 9]\n4\n9\n3\n[1, 5]\n",
+        "[2, 10]\n[5]\n106\n[\"apple\", \"fig\", \"pear\"]\n[0, 1, 5]\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_lists_are_shared_and_compared_by_content() -> io::Result<()> {
+    let content = r#"var a = [1, [2]];
+var b = a;
+b.push("x");
+print a;
+print a == [1, [2], "x"];
+print a == [1, [2]];
+a.push(a);
+print a;
+"#;
+    let expected = "[1, [2], \"x\"]\ntrue\nfalse\n[1, [2], \"x\", [...]]\n";
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_sort_puts_nan_last() -> io::Result<()> {
+    let content = "var xs = [3, 0/0, 1, -(0/0), 2]; xs.sort(); print xs;";
+
+    assert_output_on_all_backends(content, "[1, 2, 3, NaN, NaN]\n")
+}
+
+#[test]
+fn test_comparing_lists_that_contain_each_other() -> io::Result<()> {
+    let content = r#"var a = [1];
+var b = [1];
+a.push(b);
+b.push(a);
+print a == b;
+var c = [1];
+c.push(c);
+print a == c;
+c.push(2);
+print a == c;
+"#;
+    let expected = "true\ntrue\nfalse\n";
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_list_errors() -> io::Result<()> {
+    let cases = [
+        ("print [1, 2][2];", "[line 1] List index out of range."),
+        (
+            "print [1, 2][0.5];",
+            "[line 1] List index must be an integer.",
+        ),
+        (
+            "var n = 12; print n[0];",
+            "[line 1] Only lists, maps and strings can be indexed.",
+        ),
+        (
+            "print [1][\"a\":];",
+            "[line 1] List index must be an integer.",
+        ),
+        ("[].pop();", "[line 1] Can't pop from an empty list."),
+        (
+            "[1, \"a\"].sort();",
+            "[line 1] Can only sort lists of numbers or of strings.",
+        ),
+        ("[].size();", "[line 1] Undefined property 'size'."),
+        ("[].push();", "[line 1] Expected 1 argument, but got 0."),
+    ];
+
+    for (content, expected) in cases {
+        assert_error_on_all_backends(content, 70, expected)?;
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_callback_errors_report_the_callback() -> io::Result<()> {
+    let content = r#"fun boom(x) {
+  return -"nope";
+}
+[1].map(boom);
+"#;
+    let expected = "[line 2] Not a number for MINUS operation.";
+
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_backend(content, backend)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code().unwrap(), 70);
+        assert_eq!(stderr.lines().next(), Some(expected));
+        assert!(stderr.contains("in boom()"), "{backend}: {stderr}");
+    }
+
+    Ok(())
+}
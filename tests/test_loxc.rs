@@ -0,0 +1,216 @@
+use crate::fixtures::{run_command_with_content, run_program_with_args};
+use std::fs;
+use std::io;
+use tempfile::TempDir;
+
+mod fixtures;
+
+const PROGRAM: &str = r#"
+fun counter(step) {
+  var count = 0;
+  fun next() {
+    count = count + step;
+    return count;
+  }
+  return next;
+}
+
+var next = counter(3);
+next();
+print next();
+print "done";
+"#;
+
+fn compile_program(dir: &TempDir) -> io::Result<String> {
+    let source = dir.path().join("program.lox");
+    fs::write(&source, PROGRAM)?;
+
+    let output = run_program_with_args(&["compile", source.to_str().unwrap()])?;
+    assert!(output.status.success());
+
+    let compiled = dir.path().join("program.loxc");
+    assert!(compiled.exists());
+    Ok(compiled.to_str().unwrap().to_string())
+}
+
+#[test]
+fn test_run_compiled_file() -> io::Result<()> {
+    let dir = TempDir::new()?;
+    let compiled = compile_program(&dir)?;
+
+    let output = run_program_with_args(&["run", &compiled])?;
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\ndone\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_compile_to_output_path() -> io::Result<()> {
+    let dir = TempDir::new()?;
+    let source = dir.path().join("program.lox");
+    let target = dir.path().join("out.bin");
+    fs::write(&source, PROGRAM)?;
+
+    let output = run_program_with_args(&[
+        &format!("--output={}", target.display()),
+        "compile",
+        source.to_str().unwrap(),
+    ])?;
+    assert!(output.status.success());
+
+    let output = run_program_with_args(&[target.to_str().unwrap()])?;
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "6\ndone\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_disasm_source_file() -> io::Result<()> {
+    let output = run_command_with_content("disasm", "print 1 + 2;")?;
+    let expected = r#"
+== <script> ==
+; line 1: print 1 + 2;
+0000    1 OP_CONSTANT           0 '1'
+0003    | OP_CONSTANT           1 '2'
+0006    | OP_ADD
+0007    | OP_PRINT
+0008    | OP_NIL
+0009    | OP_RETURN
+"#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_disasm_compiled_file_lists_nested_functions() -> io::Result<()> {
+    let dir = TempDir::new()?;
+    let compiled = compile_program(&dir)?;
+
+    let output = run_program_with_args(&["disasm", &compiled])?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("== <script> =="));
+    assert!(stdout.contains("== <fn counter> =="));
+    assert!(stdout.contains("== <fn next> =="));
+    assert!(stdout.contains("local 1"));
+
+    Ok(())
+}
+
+#[test]
+fn test_corrupt_compiled_file() -> io::Result<()> {
+    let dir = TempDir::new()?;
+    let compiled = compile_program(&dir)?;
+
+    let mut bytes = fs::read(&compiled)?;
+    let last = bytes.len() - 1;
+    bytes[last] ^= 0xff;
+    fs::write(&compiled, bytes)?;
+
+    let output = run_program_with_args(&["run", &compiled])?;
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "Compiled file is corrupt (checksum mismatch)."
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_compiled_file_with_other_version() -> io::Result<()> {
+    let dir = TempDir::new()?;
+    let compiled = compile_program(&dir)?;
+
+    let mut bytes = fs::read(&compiled)?;
+    bytes[4..6].copy_from_slice(&99u16.to_le_bytes());
+    fs::write(&compiled, bytes)?;
+
+    let output = run_program_with_args(&["run", &compiled])?;
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "Unsupported bytecode format version 99 (expected 7). Recompile the script from source."
+    );
+
+    Ok(())
+}
+
+/// Compiles `print 1;`, whose script is `OP_CONSTANT 0`, `OP_PRINT`,
+/// `OP_NIL` and `OP_RETURN`, replaces its code with `code` of the same
+/// length and updates the checksum, as a crafted file would.
+fn write_crafted_file(dir: &TempDir, code: &[u8; 6]) -> io::Result<String> {
+    let source = dir.path().join("crafted.lox");
+    fs::write(&source, "print 1;")?;
+    let output = run_program_with_args(&["compile", source.to_str().unwrap()])?;
+    assert!(output.status.success());
+
+    let compiled = dir.path().join("crafted.loxc");
+    let mut bytes = fs::read(&compiled)?;
+    // The header, then the script's name, arity, upvalue count, exports and
+    // code length, four bytes each.
+    let code_start = 14 + 4 * 5;
+    assert_eq!(&bytes[code_start..code_start + 6], &[0, 0, 0, 26, 1, 33]);
+    bytes[code_start..code_start + 6].copy_from_slice(code);
+    let checksum = crc32(&bytes[14..]);
+    bytes[6..10].copy_from_slice(&checksum.to_le_bytes());
+    fs::write(&compiled, bytes)?;
+    Ok(compiled.to_str().unwrap().to_string())
+}
+
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in bytes {
+        crc ^= byte as u32;
+        for _ in 0..8 {
+            let mask = (crc & 1).wrapping_neg();
+            crc = (crc >> 1) ^ (0xEDB8_8320 & mask);
+        }
+    }
+    !crc
+}
+
+#[test]
+fn test_compiled_file_with_invalid_bytecode() -> io::Result<()> {
+    let cases: [(&[u8; 6], &str); 4] = [
+        (
+            &[0, 0, 9, 26, 1, 33],
+            "in <script> at offset 0: constant 9 does not exist.",
+        ),
+        (
+            &[0, 0, 0, 26, 1, 0],
+            "in <script> at offset 5: OP_CONSTANT has a truncated operand.",
+        ),
+        (
+            &[27, 0, 200, 26, 1, 33],
+            "in <script> at offset 0: OP_JUMP jumps outside the instructions.",
+        ),
+        (
+            &[5, 0, 4, 26, 1, 33],
+            "in <script> at offset 0: local 4 does not exist.",
+        ),
+    ];
+
+    for (code, problem) in cases {
+        let dir = TempDir::new()?;
+        let compiled = write_crafted_file(&dir, code)?;
+        for command in ["run", "disasm"] {
+            let output = run_program_with_args(&[command, &compiled])?;
+            assert_eq!(output.status.code().unwrap(), 65);
+            assert_eq!(
+                String::from_utf8_lossy(&output.stderr).trim(),
+                format!("Compiled file has invalid bytecode {problem}")
+            );
+        }
+    }
+
+    Ok(())
+}
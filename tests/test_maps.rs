@@ -0,0 +1,136 @@
+use crate::fixtures::{
+    assert_error_on_all_backends, assert_output_on_all_backends, run_command_with_content,
+};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_literals_and_subscripts() -> io::Result<()> {
+    let content = r#"var m = {"a": 1, 2: "two", true: nil,};
+print m;
+print m["a"] + 1;
+m["a"] = 10;
+m[nil] = [1];
+m[-0] = "zero";
+print m[0];
+print m;
+print {};
+"#;
+    let expected = concat!(
+        "{\"a\": 1, 2: \"two\", true: nil}\n2\nzero\n",
+        "{\"a\": 10, 2: \"two\", true: nil, nil: [1], 0: \"zero\"}\n{}\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_methods() -> io::Result<()> {
+    let content = r#"var ages = {"ann": 31, "bob": 27};
+ages["cid"] = 40;
+print ages.keys();
+print ages.values();
+print ages.has("bob");
+print ages.has("dan");
+print ages.remove("bob");
+print ages.len();
+print ages;
+"#;
+    let expected = concat!(
+        "[\"ann\", \"bob\", \"cid\"]\n[31, 27, 40]\ntrue\nfalse\n27\n2\n",
+        "{\"ann\": 31, \"cid\": 40}\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_maps_are_shared_and_compared_by_content() -> io::Result<()> {
+    let content = r#"var a = {"x": [1]};
+var b = a;
+b["y"] = 2;
+print a;
+print a == {"y": 2, "x": [1]};
+print a == {"x": [1]};
+a["self"] = a;
+print a;
+"#;
+    let expected = "{\"x\": [1], \"y\": 2}\ntrue\nfalse\n{\"x\": [1], \"y\": 2, \"self\": {...}}\n";
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_comparing_maps_that_contain_each_other() -> io::Result<()> {
+    let content = r#"var a = {"n": 1};
+var b = {"n": 1};
+a["other"] = b;
+b["other"] = a;
+print a == b;
+var c = {"n": 1};
+c["other"] = [c];
+var d = {"n": 1};
+d["other"] = [d];
+print c == d;
+d["n"] = 2;
+print c == d;
+"#;
+    let expected = "true\ntrue\nfalse\n";
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_statement_braces_disambiguation() -> io::Result<()> {
+    let content = r#"{"a": 1}.len();
+{}
+{
+  var a = {"b": {}};
+  print a;
+}
+"#;
+
+    assert_output_on_all_backends(content, "{\"b\": {}}\n")
+}
+
+#[test]
+fn test_parse_map_literal() -> io::Result<()> {
+    let output = run_command_with_content("parse", r#"{"a": 1, 2: [3]}["a"]"#)?;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout),
+        "(index (map (a 1.0) (2.0 (list 3.0))) a)\n"
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_map_errors() -> io::Result<()> {
+    let not_hashable = "[line 1] Map keys must be strings, numbers, booleans or nil.";
+    let cases = [
+        (
+            "print {\"a\": 1}[\"b\"];",
+            70,
+            "[line 1] Undefined key \"b\".",
+        ),
+        ("var m = {}; m[[1]] = 2;", 70, not_hashable),
+        ("print {[1]: 2};", 70, not_hashable),
+        ("fun f() {} print {}.has(f);", 70, not_hashable),
+        ("var m = {}; m.remove(1);", 70, "[line 1] Undefined key 1."),
+        (
+            "var m = {}; m.size();",
+            70,
+            "[line 1] Undefined property 'size'.",
+        ),
+        ("print {\"a\" 1};", 65, "[line 1] Expect ':' after map key."),
+    ];
+
+    for (content, exit_code, expected) in cases {
+        assert_error_on_all_backends(content, exit_code, expected)?;
+    }
+
+    Ok(())
+}
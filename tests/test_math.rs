@@ -0,0 +1,61 @@
+use crate::fixtures::{assert_error_on_all_backends, assert_output_on_all_backends};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_math_functions_and_constants() -> io::Result<()> {
+    let content = r#"import "math" as math;
+from "math" import PI, E, max, min;
+print math.sqrt(16);
+print math.pow(2, 10);
+print math.abs(-3);
+print [math.floor(-1.5), math.ceil(1.2), math.round(2.5)];
+print [max(1, 7, 3), min(4), min(2, -1)];
+print PI;
+print E;
+print math.sin(0) + math.cos(0);
+print math.atan2(1, 1) * 4 == PI;
+print [math.log(E), math.exp(0)];
+print [math.is_nan(math.sqrt(-1)), math.is_finite(1 / 0), math.is_finite(1)];
+print math;
+try { math.abs([]); } catch (e) { print e; }
+"#;
+    let expected = concat!(
+        "4\n1024\n3\n[-2, 2, 3]\n[7, 4, -1]\n",
+        "3.141592653589793\n2.718281828459045\n1\ntrue\n[1, 1]\n",
+        "[true, false, true]\n<module math>\n",
+        "TypeError: Expected a number for argument 1 of 'abs', got list.\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_math_argument_errors() -> io::Result<()> {
+    let cases = [
+        (
+            "print math.sqrt(\"four\");",
+            "[line 3] Expected a number for argument 1 of 'sqrt', got string.",
+        ),
+        (
+            "print math.pow(2);",
+            "[line 3] Expected 2 arguments, but got 1.",
+        ),
+        (
+            "print math.max();",
+            "[line 3] Expected at least 1 argument, but got 0.",
+        ),
+        (
+            "print math.min(1, nil);",
+            "[line 3] Expected a number for argument 2 of 'min', got nil.",
+        ),
+    ];
+
+    for (line, expected) in cases {
+        let content = format!("import \"math\" as math;\n\n{line}\n");
+        assert_error_on_all_backends(&content, 70, expected)?;
+    }
+
+    Ok(())
+}
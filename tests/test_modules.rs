@@ -0,0 +1,187 @@
+use crate::fixtures::{run_program_with_files, BACKENDS};
+use std::io;
+
+mod fixtures;
+
+const GEOMETRY: &str = r#"print "loading geometry";
+export var unit = 2;
+export fun area(r) { return unit * r * r; }
+export class Point {
+  init(x) { this.x = x; }
+}
+var secret = "hidden";
+fun reveal() { return secret; }
+export fun callReveal() { return reveal(); }
+"#;
+
+#[test]
+fn test_import_module_as_name() -> io::Result<()> {
+    let content = r#"import "geometry.lox" as geo;
+print geo.unit;
+print geo.area(3);
+print geo.Point(5).x;
+print geo.callReveal();
+print geo;
+var secret = "main";
+print secret;
+"#;
+    let expected = concat!(
+        "loading geometry\n",
+        "2\n18\n5\nhidden\n",
+        "<module geometry.lox>\n",
+        "main\n",
+    );
+
+    for backend in BACKENDS {
+        let files = [("geometry.lox", GEOMETRY)];
+        let args = [&*format!("--backend={backend}")];
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+
+        assert!(output.status.success(), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            expected,
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_from_import_runs_module_once() -> io::Result<()> {
+    let content = r#"from "geometry.lox" import area, unit;
+import "geometry.lox" as geo;
+fun local() {
+  from "geometry.lox" import Point;
+  return Point(unit).x;
+}
+print area(1);
+print local();
+try { print geo.secret; } catch (e) { print e; }
+"#;
+    let expected = concat!(
+        "loading geometry\n",
+        "2\n2\n",
+        "PropertyError: Module \"geometry.lox\" does not export 'secret'.\n",
+    );
+
+    for backend in BACKENDS {
+        let files = [("geometry.lox", GEOMETRY)];
+        let args = [&*format!("--backend={backend}")];
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+
+        assert!(output.status.success(), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            expected,
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_modules_found_on_lox_path() -> io::Result<()> {
+    let content = r#"from "strings.lox" import greet;
+print greet("lox");
+"#;
+    let files = [
+        (
+            "lib/strings.lox",
+            "export fun greet(name) { return \"hi \" + name; }",
+        ),
+        ("lib/other.lox", ""),
+    ];
+
+    for backend in BACKENDS {
+        let args = [&*format!("--backend={backend}")];
+        let output =
+            run_program_with_files(content, &files, &args, &[], &[("LOX_PATH", "lib")], None)?;
+
+        assert!(output.status.success(), "{backend}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "hi lox\n");
+
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code().unwrap(), 65, "{backend}");
+        assert_eq!(
+            stderr.lines().next(),
+            Some("[line 1] Cannot find module \"strings.lox\"."),
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_module_errors() -> io::Result<()> {
+    let cases = [
+        (
+            r#"import "a.lox" as a;"#,
+            "[line 1] Import cycle: a.lox -> b.lox -> a.lox.",
+        ),
+        (
+            r#"from "geometry.lox" import secret;"#,
+            "[line 1] Module \"geometry.lox\" does not export 'secret'.",
+        ),
+        (
+            "{ export var x = 1; }",
+            "[line 1] Can only export top-level declarations.",
+        ),
+        (
+            r#"import "geometry.lox";"#,
+            "[line 1] Expect 'as' after module path.",
+        ),
+    ];
+    let files = [
+        ("a.lox", r#"import "b.lox" as b;"#),
+        ("b.lox", r#"import "a.lox" as a;"#),
+        ("geometry.lox", GEOMETRY),
+    ];
+
+    for backend in BACKENDS {
+        let args = [&*format!("--backend={backend}")];
+        for (content, expected) in cases {
+            let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+            let stderr = String::from_utf8_lossy(&output.stderr);
+
+            assert_eq!(output.status.code().unwrap(), 65, "{backend}: {content}");
+            assert_eq!(
+                stderr.lines().next(),
+                Some(expected),
+                "{backend}: {content}"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_runtime_error_in_module() -> io::Result<()> {
+    let content = "\nimport \"broken.lox\" as broken;\n";
+    let files = [("broken.lox", "var x = 1;\nprint x + nil;\n")];
+
+    for backend in BACKENDS {
+        let args = [&*format!("--backend={backend}"), "--error-format=short"];
+        let output = run_program_with_files(content, &files, &args, &[], &[], None)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code().unwrap(), 70, "{backend}");
+        assert_eq!(
+            stderr.lines().collect::<Vec<_>>(),
+            [
+                "[line 2] Not a number or string for plus operation",
+                "[line 2] in module broken.lox",
+                "[line 2] in script",
+            ],
+            "{backend}"
+        );
+    }
+
+    Ok(())
+}
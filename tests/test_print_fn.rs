@@ -13,9 +13,9 @@ fn test_print_func() -> io::Result<()> {
     let output = interpret_temp_file_with_content(content)?;
     let expected_stdout = "Hello, tests!\n";
 
-    assert_eq!(output.status.success(), true);
+    assert!(output.status.success());
     assert_eq!(String::from_utf8_lossy(&output.stdout), expected_stdout);
-    assert_eq!(output.stderr.is_empty(), true);
+    assert!(output.stderr.is_empty());
 
     Ok(())
 }
@@ -0,0 +1,85 @@
+use crate::fixtures::{run_program_with_args, run_program_with_files, BACKENDS};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_args_and_getenv() -> io::Result<()> {
+    let content = r#"print args;
+print getenv("LOX_GREETING");
+print getenv("LOX_UNSET_VARIABLE");
+"#;
+    let envs = [("LOX_GREETING", "hello")];
+
+    for backend in BACKENDS {
+        let backend_arg = format!("--backend={backend}");
+        let script_args = ["one", "--two", "run"];
+        let output =
+            run_program_with_files(content, &[], &[&backend_arg], &script_args, &envs, None)?;
+
+        assert!(output.status.success(), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "[\"one\", \"--two\", \"run\"]\nhello\nnil\n",
+            "{backend}"
+        );
+
+        let output =
+            run_program_with_files(content, &[], &["run", &backend_arg], &[], &envs, None)?;
+        assert!(output.status.success(), "{backend}");
+        assert!(String::from_utf8_lossy(&output.stdout).starts_with("[]\n"));
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_exit_sets_the_status() -> io::Result<()> {
+    let content = r#"fun check(code) {
+  print "checking";
+  try {
+    if (code > 0) { exit(code); }
+  } catch (e) {
+    print "caught";
+  } finally {
+    print "finally";
+  }
+  print "done";
+}
+if (args[0] == "42") { check(42); } else { check(0); }
+"#;
+
+    for backend in BACKENDS {
+        let backend_arg = format!("--backend={backend}");
+        let output = run_program_with_files(content, &[], &[&backend_arg], &["42"], &[], None)?;
+
+        assert_eq!(output.status.code(), Some(42), "{backend}");
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "checking\n");
+        assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+
+        let output = run_program_with_files(content, &[], &[&backend_arg], &["0"], &[], None)?;
+        assert_eq!(output.status.code(), Some(0), "{backend}");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stdout),
+            "checking\nfinally\ndone\n"
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_unknown_commands_are_usage_errors() -> io::Result<()> {
+    for args in [
+        &["tokenze", "main.lox"][..],
+        &["tokenze"],
+        &["runn", "main.lox", "x"],
+    ] {
+        let output = run_program_with_args(args)?;
+        let stderr = String::from_utf8_lossy(&output.stderr);
+
+        assert_eq!(output.status.code(), Some(64), "{args:?}");
+        assert!(stderr.starts_with("Usage:"), "{args:?}: {stderr}");
+    }
+    Ok(())
+}
@@ -0,0 +1,117 @@
+use crate::fixtures::run_repl_with_input;
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_repl_keeps_globals_and_echoes_expressions() -> io::Result<()> {
+    let input = r#"
+var a = 20;
+fun double(n) {
+    return n * 2;
+}
+double(a) + 2;
+print "done";
+"#;
+    let output = run_repl_with_input(input.trim_start())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert!(stdout.contains("42\n"));
+    assert!(stdout.contains("done\n"));
+    assert!(output.stderr.is_empty());
+
+    Ok(())
+}
+
+#[test]
+fn test_repl_reports_errors_and_continues() -> io::Result<()> {
+    let input = r#"
+print missing;
+var value = "still alive";
+print value;
+"#;
+    let output = run_repl_with_input(input.trim_start())?;
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("still alive"));
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        concat!(
+            "[line 1] Undefined variable 'missing'.\n",
+            " --> <repl-1>:1:7\n",
+            "  |\n",
+            "1 | print missing;\n",
+            "  |       ^^^^^^^",
+        )
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_repl_reset_and_env_commands() -> io::Result<()> {
+    let input = r#"
+var kept = 1;
+:env
+:reset
+:env
+:quit
+print "never";
+"#;
+    let output = run_repl_with_input(input.trim_start())?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert_eq!(stdout.matches("kept=1").count(), 1);
+    assert_eq!(stdout.matches("clock=<fn clock>").count(), 2);
+    assert!(!stdout.contains("never"));
+
+    Ok(())
+}
+
+#[test]
+fn test_repl_reports_errors_in_earlier_inputs_and_reads_open_brackets() -> io::Result<()> {
+    let input = r#"
+fun fail() {
+  return nil + 1;
+}
+var xs = [
+  1,
+  2
+];
+print xs;
+fail();
+"#;
+    let output = run_repl_with_input(input.trim_start())?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+
+    assert!(output.status.success());
+    assert!(String::from_utf8_lossy(&output.stdout).contains("[1, 2]"));
+    assert!(stderr.contains(" --> <repl-1>:2:14\n"));
+    assert!(stderr.contains("2 |   return nil + 1;\n"));
+
+    Ok(())
+}
+
+#[test]
+fn test_repl_reads_quotes_and_braces_inside_interpolations() -> io::Result<()> {
+    let input = r#"print "a ${ "{" } b";
+print "c ${ "}" + "\"" } d";
+print "e ${
+  "f"
+} g";
+"#;
+    let output = run_repl_with_input(input)?;
+    let stdout = String::from_utf8_lossy(&output.stdout);
+
+    assert!(output.status.success());
+    assert_eq!(
+        stdout,
+        "> a { b\n> c }\" d\n> ... ... e f g\n> ",
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    Ok(())
+}
@@ -0,0 +1,80 @@
+use crate::fixtures::interpret_temp_file_with_content;
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_closure_keeps_resolved_variable_success() -> io::Result<()> {
+    let content = r#"
+    var a = "global";
+    {
+        fun showA() {
+            print a;
+        }
+
+        showA();
+        var a = "block";
+        showA();
+    }
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+global
+global
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_static_errors() -> io::Result<()> {
+    let content = r#"
+    return 1;
+    {
+        var a = a;
+    }
+    {
+        var b = 1;
+        var b = 2;
+    }
+    print this;
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+    let expected = r#"
+[line 1] Can't return from top-level code.
+ --> main.lox:1:1
+  |
+1 | return 1;
+  | ^^^^^^
+[line 3] Can't read local variable in its own initializer.
+ --> main.lox:3:17
+  |
+3 |         var a = a;
+  |                 ^
+[line 7] Already a variable with this name in this scope.
+ --> main.lox:7:13
+  |
+7 |         var b = 2;
+  |             ^
+[line 9] Can't use 'this' outside of a class.
+ --> main.lox:9:11
+  |
+9 |     print this;
+  |           ^^^^
+    "#;
+
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
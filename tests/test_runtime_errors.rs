@@ -0,0 +1,113 @@
+use crate::fixtures::{
+    interpret_temp_file_with_backend, interpret_temp_file_with_content, BACKENDS,
+};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_error_stops_the_program() -> io::Result<()> {
+    let content = r#"
+    print "first";
+    print -"second";
+    print "third";
+    "#;
+    let output = interpret_temp_file_with_content(content.trim())?;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "first\n");
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        r#"
+[line 2] Not a number for MINUS operation.
+ --> main.lox:2:11
+  |
+2 |     print -"second";
+  |           ^
+        "#
+        .trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_error_in_function_unwinds_with_stack_trace() -> io::Result<()> {
+    let content = r#"
+fun inner(x) {
+  return x + "a";
+}
+fun outer() {
+  print "before";
+  inner(1);
+  print "unreachable";
+}
+class Foo {
+  init() {
+    outer();
+  }
+}
+Foo();
+print "after";
+"#;
+    let expected = r#"
+[line 2] Not a number or string for plus operation
+ --> main.lox:2:12
+  |
+2 |   return x + "a";
+  |            ^
+[line 2] in inner()
+[line 6] in outer()
+[line 11] in init()
+[line 14] in script
+"#;
+
+    for backend in BACKENDS {
+        let output = interpret_temp_file_with_backend(content.trim(), backend)?;
+
+        assert_eq!(output.status.code().unwrap(), 70);
+        assert_eq!(String::from_utf8_lossy(&output.stdout), "before\n");
+        assert_eq!(
+            String::from_utf8_lossy(&output.stderr).trim(),
+            expected.trim()
+        );
+    }
+
+    Ok(())
+}
+
+#[test]
+fn test_error_in_method_called_from_function() -> io::Result<()> {
+    let content = r#"
+class Counter {
+  bump() {
+    return this.count + 1;
+  }
+}
+fun run(counter) {
+  return counter.bump();
+}
+print run(Counter());
+"#;
+    let expected = r#"
+[line 3] Undefined property 'count'.
+ --> main.lox:3:17
+  |
+3 |     return this.count + 1;
+  |                 ^^^^^
+[line 3] in bump()
+[line 7] in run()
+[line 9] in script
+"#;
+
+    let output = interpret_temp_file_with_content(content.trim())?;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert!(output.stdout.is_empty());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
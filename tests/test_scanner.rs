@@ -0,0 +1,107 @@
+use crate::fixtures::{
+    interpret_temp_file_with_content, run_command_with_content, run_repl_with_input,
+};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_multi_line_string_keeps_line_numbers() -> io::Result<()> {
+    let content = r#"var poem = "roses
+are red";
+print poem;
+print -poem;
+"#;
+    let output = interpret_temp_file_with_content(content)?;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "roses\nare red\n");
+    assert!(String::from_utf8_lossy(&output.stderr)
+        .starts_with("[line 4] Not a number for MINUS operation."));
+
+    Ok(())
+}
+
+#[test]
+fn test_nested_block_comments() -> io::Result<()> {
+    let content = r#"/* a comment /* with a nested
+   comment */ still commented out
+print "hidden"; */
+print "visible"; /* trailing */ print 1 /* inline */ + 2;
+"#;
+    let output = interpret_temp_file_with_content(content)?;
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), "visible\n3\n");
+
+    Ok(())
+}
+
+#[test]
+fn test_escape_sequences() -> io::Result<()> {
+    let content = r#"print "tab\there \"quoted\" back\\slash";
+print "caf\u{e9} \u{1F600}";
+print "two\nlines";
+"#;
+    let output = interpret_temp_file_with_content(content)?;
+    let expected = "tab\there \"quoted\" back\\slash\ncafé 😀\ntwo\nlines\n";
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_tokenize_keeps_the_source_lexeme() -> io::Result<()> {
+    let content = "\"a\\tb\nc\" 7.";
+    let output = run_command_with_content("tokenize", content)?;
+    let expected = "STRING \"a\\tb\nc\" a\tb\nc\nNUMBER 7 7.0\nDOT . null\nEOF  null\n";
+
+    assert!(output.status.success());
+    assert_eq!(String::from_utf8_lossy(&output.stdout), expected);
+
+    Ok(())
+}
+
+#[test]
+fn test_invalid_escapes_and_unterminated_comment() -> io::Result<()> {
+    let content = r#"print "\q \u{110000} \u{zz}";
+/* never
+closed"#;
+    let output = run_command_with_content("tokenize", content)?;
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let errors: Vec<&str> = stderr.lines().filter(|l| l.starts_with('[')).collect();
+
+    assert_eq!(output.status.code().unwrap(), 65);
+    assert_eq!(
+        errors,
+        [
+            r"[line 1] Error: Invalid escape sequence: \q",
+            r"[line 1] Error: Invalid escape sequence: \u{110000}",
+            r"[line 1] Error: Invalid escape sequence: \u{",
+            "[line 2] Error: Unterminated block comment.",
+        ]
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_repl_continues_open_strings_and_comments() -> io::Result<()> {
+    let input = r#"
+print "first
+second";
+/* still
+commenting */ print "done";
+"#;
+    let output = run_repl_with_input(input.trim_start())?;
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("first\nsecond\n"));
+    assert!(stdout.contains("done\n"));
+    assert_eq!(String::from_utf8_lossy(&output.stderr), "");
+
+    Ok(())
+}
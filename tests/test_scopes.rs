@@ -39,12 +39,12 @@ global b
 global c
     "#;
 
-    assert_eq!(output.status.success(), true);
+    assert!(output.status.success());
     assert_eq!(
         String::from_utf8_lossy(&output.stdout).trim(),
         expected_stdout.trim()
     );
-    assert_eq!(output.stderr.is_empty(), true);
+    assert!(output.stderr.is_empty());
 
     Ok(())
 }
@@ -66,7 +66,10 @@ fn test_error_after_local_scope() -> io::Result<()> {
     let output = interpret_temp_file_with_content(content.trim())?;
     let expected = r#"
 [line 8] Undefined variable 'calculated'.
-[line 9] Undefined variable 'is_true'.
+ --> main.lox:8:11
+  |
+8 |     print calculated;
+  |           ^^^^^^^^^^
     "#;
 
     assert_eq!(output.status.code().unwrap(), 70);
@@ -0,0 +1,96 @@
+use crate::fixtures::{assert_error_on_all_backends, assert_output_on_all_backends};
+use std::io;
+
+mod fixtures;
+
+#[test]
+fn test_string_methods() -> io::Result<()> {
+    let content = r#"var s = "héllo wörld";
+print s.len();
+print s.upper() + " " + "ÀB".lower();
+print "  x ".trim() + "|";
+print s.split(" ");
+print "ab".split("");
+print ", ".join(["a", 1, nil]);
+print s.replace("ö", "o");
+print [s.contains("wö"), s.starts_with("hé"), s.ends_with("x")];
+print [s.find("w"), s.find("z")];
+print s.substring(1, 4) + "|" + s.substring(-5, nil);
+print "ab".repeat(3);
+print s.upper;
+"#;
+    let expected = concat!(
+        "11\nHÉLLO WÖRLD àb\nx|\n[\"héllo\", \"wörld\"]\n[\"a\", \"b\"]\n",
+        "a, 1, nil\nhéllo world\n[true, true, false]\n[6, nil]\n",
+        "éll|wörld\nababab\n<fn upper>\n",
+    );
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_string_indexing_and_module() -> io::Result<()> {
+    let content = r#"var s = "日本語です";
+print s[0] + s[-1];
+print s[1:3];
+print s[:-2];
+import "string" as string;
+from "string" import join;
+print string.len(s);
+print string.upper("abc");
+print join("-", ["x", "y"]);
+"#;
+    let expected = "日す\n本語\n日本語\n5\nABC\nx-y\n";
+
+    assert_output_on_all_backends(content, expected)
+}
+
+#[test]
+fn test_string_errors() -> io::Result<()> {
+    let cases = [
+        ("print \"ab\"[2];", "[line 1] String index out of range."),
+        (
+            "print \"ab\"[0.5];",
+            "[line 1] String index must be an integer.",
+        ),
+        (
+            "var s = \"ab\"; s[0] = \"c\";",
+            "[line 1] Strings cannot be modified.",
+        ),
+        ("print \"ab\".size;", "[line 1] Undefined property 'size'."),
+        (
+            "print \"ab\".repeat(\"x\");",
+            "[line 1] Expected an integer for argument 1 of 'repeat', got string.",
+        ),
+        (
+            "print \"ab\".repeat(-1);",
+            "[line 1] Repeat count must not be negative.",
+        ),
+        (
+            "print \"ab\".repeat(100000000000);",
+            "[line 1] Repeated string is too long.",
+        ),
+        (
+            "print \"ab\".repeat(100000000000000000000);",
+            "[line 1] Repeated string is too long.",
+        ),
+        (
+            "print \"ab\".repeat(1.5);",
+            "[line 1] Expected an integer for argument 1 of 'repeat', got number.",
+        ),
+        (
+            "print \"ab\".split();",
+            "[line 1] Expected 1 argument, but got 0.",
+        ),
+        (
+            "import \"string\" as string; print string.trim(1);",
+            "[line 1] Expected a string for argument 1 of 'trim', got number.",
+        ),
+    ];
+
+    for (content, expected) in cases {
+        assert_error_on_all_backends(content, 70, expected)?;
+    }
+
+    Ok(())
+}
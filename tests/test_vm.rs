@@ -0,0 +1,139 @@
+use crate::fixtures::interpret_temp_file_with_backend;
+use std::io;
+
+mod fixtures;
+
+fn assert_same_output_on_both_backends(content: &str) -> io::Result<()> {
+    let tree = interpret_temp_file_with_backend(content, "tree")?;
+    let vm = interpret_temp_file_with_backend(content, "vm")?;
+
+    assert_eq!(
+        String::from_utf8_lossy(&tree.stdout),
+        String::from_utf8_lossy(&vm.stdout)
+    );
+    assert_eq!(
+        String::from_utf8_lossy(&tree.stderr),
+        String::from_utf8_lossy(&vm.stderr)
+    );
+    assert_eq!(tree.status.code(), vm.status.code());
+
+    Ok(())
+}
+
+#[test]
+fn test_vm_closures_success() -> io::Result<()> {
+    let content = r#"
+    fun outer() {
+        var x = "outside";
+        fun middle() {
+            fun inner() {
+                print x;
+                x = x + "!";
+            }
+            return inner;
+        }
+        return middle();
+    }
+
+    var f = outer();
+    f();
+    f();
+
+    for (var i = 0; i < 3; i = i + 1) {
+        fun show() {
+            print i;
+        }
+        show();
+    }
+    "#;
+    let output = interpret_temp_file_with_backend(content.trim(), "vm")?;
+    let expected = r#"
+outside
+outside!
+0
+1
+2
+    "#;
+
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8_lossy(&output.stdout).trim(),
+        expected.trim()
+    );
+
+    Ok(())
+}
+
+#[test]
+fn test_vm_matches_tree_walker_on_classes() -> io::Result<()> {
+    let content = r#"
+    class A {
+        init(name) {
+            this.name = name;
+        }
+
+        describe() {
+            return "A " + this.name;
+        }
+    }
+
+    class B < A {
+        describe() {
+            fun inner() {
+                return super.describe() + " via B";
+            }
+            return inner();
+        }
+    }
+
+    var b = B("b");
+    print b.describe();
+    print b;
+    print B;
+    print b.describe;
+    print clock;
+    "#;
+
+    assert_same_output_on_both_backends(content.trim())
+}
+
+#[test]
+fn test_vm_matches_tree_walker_on_runtime_errors() -> io::Result<()> {
+    let content = r#"
+    fun broken() {
+        print "before";
+        print 1 + "one";
+        print "after";
+    }
+
+    broken();
+    print missing;
+    print -"text";
+    print "still running";
+    "#;
+
+    assert_same_output_on_both_backends(content.trim())
+}
+
+#[test]
+fn test_vm_arity_error() -> io::Result<()> {
+    let content = r#"
+    fun add(a, b) {
+        return a + b;
+    }
+    print add(1);
+    "#;
+    let output = interpret_temp_file_with_backend(content.trim(), "vm")?;
+
+    assert_eq!(output.status.code().unwrap(), 70);
+    assert_eq!(
+        String::from_utf8_lossy(&output.stderr).trim(),
+        "[line 4] Expected 2 arguments, but got 1.
+ --> main.lox:4:11
+  |
+4 |     print add(1);
+  |           ^^^^^^"
+    );
+
+    Ok(())
+}